@@ -7,8 +7,8 @@
 #![allow(dead_code)]
 
 use egg_mode;
+use egg_mode::auth::store::{self, JsonFileStore};
 use std;
-use std::io::{Read, Write};
 
 pub use yansi::Paint;
 
@@ -23,18 +23,6 @@ pub struct Config {
 
 impl Config {
     pub async fn load() -> Self {
-        let a1 = Config::load_inner().await;
-        if let Some(conf) = a1 {
-            return conf;
-        }
-
-        Config::load_inner().await.unwrap()
-    }
-
-    /// This needs to be a separate function so we can retry after creating the
-    /// twitter_settings file. Idealy we would recurse, but that requires boxing
-    /// the output which doesn't seem worthwhile
-    async fn load_inner() -> Option<Self> {
         //IMPORTANT: make an app for yourself at apps.twitter.com and get your
         //key/secret into these files; these examples won't work without them
         let consumer_key = include_str!("consumer_key").trim();
@@ -42,37 +30,13 @@ impl Config {
 
         let con_token = egg_mode::KeyPair::new(consumer_key, consumer_secret);
 
-        let mut config = String::new();
-        let user_id: u64;
-        let username: String;
-        let token: egg_mode::Token;
-
-        //look at all this unwrapping! who told you it was my birthday?
-        if let Ok(mut f) = std::fs::File::open("twitter_settings") {
-            f.read_to_string(&mut config).unwrap();
-
-            let mut iter = config.split('\n');
-
-            username = iter.next().unwrap().to_string();
-            user_id = u64::from_str_radix(&iter.next().unwrap(), 10).unwrap();
-            let access_token = egg_mode::KeyPair::new(
-                iter.next().unwrap().to_string(),
-                iter.next().unwrap().to_string(),
-            );
-            token = egg_mode::Token::Access {
-                consumer: con_token,
-                access: access_token,
-            };
-
-            if let Err(err) = egg_mode::auth::verify_tokens(&token).await {
-                println!("We've hit an error using your old tokens: {:?}", err);
-                println!("We'll have to reauthenticate before continuing.");
-                std::fs::remove_file("twitter_settings").unwrap();
-            } else {
-                println!("Welcome back, {}!\n", username);
-            }
-        } else {
-            let request_token = egg_mode::auth::request_token(&con_token, "oob").await.unwrap();
+        //the token is saved in twitter_settings.json, and checked with twitter each time an
+        //example starts. if there isn't one yet, or twitter rejects it, we sign in again
+        let store = JsonFileStore::new("twitter_settings.json");
+        let mut signed_in = false;
+        let saved = store::load_verified(&store, "default", || async {
+            signed_in = true;
+            let request_token = egg_mode::auth::request_token(&con_token, "oob").await?;
 
             println!("Go to the following URL, sign in, and give me the PIN that comes back:");
             println!("{}", egg_mode::auth::authorize_url(&request_token));
@@ -81,45 +45,24 @@ impl Config {
             std::io::stdin().read_line(&mut pin).unwrap();
             println!("");
 
-            let tok_result = egg_mode::auth::access_token(con_token, &request_token, pin)
-                .await
-                .unwrap();
-
-            token = tok_result.0;
-            user_id = tok_result.1;
-            username = tok_result.2;
-
-            match token {
-                egg_mode::Token::Access {
-                    access: ref access_token,
-                    ..
-                } => {
-                    config.push_str(&username);
-                    config.push('\n');
-                    config.push_str(&format!("{}", user_id));
-                    config.push('\n');
-                    config.push_str(&access_token.key);
-                    config.push('\n');
-                    config.push_str(&access_token.secret);
-                }
-                _ => unreachable!(),
-            }
-
-            let mut f = std::fs::File::create("twitter_settings").unwrap();
-            f.write_all(config.as_bytes()).unwrap();
+            let (token, _, _) =
+                egg_mode::auth::access_token(con_token.clone(), &request_token, pin).await?;
+            Ok(token)
+        })
+        .await
+        .unwrap();
 
-            println!("Welcome, {}, let's get this show on the road!", username);
+        let screen_name = saved.screen_name.unwrap();
+        if signed_in {
+            println!("Welcome, {}, let's get this show on the road!", screen_name);
+        } else {
+            println!("Welcome back, {}!\n", screen_name);
         }
 
-        //TODO: Is there a better way to query whether a file exists?
-        if std::fs::metadata("twitter_settings").is_ok() {
-            Some(Config {
-                token: token,
-                user_id: user_id,
-                screen_name: username,
-            })
-        } else {
-            None
+        Config {
+            token: saved.token,
+            user_id: saved.user_id.unwrap(),
+            screen_name,
         }
     }
 }
@@ -0,0 +1,115 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::auth::AccessLevel;
+use crate::error::Result;
+use crate::links;
+
+use super::*;
+
+/// Builds the URL for a path under the given environment.
+fn env_link(env_name: &str, path: &str) -> String {
+    format!(
+        "{}/{}/{}",
+        links::activity::ALL,
+        percent_encode(env_name),
+        path
+    )
+}
+
+/// Registers a new webhook URL for the given environment.
+///
+/// Twitter sends a Challenge-Response Check to the URL before registering it, so the server
+/// behind it needs to be running and able to answer the check. This call must be made with the
+/// app's owner as the authenticated user.
+pub async fn register_webhook(
+    env_name: &str,
+    url: &str,
+    token: impl Into<Client>,
+) -> Result<Response<Webhook>> {
+    let client: Client = token.into();
+    let params = ParamList::new().add_param("url", url.to_string());
+    let req = client.request_post(&env_link(env_name, "webhooks.json"), Some(&params));
+    client.response_json(req).await
+}
+
+/// Loads the webhooks registered for the given environment.
+///
+/// This can be called with either a user token or a bearer token for the app.
+pub async fn webhooks(env_name: &str, token: impl Into<Client>) -> Result<Response<Vec<Webhook>>> {
+    let client: Client = token.into();
+    let req = client.request_get(&env_link(env_name, "webhooks.json"), None);
+    client.response_json(req).await
+}
+
+/// Removes the given webhook from the given environment.
+///
+/// Once a webhook is removed, Twitter stops sending events for the environment's subscriptions
+/// until a new webhook is registered. The subscriptions themselves are kept.
+pub async fn delete_webhook(
+    env_name: &str,
+    webhook_id: u64,
+    token: impl Into<Client>,
+) -> Result<Response<()>> {
+    let client: Client = token.into();
+    let path = format!("webhooks/{}.json", webhook_id);
+    let req = client.request_delete(&env_link(env_name, &path), None);
+    client.response_empty(req).await
+}
+
+/// Asks Twitter to send a new Challenge-Response Check to the given webhook.
+///
+/// If the check succeeds, the webhook is marked valid again and events start being sent to it.
+/// Twitter only returns an error here if the check fails.
+pub async fn revalidate_webhook(
+    env_name: &str,
+    webhook_id: u64,
+    token: impl Into<Client>,
+) -> Result<Response<()>> {
+    let client: Client = token.into();
+    let path = format!("webhooks/{}.json", webhook_id);
+    let req = client.request_put(&env_link(env_name, &path), None);
+    client.response_empty(req).await
+}
+
+/// Subscribes the authenticated user to the given environment, so their events are sent to its
+/// webhook.
+///
+/// The token must have access to the user's Direct Messages, since those are sent along with
+/// everything else.
+pub async fn subscribe(env_name: &str, token: impl Into<Client>) -> Result<Response<()>> {
+    let client: Client = token.into();
+    client.require_access(AccessLevel::ReadWriteDirectMessages)?;
+    let req = client.request_post(&env_link(env_name, "subscriptions.json"), None);
+    client.response_empty(req).await
+}
+
+/// Checks whether the authenticated user is subscribed to the given environment.
+///
+/// Twitter answers this with an error when the user isn't subscribed, so this returns `false`
+/// for errors where `is_not_found` is true, and passes other errors along.
+pub async fn is_subscribed(env_name: &str, token: impl Into<Client>) -> Result<bool> {
+    let client: Client = token.into();
+    let req = client.request_get(&env_link(env_name, "subscriptions.json"), None);
+    match client.response_empty(req).await {
+        Ok(_) => Ok(true),
+        Err(e) if e.is_not_found() => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Unsubscribes the given user from the given environment, so their events are no longer sent
+/// to its webhook.
+///
+/// Twitter only accepts this call with a bearer token for the app, from `auth::bearer_token`.
+pub async fn unsubscribe(
+    env_name: &str,
+    user_id: u64,
+    token: impl Into<Client>,
+) -> Result<Response<()>> {
+    let client: Client = token.into();
+    let path = format!("subscriptions/{}.json", user_id);
+    let req = client.request_delete(&env_link(env_name, &path), None);
+    client.response_empty(req).await
+}
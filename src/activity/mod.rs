@@ -0,0 +1,523 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Structs and methods for receiving account events through the Account Activity API.
+//!
+//! Rather than polling for new tweets, likes, follows, or Direct Messages, the Account Activity
+//! API sends them to a webhook: a URL on your own server that Twitter makes POST requests to
+//! whenever something happens on an account that has subscribed to your app. Webhooks and
+//! subscriptions are set up per "environment", which you create on the developer portal and refer
+//! to by its label, given here as `env_name`.
+//!
+//! Before a webhook can be registered, and every so often afterward, Twitter sends it a
+//! Challenge-Response Check (CRC) to make sure you own it. Your server will need to answer these
+//! checks for `register_webhook` to succeed. Every event Twitter posts to the webhook is also
+//! signed, and should be checked before it's trusted. The `webhook_crc_response` and
+//! `verify_webhook_signature` methods on your consumer `KeyPair` handle both of these, and
+//! `WebhookService` puts them together into a hyper `Service` that gives verified events to a
+//! callback.
+//!
+//! ## Types
+//!
+//! * `Webhook`: Information about a registered webhook, returned by `register_webhook` and
+//!   `webhooks`.
+//! * `WebhookPayload`: The body of a request Twitter sends to your webhook. Its `event` field
+//!   holds an `ActivityEvent` describing what happened, using the types from the rest of egg-mode
+//!   where possible.
+//! * `WebhookService`: A hyper `Service` that answers CRCs and checks the signatures of incoming
+//!   events before handing them off.
+//!
+//! ## Functions
+//!
+//! * `register_webhook`/`webhooks`/`delete_webhook`: These let you set up and take down the
+//!   webhooks for an environment.
+//! * `revalidate_webhook`: This asks Twitter to send a new CRC to a webhook, to mark it valid
+//!   again after it failed one.
+//! * `subscribe`/`is_subscribed`/`unsubscribe`: These start and stop sending the authenticated
+//!   user's events to the environment's webhook.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # fn main() -> Result<(), serde_json::Error> {
+//! use egg_mode::activity::{ActivityEvent, WebhookPayload};
+//!
+//! // the body of a POST request Twitter sent to your webhook
+//! # let body: &[u8] = b"";
+//! let payload: WebhookPayload = serde_json::from_slice(body)?;
+//!
+//! match payload.event {
+//!     ActivityEvent::TweetCreate(tweets) => {
+//!         for tweet in tweets {
+//!             println!("{} tweeted: {}", payload.for_user_id, tweet.text);
+//!         }
+//!     }
+//!     ActivityEvent::DirectMessage(messages) => {
+//!         for dm in messages {
+//!             println!("DM from {}: {}", dm.sender_id, dm.text);
+//!         }
+//!     }
+//!     _ => (),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+use chrono::{self, TimeZone};
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+use crate::common::*;
+use crate::direct::{self, DirectMessage};
+use crate::tweet::{Tweet, TweetSource};
+use crate::user::TwitterUser;
+
+mod fun;
+mod webhook;
+
+pub use self::fun::*;
+pub use self::webhook::*;
+
+/// A webhook registered with the Account Activity API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Webhook {
+    /// Numeric ID for this webhook.
+    #[serde(with = "serde_via_string")]
+    pub id: u64,
+    /// The URL Twitter sends events to.
+    pub url: String,
+    /// Whether the webhook passed its latest Challenge-Response Check. Twitter doesn't send events
+    /// to webhooks that aren't valid; use `revalidate_webhook` to check them again.
+    pub valid: bool,
+    /// UTC timestamp from when this webhook was registered.
+    #[serde(
+        rename = "created_timestamp",
+        deserialize_with = "deserialize_webhook_time"
+    )]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The body of a request Twitter sends to a webhook.
+///
+/// Twitter sends one kind of event per request, though there may be several events of that kind.
+/// Payloads can be deserialized directly from the request body with `serde_json`.
+#[derive(Debug, Deserialize)]
+#[serde(from = "RawPayload")]
+pub struct WebhookPayload {
+    /// The ID of the subscribed user these events are for.
+    pub for_user_id: u64,
+    /// For `TweetCreate` events that mention the subscribed user, whether they have blocked the
+    /// author.
+    pub user_has_blocked: Option<bool>,
+    /// The events that happened.
+    pub event: ActivityEvent,
+}
+
+/// The events delivered in a `WebhookPayload`.
+#[derive(Debug)]
+pub enum ActivityEvent {
+    /// Tweets, retweets, replies, mentions, or quote tweets that involve the subscribed user.
+    TweetCreate(Vec<Tweet>),
+    /// Likes given by or to the subscribed user.
+    Favorite(Vec<FavoriteEvent>),
+    /// The subscribed user following or unfollowing someone, or being followed.
+    Follow(Vec<UserEvent>),
+    /// The subscribed user blocking or unblocking someone.
+    Block(Vec<UserEvent>),
+    /// The subscribed user muting or unmuting someone.
+    Mute(Vec<UserEvent>),
+    /// Tweets by the subscribed user that were deleted.
+    TweetDelete(Vec<TweetDeleteEvent>),
+    /// Direct messages sent or received by the subscribed user.
+    DirectMessage(Vec<DirectMessage>),
+    /// Users typing a direct message to the subscribed user.
+    DirectMessageIndicateTyping(Vec<TypingEvent>),
+    /// Direct messages to the subscribed user that were read.
+    DirectMessageMarkRead(Vec<ReadEvent>),
+    /// An event egg-mode doesn't know how to read.
+    Other,
+}
+
+/// A tweet being liked by or from the subscribed user.
+#[derive(Debug, Deserialize)]
+pub struct FavoriteEvent {
+    /// String ID for this event.
+    pub id: String,
+    /// UTC timestamp from when the tweet was liked.
+    #[serde(with = "serde_datetime")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// The tweet that was liked.
+    pub favorited_status: Tweet,
+    /// The user who liked the tweet.
+    pub user: TwitterUser,
+}
+
+/// One user following, blocking, or muting another, or undoing one of those.
+#[derive(Debug, Deserialize)]
+pub struct UserEvent {
+    /// What happened.
+    #[serde(rename = "type")]
+    pub kind: UserEventKind,
+    /// UTC timestamp from when this happened.
+    #[serde(rename = "created_timestamp", deserialize_with = "deserialize_millis")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// The user who followed, blocked, or muted `target`.
+    pub source: TwitterUser,
+    /// The user who was followed, blocked, or muted.
+    pub target: TwitterUser,
+}
+
+/// The kinds of `UserEvent`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserEventKind {
+    /// `source` followed `target`.
+    Follow,
+    /// `source` unfollowed `target`.
+    Unfollow,
+    /// `source` blocked `target`.
+    Block,
+    /// `source` unblocked `target`.
+    Unblock,
+    /// `source` muted `target`.
+    Mute,
+    /// `source` unmuted `target`.
+    Unmute,
+}
+
+/// A tweet that was deleted.
+///
+/// Twitter only sends the IDs of the tweet and its author; the tweet itself is gone by the time
+/// this arrives.
+#[derive(Debug, Deserialize)]
+#[serde(from = "RawTweetDelete")]
+pub struct TweetDeleteEvent {
+    /// Numeric ID of the deleted tweet.
+    pub id: u64,
+    /// Numeric ID of the user who posted the tweet.
+    pub user_id: u64,
+    /// UTC timestamp from when the tweet was deleted.
+    pub deleted_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A user typing a direct message.
+#[derive(Debug, Deserialize)]
+#[serde(from = "RawDMIndicator")]
+pub struct TypingEvent {
+    /// UTC timestamp from when the user started typing.
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// The ID of the user who is typing.
+    pub sender_id: u64,
+    /// The ID of the user they're typing to.
+    pub recipient_id: u64,
+}
+
+/// A user reading their direct messages.
+#[derive(Debug, Deserialize)]
+#[serde(from = "RawDMIndicator")]
+pub struct ReadEvent {
+    /// UTC timestamp from when the messages were read.
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// The ID of the user who read the messages.
+    pub sender_id: u64,
+    /// The ID of the user who sent the messages.
+    pub recipient_id: u64,
+    /// The ID of the last message that was read.
+    pub last_read_event_id: Option<u64>,
+}
+
+// Twitter sends every kind of event under its own key, so the payload is read into a struct with
+// a field for each of them, and the first one that's present becomes the `ActivityEvent`.
+
+#[derive(Deserialize)]
+struct RawPayload {
+    #[serde(with = "serde_via_string")]
+    for_user_id: u64,
+    #[serde(default)]
+    user_has_blocked: Option<bool>,
+    tweet_create_events: Option<Vec<Tweet>>,
+    favorite_events: Option<Vec<FavoriteEvent>>,
+    follow_events: Option<Vec<UserEvent>>,
+    block_events: Option<Vec<UserEvent>>,
+    mute_events: Option<Vec<UserEvent>>,
+    tweet_delete_events: Option<Vec<TweetDeleteEvent>>,
+    direct_message_events: Option<Vec<direct::raw::EventType>>,
+    direct_message_indicate_typing_events: Option<Vec<TypingEvent>>,
+    direct_message_mark_read_events: Option<Vec<ReadEvent>>,
+    /// Source apps for `direct_message_events` sent by the subscribed user.
+    #[serde(default)]
+    apps: HashMap<String, TweetSource>,
+}
+
+impl From<RawPayload> for WebhookPayload {
+    fn from(raw: RawPayload) -> WebhookPayload {
+        let apps = raw.apps;
+        let event = if let Some(tweets) = raw.tweet_create_events {
+            ActivityEvent::TweetCreate(tweets)
+        } else if let Some(events) = raw.favorite_events {
+            ActivityEvent::Favorite(events)
+        } else if let Some(events) = raw.follow_events {
+            ActivityEvent::Follow(events)
+        } else if let Some(events) = raw.block_events {
+            ActivityEvent::Block(events)
+        } else if let Some(events) = raw.mute_events {
+            ActivityEvent::Mute(events)
+        } else if let Some(events) = raw.tweet_delete_events {
+            ActivityEvent::TweetDelete(events)
+        } else if let Some(events) = raw.direct_message_events {
+            ActivityEvent::DirectMessage(
+                events
+                    .into_iter()
+                    .map(|ev| ev.as_raw_dm().into_dm(&apps))
+                    .collect(),
+            )
+        } else if let Some(events) = raw.direct_message_indicate_typing_events {
+            ActivityEvent::DirectMessageIndicateTyping(events)
+        } else if let Some(events) = raw.direct_message_mark_read_events {
+            ActivityEvent::DirectMessageMarkRead(events)
+        } else {
+            ActivityEvent::Other
+        };
+
+        WebhookPayload {
+            for_user_id: raw.for_user_id,
+            user_has_blocked: raw.user_has_blocked,
+            event,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawTweetDelete {
+    status: RawDeletedStatus,
+    #[serde(deserialize_with = "deserialize_millis")]
+    timestamp_ms: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Deserialize)]
+struct RawDeletedStatus {
+    #[serde(with = "serde_via_string")]
+    id: u64,
+    #[serde(with = "serde_via_string")]
+    user_id: u64,
+}
+
+impl From<RawTweetDelete> for TweetDeleteEvent {
+    fn from(raw: RawTweetDelete) -> TweetDeleteEvent {
+        TweetDeleteEvent {
+            id: raw.status.id,
+            user_id: raw.status.user_id,
+            deleted_at: raw.timestamp_ms,
+        }
+    }
+}
+
+/// The shape shared by typing indicators and read receipts.
+#[derive(Deserialize)]
+struct RawDMIndicator {
+    #[serde(deserialize_with = "deserialize_millis")]
+    created_timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "serde_via_string")]
+    sender_id: u64,
+    target: RawDMTarget,
+    #[serde(
+        default,
+        deserialize_with = "serde_num_string::deserialize_number_from_opt_string"
+    )]
+    last_read_event_id: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct RawDMTarget {
+    #[serde(with = "serde_via_string")]
+    recipient_id: u64,
+}
+
+impl From<RawDMIndicator> for TypingEvent {
+    fn from(raw: RawDMIndicator) -> TypingEvent {
+        TypingEvent {
+            created_at: raw.created_timestamp,
+            sender_id: raw.sender_id,
+            recipient_id: raw.target.recipient_id,
+        }
+    }
+}
+
+impl From<RawDMIndicator> for ReadEvent {
+    fn from(raw: RawDMIndicator) -> ReadEvent {
+        ReadEvent {
+            created_at: raw.created_timestamp,
+            sender_id: raw.sender_id,
+            recipient_id: raw.target.recipient_id,
+            last_read_event_id: raw.last_read_event_id,
+        }
+    }
+}
+
+/// Reads a timestamp given as a string of milliseconds since the Unix epoch.
+fn deserialize_millis<'de, D>(de: D) -> Result<chrono::DateTime<chrono::Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let millis: i64 = serde_num_string::deserialize_number_from_string(de)?;
+    chrono::Utc
+        .timestamp_millis_opt(millis)
+        .single()
+        .ok_or_else(|| D::Error::custom(format!("timestamp out of range: {}", millis)))
+}
+
+/// Reads a webhook's `created_timestamp`, like `2016-06-02 23:54:02 +0000`.
+fn deserialize_webhook_time<'de, D>(de: D) -> Result<chrono::DateTime<chrono::Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(de)?;
+    chrono::DateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S %z")
+        .map(|date| date.with_timezone(&chrono::Utc))
+        .map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_file;
+
+    fn user() -> serde_json::Value {
+        let users: Vec<serde_json::Value> =
+            serde_json::from_str(&load_file("sample_payloads/user_array.json")).unwrap();
+        users[0].clone()
+    }
+
+    fn parse(payload: serde_json::Value) -> WebhookPayload {
+        serde_json::from_value(payload).unwrap()
+    }
+
+    #[test]
+    fn parse_tweet_and_user_events() {
+        let tweet: serde_json::Value =
+            serde_json::from_str(&load_file("sample_payloads/sample-quote.json")).unwrap();
+        let payload = parse(serde_json::json!({
+            "for_user_id": "2244994945",
+            "user_has_blocked": false,
+            "tweet_create_events": [tweet],
+        }));
+        assert_eq!(payload.for_user_id, 2244994945);
+        assert_eq!(payload.user_has_blocked, Some(false));
+        match payload.event {
+            ActivityEvent::TweetCreate(tweets) => assert_eq!(tweets.len(), 1),
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let payload = parse(serde_json::json!({
+            "for_user_id": "2244994945",
+            "follow_events": [{
+                "type": "unfollow",
+                "created_timestamp": "1517588749178",
+                "source": user(),
+                "target": user(),
+            }],
+        }));
+        match payload.event {
+            ActivityEvent::Follow(events) => {
+                assert_eq!(events[0].kind, UserEventKind::Unfollow);
+                assert_eq!(events[0].created_at.timestamp_millis(), 1517588749178);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let payload = parse(serde_json::json!({
+            "for_user_id": "2244994945",
+            "tweet_delete_events": [{
+                "status": { "id": "1001", "user_id": "2244994945" },
+                "timestamp_ms": "1432228155593",
+            }],
+        }));
+        match payload.event {
+            ActivityEvent::TweetDelete(events) => {
+                assert_eq!(events[0].id, 1001);
+                assert_eq!(events[0].user_id, 2244994945);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_direct_message_events() {
+        let payload = parse(serde_json::json!({
+            "for_user_id": "4337869213",
+            "direct_message_events": [{
+                "type": "message_create",
+                "id": "954491830116155396",
+                "created_timestamp": "1516403560557",
+                "message_create": {
+                    "target": { "recipient_id": "4337869213" },
+                    "sender_id": "3001969357",
+                    "source_app_id": "13090192",
+                    "message_data": {
+                        "text": "Hello World!",
+                        "entities": {
+                            "hashtags": [],
+                            "symbols": [],
+                            "user_mentions": [],
+                            "urls": [],
+                        },
+                    },
+                },
+            }],
+            "apps": {
+                "13090192": { "id": "13090192", "name": "FuriousCamperTestApp1", "url": "" },
+            },
+            "users": {},
+        }));
+        match payload.event {
+            ActivityEvent::DirectMessage(messages) => {
+                assert_eq!(messages[0].id, 954491830116155396);
+                assert_eq!(messages[0].text, "Hello World!");
+                assert_eq!(
+                    messages[0].source_app.as_ref().unwrap().name,
+                    "FuriousCamperTestApp1"
+                );
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let payload = parse(serde_json::json!({
+            "for_user_id": "4337869213",
+            "direct_message_indicate_typing_events": [{
+                "created_timestamp": "1518127183443",
+                "sender_id": "3284025577",
+                "target": { "recipient_id": "3001969357" },
+            }],
+        }));
+        match payload.event {
+            ActivityEvent::DirectMessageIndicateTyping(events) => {
+                assert_eq!(events[0].sender_id, 3284025577);
+                assert_eq!(events[0].recipient_id, 3001969357);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let payload = parse(serde_json::json!({
+            "for_user_id": "4337869213",
+            "user_event": { "revoke": {} },
+        }));
+        assert!(matches!(payload.event, ActivityEvent::Other));
+    }
+
+    #[test]
+    fn parse_webhook() {
+        let webhook: Webhook = serde_json::from_str(
+            r#"{
+                "id": "1234567890",
+                "url": "https://your_domain.com/webhook/twitter/0",
+                "valid": true,
+                "created_timestamp": "2016-06-02 23:54:02 +0000"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(webhook.id, 1234567890);
+        assert_eq!(webhook.created_at.timestamp(), 1464911642);
+    }
+}
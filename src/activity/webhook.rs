@@ -0,0 +1,256 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use hyper::body::HttpBody;
+use hyper::service::Service;
+use hyper::{header, Body, Method, Request, StatusCode};
+
+use crate::auth::KeyPair;
+
+use super::WebhookPayload;
+
+/// The header Twitter signs webhook events with.
+pub const SIGNATURE_HEADER: &str = "x-twitter-webhooks-signature";
+
+/// The largest request body `WebhookService` will read. Larger requests are answered with
+/// `413 Payload Too Large`.
+pub const MAX_PAYLOAD: usize = 4 * 1024 * 1024;
+
+/// A hyper `Service` that answers Twitter's requests to an Account Activity webhook.
+///
+/// `WebhookService` answers Challenge-Response Checks, and checks the signature on every event
+/// Twitter posts. Events with a valid signature are parsed into a `WebhookPayload` and given to
+/// the callback, and anything else is rejected without calling it. The callback runs before
+/// Twitter gets its response, so slow work should be spawned onto another task.
+///
+/// | Request | Response |
+/// |---|---|
+/// | `GET` with a `crc_token` | `200 OK`, with the `response_token` |
+/// | `POST` with a valid signature | `200 OK`, after calling the callback |
+/// | `POST` with a missing or wrong signature | `401 Unauthorized` |
+/// | `POST` that can't be parsed | `400 Bad Request` |
+///
+/// Since egg-mode doesn't enable hyper's server, you'll need to run this with your own copy of
+/// hyper, or call `handle` from another web framework.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use egg_mode::activity::WebhookService;
+///
+/// let con_token = egg_mode::KeyPair::new("consumer key", "consumer secret");
+/// let webhook = WebhookService::new(con_token, |payload| async move {
+///     println!("events for {}: {:?}", payload.for_user_id, payload.event);
+/// });
+///
+/// let make_service = hyper::service::make_service_fn(move |_| {
+///     let webhook = webhook.clone();
+///     async move { Ok::<_, std::convert::Infallible>(webhook) }
+/// });
+/// hyper::Server::bind(&([0, 0, 0, 0], 8080).into()).serve(make_service).await?;
+/// ```
+pub struct WebhookService<F> {
+    con_token: KeyPair,
+    callback: Arc<F>,
+}
+
+impl<F, Fut> WebhookService<F>
+where
+    F: Fn(WebhookPayload) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    /// Creates a new `WebhookService` that checks requests against the given consumer token, and
+    /// gives verified events to the given callback.
+    pub fn new(con_token: KeyPair, callback: F) -> WebhookService<F> {
+        WebhookService {
+            con_token,
+            callback: Arc::new(callback),
+        }
+    }
+
+    /// Answers the given request from Twitter, calling the callback if it holds verified events.
+    pub async fn handle(&self, request: Request<Body>) -> hyper::Response<Body> {
+        match *request.method() {
+            Method::GET => self.answer_crc(&request),
+            Method::POST => self.receive_events(request).await,
+            _ => respond(StatusCode::METHOD_NOT_ALLOWED, Body::empty()),
+        }
+    }
+
+    fn answer_crc(&self, request: &Request<Body>) -> hyper::Response<Body> {
+        let query = request.uri().query().unwrap_or_default();
+        let crc_token = url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "crc_token")
+            .map(|(_, value)| value);
+
+        match crc_token {
+            Some(crc_token) => {
+                let body = serde_json::json!({
+                    "response_token": self.con_token.webhook_crc_response(&crc_token),
+                });
+                let mut response = respond(StatusCode::OK, body.to_string().into());
+                response.headers_mut().insert(
+                    header::CONTENT_TYPE,
+                    header::HeaderValue::from_static("application/json"),
+                );
+                response
+            }
+            None => respond(StatusCode::BAD_REQUEST, Body::empty()),
+        }
+    }
+
+    async fn receive_events(&self, request: Request<Body>) -> hyper::Response<Body> {
+        let signature = request
+            .headers()
+            .get(SIGNATURE_HEADER)
+            .and_then(|s| s.to_str().ok())
+            .map(|s| s.to_string());
+        let body = match read_body(request.into_body()).await {
+            Some(body) => body,
+            None => return respond(StatusCode::PAYLOAD_TOO_LARGE, Body::empty()),
+        };
+
+        match signature {
+            Some(signature) if self.con_token.verify_webhook_signature(&body, &signature) => (),
+            _ => return respond(StatusCode::UNAUTHORIZED, Body::empty()),
+        }
+
+        match serde_json::from_slice(&body) {
+            Ok(payload) => {
+                (self.callback)(payload).await;
+                respond(StatusCode::OK, Body::empty())
+            }
+            Err(_) => respond(StatusCode::BAD_REQUEST, Body::empty()),
+        }
+    }
+}
+
+impl<F> Clone for WebhookService<F> {
+    fn clone(&self) -> Self {
+        WebhookService {
+            con_token: self.con_token.clone(),
+            callback: self.callback.clone(),
+        }
+    }
+}
+
+impl<F> std::fmt::Debug for WebhookService<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookService")
+            .field("con_token", &self.con_token)
+            .finish()
+    }
+}
+
+impl<F, Fut> Service<Request<Body>> for WebhookService<F>
+where
+    F: Fn(WebhookPayload) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    type Response = hyper::Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let service = self.clone();
+        Box::pin(async move { Ok(service.handle(request).await) })
+    }
+}
+
+/// Reads the whole request body, or returns `None` if it's longer than `MAX_PAYLOAD` or can't be
+/// read.
+async fn read_body(mut body: Body) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.ok()?;
+        if bytes.len() + chunk.len() > MAX_PAYLOAD {
+            return None;
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Some(bytes)
+}
+
+fn respond(status: StatusCode, body: Body) -> hyper::Response<Body> {
+    let mut response = hyper::Response::new(body);
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn con_token() -> KeyPair {
+        KeyPair::new("consumer key", "consumer secret")
+    }
+
+    #[test]
+    fn crc_and_signatures() {
+        // generated with Python's `hmac` module
+        assert_eq!(
+            con_token().webhook_crc_response("challenge"),
+            "sha256=G8qohDAsN4eFitPgbjmGsq6xKypyrTPNiKegYAnW3SE="
+        );
+
+        let body = br#"{"for_user_id":"2244994945"}"#;
+        let signature = con_token().webhook_crc_response(std::str::from_utf8(body).unwrap());
+        assert!(con_token().verify_webhook_signature(body, &signature));
+        assert!(!con_token().verify_webhook_signature(b"{}", &signature));
+        let other = KeyPair::new("consumer key", "other secret");
+        assert!(!other.verify_webhook_signature(body, &signature));
+    }
+
+    #[tokio::test]
+    async fn service_checks_requests() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let service = {
+            let received = received.clone();
+            WebhookService::new(con_token(), move |payload: WebhookPayload| {
+                received.lock().unwrap().push(payload.for_user_id);
+                async {}
+            })
+        };
+
+        let request = Request::get("/webhook?crc_token=challenge")
+            .body(Body::empty())
+            .unwrap();
+        let response = service.handle(request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["response_token"],
+            con_token().webhook_crc_response("challenge")
+        );
+
+        let payload = r#"{"for_user_id":"2244994945"}"#;
+        let signature = con_token().webhook_crc_response(payload);
+        let post = |signature: &str| {
+            Request::post("/webhook")
+                .header(SIGNATURE_HEADER, signature)
+                .body(Body::from(payload))
+                .unwrap()
+        };
+
+        let response = service.handle(post("sha256=bogus")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(received.lock().unwrap().is_empty());
+
+        let response = service.handle(post(&signature)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(*received.lock().unwrap(), vec![2244994945]);
+    }
+}
@@ -163,6 +163,14 @@
 //! use a library like `dotenv` to load them in, so you can safely exclude them from source
 //! control.
 //!
+//! ### Signing In From Desktop Apps
+//!
+//! Apps that run on the user's computer can skip copying a PIN by listening for Twitter's redirect
+//! on a local port. The [`loopback`] module has a helper that does all three steps this way, as
+//! long as its callback URL has been added to your app's settings.
+//!
+//! [`loopback`]: loopback/index.html
+//!
 //! ### Shortcut: Pre-Generated Access Token
 //!
 //! If you only want to sign in as yourself, there's a shortcut you can use to get an Access token.
@@ -181,6 +189,15 @@
 //! // token can be given to any egg_mode method that asks for a token
 //! ```
 //!
+//! ## OAuth 2.0 User Tokens
+//!
+//! The v2 API also accepts user tokens issued with OAuth 2.0, using the Authorization Code flow
+//! with PKCE. These tokens are limited to the scopes the user granted, and expire after a couple
+//! hours unless they're refreshed. They're held in the `Token::OAuth2User` variant, and the
+//! functions to obtain them are in the [`oauth2`] module.
+//!
+//! [`oauth2`]: oauth2/index.html
+//!
 //! For more information on the individual steps of the authentication process, see the
 //! documentation for the functions in this module.
 
@@ -196,7 +213,12 @@ use crate::{
     links,
 };
 
+pub mod loopback;
+pub mod oauth1;
+pub mod oauth2;
+pub mod pending;
 pub(crate) mod raw;
+pub mod store;
 
 use raw::RequestBuilder;
 
@@ -243,25 +265,54 @@ impl KeyPair {
         }
     }
 
-    /// Internal function to create an empty KeyPair. Not meant to be used from user code.
-    fn empty() -> KeyPair {
-        KeyPair {
-            key: "".into(),
-            secret: "".into(),
-        }
+    /// Returns the `response_token` for a Challenge-Response Check sent to an Account Activity
+    /// webhook, formatted as `sha256=<signature>`.
+    ///
+    /// Twitter sends the check as a GET request to the webhook with a `crc_token` query parameter,
+    /// and the webhook needs to respond with a JSON object like `{"response_token": "..."}`
+    /// holding the string returned here. This should be called on your app's consumer token. For
+    /// more information, see the [`activity`] module.
+    ///
+    /// [`activity`]: ../activity/index.html
+    pub fn webhook_crc_response(&self, crc_token: &str) -> String {
+        self.webhook_signature(crc_token.as_bytes())
+    }
+
+    /// Checks the `x-twitter-webhooks-signature` header sent with an event posted to an Account
+    /// Activity webhook, returning whether it was signed with this key's secret.
+    ///
+    /// `body` must be the request body exactly as it was received, before any parsing. This
+    /// should be called on your app's consumer token. For more information, see the [`activity`]
+    /// module.
+    ///
+    /// [`activity`]: ../activity/index.html
+    pub fn verify_webhook_signature(&self, body: &[u8], signature: &str) -> bool {
+        let expected = self.webhook_signature(body);
+        oauth1::constant_time_eq(signature.as_bytes(), expected.as_bytes())
+    }
+
+    fn webhook_signature(&self, data: &[u8]) -> String {
+        use hmac::{Hmac, Mac, NewMac};
+
+        let mut mac = Hmac::<sha2::Sha256>::new_varkey(self.secret.as_bytes())
+            .expect("HMAC can take a key of any size");
+        mac.update(data);
+        format!("sha256={}", base64::encode(mac.finalize().into_bytes()))
     }
 }
 
 /// A token that can be used to sign requests to Twitter.
 ///
 /// Conceptually, a Token represents your authorization to call the Twitter API. It can either be a
-/// [Bearer token], representing a "logged-out" view of Twitter coming from your app itself; or an
+/// [Bearer token], representing a "logged-out" view of Twitter coming from your app itself; an
 /// [Access token], representing a combination of your app's "consumer" key with a specific user
-/// granting access for your app to use the Twitter API on their behalf. For more information, see
+/// granting access for your app to use the Twitter API on their behalf; or an [OAuth 2.0 user
+/// token], which also acts on behalf of a user, but only for the v2 API. For more information, see
 /// the [authentication documentation][auth].
 ///
 /// [Bearer token]: index.html#bearer-tokens
 /// [Access token]: index.html#access-tokens
+/// [OAuth 2.0 user token]: index.html#oauth-20-user-tokens
 /// [auth]: index.html
 ///
 /// Once you have obtained a Token of either kind, the keys within may be saved and reused in the
@@ -285,6 +336,65 @@ pub enum Token {
     /// An OAuth Bearer token indicating the request is coming from the application itself, not a
     /// particular user.
     Bearer(String),
+    /// An OAuth 2.0 user token indicating the request is coming from a specific user, limited to
+    /// the scopes they granted. See the [`oauth2`] module for details.
+    ///
+    /// [`oauth2`]: oauth2/index.html
+    OAuth2User(oauth2::OAuth2Token),
+}
+
+/// The permissions a user granted to an app, as reported by Twitter in the `x-access-level`
+/// header.
+///
+/// This is set when creating the app in the developer portal, and applies to the tokens the app
+/// receives from then on. Changing it doesn't change the access level of existing tokens; users
+/// need to sign in again to get a token with the new access level.
+///
+/// Access levels are ordered from least to most access, so `Read < ReadWrite`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum AccessLevel {
+    /// The app can read the user's data, but not post or change anything.
+    #[serde(rename = "read")]
+    Read,
+    /// The app can read and post on the user's behalf, but can't access their Direct Messages.
+    #[serde(rename = "read-write")]
+    ReadWrite,
+    /// The app can read and post on the user's behalf, including their Direct Messages.
+    #[serde(rename = "read-write-directmessages")]
+    ReadWriteDirectMessages,
+}
+
+impl AccessLevel {
+    /// Reads the access level from the `x-access-level` header in the given response headers, if
+    /// it's there.
+    pub fn from_headers(headers: &Headers) -> Option<AccessLevel> {
+        match headers.get("x-access-level")?.to_str().ok()? {
+            "read" => Some(AccessLevel::Read),
+            "read-write" => Some(AccessLevel::ReadWrite),
+            "read-write-directmessages" => Some(AccessLevel::ReadWriteDirectMessages),
+            _ => None,
+        }
+    }
+
+    /// Returns the access level as it appears in the `x-access-level` header.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AccessLevel::Read => "read",
+            AccessLevel::ReadWrite => "read-write",
+            AccessLevel::ReadWriteDirectMessages => "read-write-directmessages",
+        }
+    }
+
+    /// Returns whether a token with this access level can make requests that need the given one.
+    pub fn allows(self, required: AccessLevel) -> bool {
+        self >= required
+    }
+}
+
+impl std::fmt::Display for AccessLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// With the given consumer KeyPair, ask Twitter for a request KeyPair that can be used to request
@@ -633,12 +743,51 @@ pub async fn invalidate_bearer(con_token: &KeyPair, token: &Token) -> Result<Tok
     Ok(Token::Bearer(result.to_owned()))
 }
 
+/// The user information returned by `verify_tokens`, along with the access level of the token.
+///
+/// This derefs to the contained `TwitterUser`, so its fields can be used directly.
+#[derive(Debug, Clone, derive_more::Deref, derive_more::DerefMut)]
+pub struct VerifiedUser {
+    /// The user the token belongs to.
+    #[deref]
+    #[deref_mut]
+    pub user: crate::user::TwitterUser,
+    /// The permissions the user granted to the app, if Twitter said what they were.
+    pub access_level: Option<AccessLevel>,
+}
+
 /// If the given tokens are valid, return the user information for the authenticated user.
 ///
 /// If you have cached access tokens, using this method is a convenient way to make sure they're
 /// still valid. If the user has revoked access from your app, this function will return an error
 /// from Twitter indicating that you don't have access to the user.
-pub async fn verify_tokens(token: &Token) -> Result<Response<crate::user::TwitterUser>> {
-    let req = get(links::auth::VERIFY_CREDENTIALS, token, None);
-    request_with_json_response(req).await
+///
+/// The result also includes the token's [`AccessLevel`], from the `x-access-level` header. If you
+/// pass a `&Client` to this function, the `Client` remembers the access level, so that it can
+/// reject requests the token isn't allowed to make without sending them.
+///
+/// OAuth 2.0 user tokens aren't accepted by the v1.1 API, so for those the user is loaded from the
+/// v2 `users/me` endpoint instead, and converted into a `TwitterUser`.
+///
+/// [`AccessLevel`]: enum.AccessLevel.html
+pub async fn verify_tokens(token: impl Into<Client>) -> Result<Response<VerifiedUser>> {
+    let client: Client = token.into();
+    if let Token::OAuth2User(_) = client.token() {
+        let resp = crate::user::v2::me().send(&client).await?;
+        let access_level = client.access_level();
+        return Response::try_map(resp, |resp| {
+            Ok(VerifiedUser {
+                user: resp.into_user()?,
+                access_level,
+            })
+        });
+    }
+
+    let req = client.request_get(links::auth::VERIFY_CREDENTIALS, None);
+    let (headers, body) = client.response_raw_bytes(req).await?;
+    let access_level = AccessLevel::from_headers(&headers);
+    Ok(Response::map(json_response(&headers, &body)?, |user| VerifiedUser {
+        user,
+        access_level,
+    }))
 }
@@ -0,0 +1,361 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Signing in from desktop apps, by receiving Twitter's redirect on a local port.
+//!
+//! Apps that run on the user's own computer can't easily receive a redirect from Twitter, which is
+//! why they usually use PIN-based authorization, asking the user to copy a PIN from their browser
+//! into the app. [`LoopbackSignIn`] avoids the copying: it listens for HTTP requests on
+//! `127.0.0.1`, and uses that address as the callback URL. When the user approves your app in
+//! their browser, Twitter redirects them back to the listener, which picks up the verifier and
+//! finishes signing in.
+//!
+//! [`LoopbackSignIn`]: struct.LoopbackSignIn.html
+//!
+//! Twitter only redirects to callback URLs that have been added to your app's settings on the
+//! developer portal, so you'll need to add `http://127.0.0.1:<port>/callback` there, using the
+//! port you give to `LoopbackSignIn::start`.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # #[tokio::main]
+//! # async fn main() {
+//! use egg_mode::auth::loopback::LoopbackSignIn;
+//!
+//! let con_token = egg_mode::KeyPair::new("consumer key", "consumer secret");
+//! let sign_in = LoopbackSignIn::start(con_token, 8765).await.unwrap();
+//!
+//! // open this in the user's browser
+//! println!("Sign in at {}", sign_in.authorize_url());
+//!
+//! let (token, user_id, screen_name) = sign_in.finish().await.unwrap();
+//! # }
+//! ```
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::error::{Error, Result};
+
+use super::{KeyPair, Token};
+
+/// The path Twitter is asked to redirect to.
+const CALLBACK_PATH: &str = "/callback";
+
+/// The most that will be read of a request sent to the listener.
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// How long a connection to the listener has to send its request before it's closed.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An in-progress sign-in that receives Twitter's redirect on a local port.
+///
+/// `start` listens on the given port and gets a request token from Twitter. The user should then
+/// be sent to `authorize_url` or `authenticate_url`, usually by opening it in their browser, and
+/// `finish` waits for Twitter to redirect them back. For more information, see the [module
+/// documentation][loopback].
+///
+/// [loopback]: index.html
+#[derive(Debug)]
+pub struct LoopbackSignIn {
+    listener: TcpListener,
+    callback: String,
+    con_token: KeyPair,
+    request_token: KeyPair,
+}
+
+impl LoopbackSignIn {
+    /// Listens on the given port of `127.0.0.1`, and requests a request token from Twitter with
+    /// the listener as its callback URL.
+    ///
+    /// If `port` is zero, any free port will be used. Since Twitter only redirects to callback URLs
+    /// that have been added to your app's settings, this will only work if you've added one for
+    /// every port it could pick.
+    pub async fn start(con_token: KeyPair, port: u16) -> Result<LoopbackSignIn> {
+        let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port))).await?;
+        let callback = callback_url(&listener)?;
+        let request_token = super::request_token(&con_token, callback.as_str()).await?;
+
+        Ok(LoopbackSignIn {
+            listener,
+            callback,
+            con_token,
+            request_token,
+        })
+    }
+
+    /// Returns the callback URL that Twitter will redirect the user to.
+    pub fn callback_url(&self) -> &str {
+        &self.callback
+    }
+
+    /// Returns the request token that Twitter gave for this sign-in.
+    pub fn request_token(&self) -> &KeyPair {
+        &self.request_token
+    }
+
+    /// Returns the URL to send the user to, to approve your app. See [`authorize_url`] for more
+    /// information.
+    ///
+    /// [`authorize_url`]: ../fn.authorize_url.html
+    pub fn authorize_url(&self) -> String {
+        super::authorize_url(&self.request_token)
+    }
+
+    /// Returns the URL to send the user to with "Sign In With Twitter". See
+    /// [`authenticate_url`] for more information.
+    ///
+    /// [`authenticate_url`]: ../fn.authenticate_url.html
+    pub fn authenticate_url(&self) -> String {
+        super::authenticate_url(&self.request_token)
+    }
+
+    /// Waits for Twitter to redirect the user back to the listener, then exchanges the verifier
+    /// for an access token.
+    ///
+    /// Other requests to the listener, like a browser asking for a `favicon.ico`, are answered with
+    /// a `404 Not Found` and otherwise ignored. Requests to the callback URL whose `oauth_token`
+    /// doesn't match the request token are answered with a `400 Bad Request` and ignored as well,
+    /// so only the redirect for this sign-in can finish it. If that redirect says the user denied
+    /// your app access, an error is returned.
+    ///
+    /// Connections are handled one at a time, and each one has a few seconds to send its request
+    /// before it's closed, so a connection that the browser opened ahead of time and never used
+    /// doesn't hold up the redirect.
+    ///
+    /// This waits for as long as it takes the user to sign in, which may be forever if they close
+    /// their browser instead. To give up after a while, wrap it in `tokio::time::timeout`.
+    ///
+    /// On success, this returns the same thing as [`access_token`]: the access token, the ID of the
+    /// user who signed in, and their screen name.
+    ///
+    /// [`access_token`]: ../fn.access_token.html
+    pub async fn finish(self) -> Result<(Token, u64, String)> {
+        let verifier = wait_for_verifier(&self.listener, &self.request_token, READ_TIMEOUT).await?;
+        super::access_token(self.con_token, &self.request_token, verifier).await
+    }
+}
+
+fn callback_url(listener: &TcpListener) -> Result<String> {
+    Ok(format!(
+        "http://{}{}",
+        listener.local_addr()?,
+        CALLBACK_PATH
+    ))
+}
+
+/// Accepts connections on the given listener until one of them is Twitter's redirect for the given
+/// request token, and returns the verifier from it. Connections that don't send a request within
+/// `read_timeout` are closed.
+async fn wait_for_verifier(
+    listener: &TcpListener,
+    request_token: &KeyPair,
+    read_timeout: Duration,
+) -> Result<String> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let read = tokio::time::timeout(read_timeout, read_request_target(&mut stream));
+        let target = match read.await {
+            Ok(Ok(Some(target))) => target,
+            Ok(Ok(None)) | Ok(Err(_)) => {
+                respond(&mut stream, "400 Bad Request", "Bad request.").await;
+                continue;
+            }
+            // the connection was opened but never used, so there's no one to answer
+            Err(_) => continue,
+        };
+
+        let mut parts = target.splitn(2, '?');
+        if parts.next() != Some(CALLBACK_PATH) {
+            respond(&mut stream, "404 Not Found", "Not found.").await;
+            continue;
+        }
+
+        match parse_callback(parts.next().unwrap_or_default(), request_token) {
+            Callback::Verifier(verifier) => {
+                let message = "Signed in! You can close this window.";
+                respond(&mut stream, "200 OK", message).await;
+                return Ok(verifier);
+            }
+            Callback::Denied(token) => {
+                let message = "Signing in was canceled. You can close this window.";
+                respond(&mut stream, "200 OK", message).await;
+                return Err(Error::InvalidResponse(
+                    "user denied the authorization request",
+                    Some(token),
+                ));
+            }
+            Callback::Other => {
+                let message = "This isn't the sign-in that was started. Please try again.";
+                respond(&mut stream, "400 Bad Request", message).await;
+            }
+        }
+    }
+}
+
+/// Reads the head of an HTTP request and returns its target, if it's a `GET` request.
+async fn read_request_target(stream: &mut TcpStream) -> std::io::Result<Option<String>> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 || head.len() + read > MAX_REQUEST_HEAD {
+            return Ok(None);
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(target)) => Ok(Some(target.to_string())),
+        _ => Ok(None),
+    }
+}
+
+/// What a request to the callback URL said about the sign-in.
+#[derive(Debug, PartialEq)]
+enum Callback {
+    /// The user approved the app, and Twitter sent this verifier.
+    Verifier(String),
+    /// The user denied the app access to the given request token.
+    Denied(String),
+    /// The request wasn't a complete redirect for this sign-in.
+    Other,
+}
+
+/// Checks the query string of a request to the callback URL against the given request token.
+fn parse_callback(query: &str, request_token: &KeyPair) -> Callback {
+    let mut token = None;
+    let mut verifier = None;
+    let mut denied = None;
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match &*key {
+            "oauth_token" => token = Some(value.into_owned()),
+            "oauth_verifier" => verifier = Some(value.into_owned()),
+            "denied" => denied = Some(value.into_owned()),
+            _ => (),
+        }
+    }
+
+    // when the user denies access, Twitter sends the request token as `denied` instead
+    match (token, verifier, denied) {
+        (_, _, Some(denied)) if denied == request_token.key => Callback::Denied(denied),
+        (Some(token), Some(verifier), None) if token == request_token.key => {
+            Callback::Verifier(verifier)
+        }
+        _ => Callback::Other,
+    }
+}
+
+/// Sends a small HTML page in response to a request. Errors are ignored, since the browser closing
+/// the connection early doesn't affect signing in.
+async fn respond(stream: &mut TcpStream, status: &str, message: &str) {
+    let body = format!(
+        "<!DOCTYPE html><html><head><title>{0}</title></head><body><p>{0}</p></body></html>",
+        message
+    );
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(addr: SocketAddr, target: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, addr);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    async fn listen() -> (TcpListener, SocketAddr) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        (listener, addr)
+    }
+
+    #[tokio::test]
+    async fn verifier_is_read_from_redirect() {
+        let (listener, addr) = listen().await;
+        assert_eq!(
+            callback_url(&listener).unwrap(),
+            format!("http://{}/callback", addr)
+        );
+
+        let request_token = KeyPair::new("request-key", "request-secret");
+        let server = tokio::spawn(async move {
+            wait_for_verifier(&listener, &request_token, READ_TIMEOUT).await
+        });
+
+        assert!(get(addr, "/favicon.ico").await.starts_with("HTTP/1.1 404"));
+        let response = get(
+            addr,
+            "/callback?oauth_token=request-key&oauth_verifier=abc%3D",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert_eq!(server.await.unwrap().unwrap(), "abc=");
+    }
+
+    #[tokio::test]
+    async fn mismatched_tokens_are_ignored() {
+        let (listener, addr) = listen().await;
+        let request_token = KeyPair::new("request-key", "request-secret");
+        let server = tokio::spawn(async move {
+            wait_for_verifier(&listener, &request_token, READ_TIMEOUT).await
+        });
+
+        let response = get(addr, "/callback?oauth_token=other-key&oauth_verifier=abc").await;
+        assert!(response.starts_with("HTTP/1.1 400"));
+        let response = get(addr, "/callback?denied=other-key").await;
+        assert!(response.starts_with("HTTP/1.1 400"));
+
+        // the listener is still waiting for the redirect for its own request token
+        let response = get(addr, "/callback?denied=request-key").await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        match server.await.unwrap() {
+            Err(Error::InvalidResponse(_, Some(token))) => assert_eq!(token, "request-key"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let request_token = KeyPair::new("request-key", "request-secret");
+        assert_eq!(
+            parse_callback("oauth_token=request-key", &request_token),
+            Callback::Other
+        );
+    }
+
+    #[tokio::test]
+    async fn idle_connections_are_closed() {
+        let (listener, addr) = listen().await;
+        let request_token = KeyPair::new("request-key", "request-secret");
+        let server = tokio::spawn(async move {
+            wait_for_verifier(&listener, &request_token, Duration::from_millis(100)).await
+        });
+
+        // like a connection a browser opens ahead of time, but sends the request on another one
+        let _idle = TcpStream::connect(addr).await.unwrap();
+        let redirect = get(addr, "/callback?oauth_token=request-key&oauth_verifier=abc");
+        let response = tokio::time::timeout(Duration::from_secs(5), redirect)
+            .await
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert_eq!(server.await.unwrap().unwrap(), "abc");
+    }
+}
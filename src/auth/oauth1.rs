@@ -0,0 +1,645 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Signing and verifying requests with OAuth 1.0a.
+//!
+//! egg-mode signs requests made with an Access token using the [`Signer`] in this module. It's
+//! exposed so that it can be used on its own: to sign requests to other services that use OAuth
+//! 1.0a, to check signatures against known examples, or to [`verify`] the signatures on requests
+//! received by a test server standing in for Twitter.
+//!
+//! [`Signer`]: struct.Signer.html
+//! [`verify`]: fn.verify.html
+//!
+//! By default, a `Signer` uses HMAC-SHA1 like Twitter expects, the current system time, and a
+//! random nonce. Each of these can be replaced, so that signatures can be reproduced exactly.
+//!
+//! # Example
+//!
+//! This reproduces the example from Twitter's guide to [creating a signature][guide]:
+//!
+//! [guide]: https://developer.twitter.com/en/docs/authentication/oauth-1-0a/creating-a-signature
+//!
+//! ```rust
+//! use egg_mode::auth::oauth1::{OAuthAddOn, Signer};
+//! use egg_mode::raw::ParamList;
+//! use egg_mode::KeyPair;
+//! use hyper::Method;
+//!
+//! let consumer = KeyPair::new(
+//!     "xvz1evFS4wEEPTGEFPHBog",
+//!     "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw",
+//! );
+//! let token = KeyPair::new(
+//!     "370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb",
+//!     "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE",
+//! );
+//! let params = ParamList::new()
+//!     .add_param("include_entities", "true")
+//!     .add_param("status", "Hello Ladies + Gentlemen, a signed OAuth request!");
+//!
+//! let signer = Signer::new()
+//!     .with_clock(|| 1318622958)
+//!     .with_nonce(|| "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg".to_string());
+//! let header = signer.sign(
+//!     &Method::POST,
+//!     "https://api.twitter.com/1.1/statuses/update.json",
+//!     Some(&params),
+//!     &consumer,
+//!     Some(&token),
+//!     &OAuthAddOn::None,
+//! );
+//!
+//! assert_eq!(header.signature(), Some("hCtSmYh+iHYCEqBWrE7C7hYmtUk="));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac, NewMac};
+use hyper::Method;
+use rand::{self, Rng};
+use sha1::Sha1;
+use sha2::Sha256;
+
+use crate::common::*;
+
+use super::KeyPair;
+
+/// The ways a request can be signed with OAuth 1.0a.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignatureMethod {
+    /// An HMAC-SHA1 digest of the request. This is the only method Twitter accepts.
+    HmacSha1,
+    /// An HMAC-SHA256 digest of the request.
+    HmacSha256,
+    /// The consumer and token secrets themselves, with no digest. This should only be used over
+    /// HTTPS.
+    Plaintext,
+}
+
+impl SignatureMethod {
+    /// Returns the name of this method, as given in the `oauth_signature_method` parameter.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SignatureMethod::HmacSha1 => "HMAC-SHA1",
+            SignatureMethod::HmacSha256 => "HMAC-SHA256",
+            SignatureMethod::Plaintext => "PLAINTEXT",
+        }
+    }
+
+    /// Parses the name of a method, as given in the `oauth_signature_method` parameter.
+    pub fn from_name(name: &str) -> Option<SignatureMethod> {
+        match name {
+            "HMAC-SHA1" => Some(SignatureMethod::HmacSha1),
+            "HMAC-SHA256" => Some(SignatureMethod::HmacSha256),
+            "PLAINTEXT" => Some(SignatureMethod::Plaintext),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SignatureMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A source of timestamps for `oauth_timestamp`, as seconds since the Unix epoch.
+///
+/// This is implemented for closures that return a `u64`, so a fixed timestamp can be given with
+/// `|| 1318622958`.
+pub trait Clock: Send + Sync {
+    /// Returns the current time, as seconds since the Unix epoch.
+    fn timestamp(&self) -> u64;
+}
+
+impl<F: Fn() -> u64 + Send + Sync> Clock for F {
+    fn timestamp(&self) -> u64 {
+        self()
+    }
+}
+
+/// A `Clock` that reads the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn timestamp(&self) -> u64 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(dur) => dur,
+            Err(err) => err.duration(),
+        }
+        .as_secs()
+    }
+}
+
+/// A `Clock` that reads the system time and adds an offset to it, to make up for the system clock
+/// being wrong.
+///
+/// Clones of a `ClockOffset` share the same offset. A `Client` keeps one of these, and sets it
+/// from Twitter's `Date` header when Twitter says a request's timestamp was out of bounds.
+#[derive(Debug, Clone, Default)]
+pub struct ClockOffset(Arc<AtomicI64>);
+
+impl ClockOffset {
+    /// Creates a new `ClockOffset` with no offset.
+    pub fn new() -> ClockOffset {
+        ClockOffset::default()
+    }
+
+    /// Returns the number of seconds added to the system time.
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Sets the number of seconds to add to the system time.
+    pub fn set(&self, offset: i64) {
+        self.0.store(offset, Ordering::Relaxed)
+    }
+
+    /// Sets the offset so that the clock reads the given time, as seconds since the Unix epoch.
+    pub fn sync_to(&self, now: i64) {
+        self.set(now - SystemClock.timestamp() as i64)
+    }
+}
+
+impl Clock for ClockOffset {
+    fn timestamp(&self) -> u64 {
+        (SystemClock.timestamp() as i64 + self.get()).max(0) as u64
+    }
+}
+
+/// A source of values for `oauth_nonce`.
+///
+/// This is implemented for closures that return a `String`. Nonces should be different for every
+/// request, since Twitter uses them to reject requests that have been sent twice.
+pub trait NonceSource: Send + Sync {
+    /// Returns a new nonce.
+    fn nonce(&self) -> String;
+}
+
+impl<F: Fn() -> String + Send + Sync> NonceSource for F {
+    fn nonce(&self) -> String {
+        self()
+    }
+}
+
+/// A `NonceSource` that generates 32 random alphanumeric characters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomNonce;
+
+impl NonceSource for RandomNonce {
+    fn nonce(&self) -> String {
+        let mut rng = rand::thread_rng();
+        ::std::iter::repeat(())
+            .map(|()| rng.sample(rand::distributions::Alphanumeric))
+            .map(char::from)
+            .take(32)
+            .collect::<String>()
+    }
+}
+
+/// An `oauth_callback` or `oauth_verifier` parameter to include in a signature.
+#[derive(Clone, Debug)]
+pub enum OAuthAddOn {
+    /// An `oauth_callback` parameter, used when generating a request token.
+    Callback(String),
+    /// An `oauth_verifier` parameter, used when generating an access token.
+    Verifier(String),
+    /// Neither an `oauth_callback` nor an `oauth_verifier` parameter are present in this header.
+    /// This is the default used when signing a regular API request.
+    None,
+}
+
+impl OAuthAddOn {
+    /// Returns the `oauth_callback` parameter, if present.
+    fn as_callback(&self) -> Option<&str> {
+        match self {
+            OAuthAddOn::Callback(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Returns the `oauth_verifier` parameter, if present.
+    fn as_verifier(&self) -> Option<&str> {
+        match self {
+            OAuthAddOn::Verifier(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Signs requests with OAuth 1.0a.
+///
+/// A `Signer` starts out using HMAC-SHA1, the system time, and a random nonce, and each of these
+/// can be changed with the `with_*` methods. Clones of a `Signer` share the same clock and nonce
+/// source. For more information, see the [module documentation][oauth1].
+///
+/// [oauth1]: index.html
+#[derive(Clone)]
+pub struct Signer {
+    method: SignatureMethod,
+    clock: Arc<dyn Clock>,
+    nonce: Arc<dyn NonceSource>,
+}
+
+impl Default for Signer {
+    fn default() -> Signer {
+        Signer {
+            method: SignatureMethod::HmacSha1,
+            clock: Arc::new(SystemClock),
+            nonce: Arc::new(RandomNonce),
+        }
+    }
+}
+
+impl fmt::Debug for Signer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Signer")
+            .field("method", &self.method)
+            .finish()
+    }
+}
+
+impl Signer {
+    /// Creates a new `Signer` that uses HMAC-SHA1, the system time, and a random nonce.
+    pub fn new() -> Signer {
+        Signer::default()
+    }
+
+    /// Sets the signature method to use.
+    pub fn with_method(self, method: SignatureMethod) -> Self {
+        Signer { method, ..self }
+    }
+
+    /// Sets where timestamps come from.
+    pub fn with_clock(self, clock: impl Clock + 'static) -> Self {
+        Signer {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    /// Sets where nonces come from.
+    pub fn with_nonce(self, nonce: impl NonceSource + 'static) -> Self {
+        Signer {
+            nonce: Arc::new(nonce),
+            ..self
+        }
+    }
+
+    /// Returns the signature method this `Signer` uses.
+    pub fn method(&self) -> SignatureMethod {
+        self.method
+    }
+
+    /// Signs a request with the given method, URL, and parameters, using the given consumer key
+    /// and token.
+    ///
+    /// `uri` should be the URL of the request without its query string, and `params` should hold
+    /// the parameters from the query string and the request body, if the body is form-encoded.
+    /// `token` can be `None` when requesting a request token, which is signed with the consumer
+    /// key alone.
+    pub fn sign(
+        &self,
+        method: &Method,
+        uri: &str,
+        params: Option<&ParamList>,
+        consumer: &KeyPair,
+        token: Option<&KeyPair>,
+        addon: &OAuthAddOn,
+    ) -> SignedHeader {
+        let mut oauth = BTreeMap::new();
+        oauth.insert("oauth_consumer_key".to_string(), consumer.key.to_string());
+        oauth.insert("oauth_nonce".to_string(), self.nonce.nonce());
+        oauth.insert(
+            "oauth_signature_method".to_string(),
+            self.method.as_str().to_string(),
+        );
+        oauth.insert(
+            "oauth_timestamp".to_string(),
+            self.clock.timestamp().to_string(),
+        );
+        oauth.insert("oauth_version".to_string(), "1.0".to_string());
+        if let Some(token) = token {
+            oauth.insert("oauth_token".to_string(), token.key.to_string());
+        }
+        if let Some(callback) = addon.as_callback() {
+            oauth.insert("oauth_callback".to_string(), callback.to_string());
+        }
+        if let Some(verifier) = addon.as_verifier() {
+            oauth.insert("oauth_verifier".to_string(), verifier.to_string());
+        }
+
+        let secret = token.map(|t| &*t.secret).unwrap_or_default();
+        let signature = signature(
+            self.method,
+            method,
+            uri,
+            params,
+            &oauth,
+            &consumer.secret,
+            secret,
+        );
+        oauth.insert("oauth_signature".to_string(), signature);
+
+        SignedHeader { params: oauth }
+    }
+}
+
+/// Creates the signature for a request, with the given `oauth_*` parameters.
+fn signature(
+    signature_method: SignatureMethod,
+    method: &Method,
+    uri: &str,
+    params: Option<&ParamList>,
+    oauth: &BTreeMap<String, String>,
+    consumer_secret: &str,
+    token_secret: &str,
+) -> String {
+    let key = format!(
+        "{}&{}",
+        percent_encode(consumer_secret),
+        percent_encode(token_secret)
+    );
+
+    let base_str = || {
+        let mut query = params
+            .into_iter()
+            .flat_map(|p| p.iter())
+            .map(|(k, v)| (&**k, &**v))
+            .chain(oauth.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+            .filter(|(k, _)| *k != "oauth_signature")
+            .map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(v)))
+            .collect::<Vec<_>>();
+        query.sort();
+
+        format!(
+            "{}&{}&{}",
+            percent_encode(method.as_str()),
+            percent_encode(uri),
+            percent_encode(&query.join("&"))
+        )
+    };
+
+    match signature_method {
+        SignatureMethod::HmacSha1 => {
+            let mut digest = Hmac::<Sha1>::new_varkey(key.as_bytes()).expect("Wrong key length");
+            digest.update(base_str().as_bytes());
+            base64::encode(digest.finalize().into_bytes())
+        }
+        SignatureMethod::HmacSha256 => {
+            let mut digest = Hmac::<Sha256>::new_varkey(key.as_bytes()).expect("Wrong key length");
+            digest.update(base_str().as_bytes());
+            base64::encode(digest.finalize().into_bytes())
+        }
+        SignatureMethod::Plaintext => key,
+    }
+}
+
+/// The `oauth_*` parameters of a signed request, including its signature.
+///
+/// The `Display` impl formats these as an `Authorization` header, and `parse` reads them back from
+/// one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedHeader {
+    /// The OAuth parameters used to create the signature, along with the signature itself.
+    params: BTreeMap<String, String>,
+}
+
+impl SignedHeader {
+    /// Parses the `oauth_*` parameters from the value of an `Authorization` header.
+    ///
+    /// Returns `None` if the header doesn't use the `OAuth` scheme or isn't formatted correctly.
+    pub fn parse(authorization: &str) -> Option<SignedHeader> {
+        let authorization = authorization.trim();
+        if authorization.len() < 6 || !authorization[..6].eq_ignore_ascii_case("oauth ") {
+            return None;
+        }
+
+        let mut params = BTreeMap::new();
+        for param in authorization[6..].split(',') {
+            let mut kv = param.trim().splitn(2, '=');
+            let key = kv.next()?;
+            let value = kv.next()?.strip_prefix('"')?.strip_suffix('"')?;
+            let value = percent_encoding::percent_decode_str(value)
+                .decode_utf8()
+                .ok()?;
+            if key != "realm" {
+                params.insert(key.to_string(), value.into_owned());
+            }
+        }
+
+        Some(SignedHeader { params })
+    }
+
+    /// Returns the value of the given parameter, like `oauth_nonce`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(|s| s.as_str())
+    }
+
+    /// Returns the key of the consumer token that signed the request.
+    pub fn consumer_key(&self) -> Option<&str> {
+        self.get("oauth_consumer_key")
+    }
+
+    /// Returns the key of the token that signed the request, if one was given.
+    pub fn token(&self) -> Option<&str> {
+        self.get("oauth_token")
+    }
+
+    /// Returns the timestamp the request was signed with.
+    pub fn timestamp(&self) -> Option<u64> {
+        self.get("oauth_timestamp")?.parse().ok()
+    }
+
+    /// Returns the signature of the request.
+    pub fn signature(&self) -> Option<&str> {
+        self.get("oauth_signature")
+    }
+
+    /// Returns the method used to create the signature, if it's one egg-mode knows.
+    pub fn signature_method(&self) -> Option<SignatureMethod> {
+        SignatureMethod::from_name(self.get("oauth_signature_method")?)
+    }
+}
+
+/// The `Display` impl for `SignedHeader` formats it as an `Authorization` header for an HTTP
+/// request.
+impl fmt::Display for SignedHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // authorization scheme
+        write!(f, "OAuth ")?;
+
+        // authorization data
+
+        let mut first = true;
+        for (k, v) in &self.params {
+            if first {
+                first = false;
+            } else {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}=\"{}\"", k, percent_encode(v))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks the signature on a request received with the given method, URL, and parameters.
+///
+/// The signature is checked with the method given in the header's `oauth_signature_method`, and
+/// the header's consumer key and token must match the given ones. `uri` and `params` have the same
+/// meaning as in [`Signer::sign`]. If `token` is `None`, the request must not include a token.
+///
+/// [`Signer::sign`]: struct.Signer.html#method.sign
+///
+/// This only checks the signature. Servers should also reject requests whose `timestamp` is too
+/// far from the current time, and nonces they've already seen, so that requests can't be replayed.
+pub fn verify(
+    header: &SignedHeader,
+    method: &Method,
+    uri: &str,
+    params: Option<&ParamList>,
+    consumer: &KeyPair,
+    token: Option<&KeyPair>,
+) -> bool {
+    let signature_method = match header.signature_method() {
+        Some(signature_method) => signature_method,
+        None => return false,
+    };
+    let given = match header.signature() {
+        Some(given) => given,
+        None => return false,
+    };
+    if header.consumer_key() != Some(&*consumer.key) || header.token() != token.map(|t| &*t.key) {
+        return false;
+    }
+
+    let secret = token.map(|t| &*t.secret).unwrap_or_default();
+    let expected = signature(
+        signature_method,
+        method,
+        uri,
+        params,
+        &header.params,
+        &consumer.secret,
+        secret,
+    );
+    constant_time_eq(given.as_bytes(), expected.as_bytes())
+}
+
+/// Compares two byte strings without returning early, so the time taken doesn't reveal how much of
+/// a signature was correct.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn twitter_example() -> (KeyPair, KeyPair, ParamList) {
+        let consumer = KeyPair::new(
+            "xvz1evFS4wEEPTGEFPHBog",
+            "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw",
+        );
+        let token = KeyPair::new(
+            "370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb",
+            "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE",
+        );
+        let params = ParamList::new()
+            .add_param("include_entities", "true")
+            .add_param(
+                "status",
+                "Hello Ladies + Gentlemen, a signed OAuth request!",
+            );
+        (consumer, token, params)
+    }
+
+    fn fixed_signer(method: SignatureMethod) -> Signer {
+        Signer::new()
+            .with_method(method)
+            .with_clock(|| 1318622958)
+            .with_nonce(|| "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg".to_string())
+    }
+
+    const UPDATE: &str = "https://api.twitter.com/1.1/statuses/update.json";
+
+    #[test]
+    fn twitter_signature_example() {
+        let (consumer, token, params) = twitter_example();
+        let header = fixed_signer(SignatureMethod::HmacSha1).sign(
+            &Method::POST,
+            UPDATE,
+            Some(&params),
+            &consumer,
+            Some(&token),
+            &OAuthAddOn::None,
+        );
+        assert_eq!(header.signature(), Some("hCtSmYh+iHYCEqBWrE7C7hYmtUk="));
+        assert_eq!(header.timestamp(), Some(1318622958));
+
+        let header = fixed_signer(SignatureMethod::Plaintext).sign(
+            &Method::POST,
+            UPDATE,
+            Some(&params),
+            &consumer,
+            Some(&token),
+            &OAuthAddOn::None,
+        );
+        assert_eq!(
+            header.signature(),
+            Some(
+                "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw&\
+                 LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE"
+            )
+        );
+    }
+
+    #[test]
+    fn signatures_verify() {
+        let (consumer, token, params) = twitter_example();
+        for &method in &[
+            SignatureMethod::HmacSha1,
+            SignatureMethod::HmacSha256,
+            SignatureMethod::Plaintext,
+        ] {
+            let addon = OAuthAddOn::Verifier("a verifier".to_string());
+            let header = Signer::new().with_method(method).sign(
+                &Method::POST,
+                UPDATE,
+                Some(&params),
+                &consumer,
+                Some(&token),
+                &addon,
+            );
+            let parsed = SignedHeader::parse(&header.to_string()).unwrap();
+            assert_eq!(parsed, header);
+            assert_eq!(parsed.signature_method(), Some(method));
+            assert_eq!(parsed.get("oauth_verifier"), Some("a verifier"));
+
+            let verify_with = |uri, params: &ParamList, token| {
+                verify(&parsed, &Method::POST, uri, Some(params), &consumer, token)
+            };
+            assert!(verify_with(UPDATE, &params, Some(&token)));
+            assert!(!verify_with(UPDATE, &params, None));
+            let wrong_secret = KeyPair::new(token.key.clone(), "wrong");
+            assert!(!verify_with(UPDATE, &params, Some(&wrong_secret)));
+            if method != SignatureMethod::Plaintext {
+                let other = params.clone().add_param("extra", "1");
+                assert!(!verify_with(UPDATE, &other, Some(&token)));
+                assert!(!verify_with("https://example.com/", &params, Some(&token)));
+            }
+        }
+
+        assert!(SignedHeader::parse("Bearer abc").is_none());
+    }
+}
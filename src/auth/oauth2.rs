@@ -0,0 +1,716 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! OAuth 2.0 user tokens, obtained with the Authorization Code flow and PKCE.
+//!
+//! Besides the OAuth 1.0a Access tokens described in the [module docs][auth], Twitter can also
+//! issue user tokens with OAuth 2.0. These are limited to the [scopes] the user granted, and
+//! expire after a couple hours, but can be refreshed without sending the user through the sign-in
+//! process again if the `offline.access` scope was requested. Since they're only accepted by the
+//! v2 API, these tokens are mainly useful for v2 endpoints that need a user context.
+//!
+//! [auth]: ../index.html
+//! [scopes]: enum.Scope.html
+//!
+//! The process to get an OAuth 2.0 user token has three steps:
+//!
+//! 1. Create an [`OAuth2Client`] with the client ID from your app's settings in the developer
+//!    portal, and the callback URL you registered for it. If your app is a "confidential client",
+//!    add the client secret as well.
+//! 2. Send the user to the URL in the [`PkceAuthorization`] returned by [`authorize_url`], and save
+//!    the rest of it. When the user accepts, Twitter redirects them to your callback URL with
+//!    `state` and `code` query parameters.
+//! 3. Check that `state` matches the one you saved, and hand the `code` and the saved
+//!    `code_verifier` to [`access_token`] to get the `Token`.
+//!
+//! The requests to exchange, refresh, and revoke tokens are sent with a `Client`, so they use its
+//! endpoints, transport, proxy, and timeouts like any other request. These requests carry the
+//! app's own credentials instead of a user's, so the token of that `Client` isn't sent.
+//!
+//! [`OAuth2Client`]: struct.OAuth2Client.html
+//! [`PkceAuthorization`]: struct.PkceAuthorization.html
+//! [`authorize_url`]: fn.authorize_url.html
+//! [`access_token`]: fn.access_token.html
+//!
+//! When a `Client` with an expired `OAuth2User` token sends a request, it refreshes the token
+//! first, as long as the token has a refresh token. Since Twitter only allows each refresh token
+//! to be used once, the new tokens are shared with every clone of the `Token` and every `Client`
+//! made from it. To keep using the token after your program restarts, save its
+//! [`credentials`][creds] after making calls, rather than the ones you started with. If the refresh
+//! token was already used or revoked, the refresh fails with `Error::RefreshRejected`, and the user
+//! needs to sign in again.
+//!
+//! [creds]: struct.OAuth2Token.html#method.credentials
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # #[tokio::main]
+//! # async fn main() {
+//! use egg_mode::auth::oauth2::{self, OAuth2Client, Scope};
+//! use egg_mode::{Client, Token};
+//!
+//! let client = OAuth2Client::new("client id", "https://myapp.io/callback");
+//! let scopes = [Scope::TweetRead, Scope::UsersRead, Scope::OfflineAccess];
+//! let auth = oauth2::authorize_url(&client, &scopes);
+//!
+//! // send the user to auth.url, and save auth.state and auth.code_verifier until they come back
+//! // to the callback URL...
+//! let (state, code) = ("state from the callback", "code from the callback");
+//! assert_eq!(state, auth.state);
+//!
+//! // the token of this client isn't used, since the user doesn't have one yet
+//! let http = Client::new(Token::Bearer(String::new()));
+//! let token = oauth2::access_token(&client, code, &auth.code_verifier, &http)
+//!     .await
+//!     .unwrap();
+//! // token can be given to any egg_mode method that asks for a token
+//! # }
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Duration, Utc};
+use hyper::{Body, Method, Request};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+use crate::client::Client;
+use crate::common::*;
+use crate::error::{self, Result};
+use crate::links;
+
+use super::raw::RequestBuilder;
+use super::{KeyPair, Token};
+
+/// How long before its expiry a token is considered expired, so that a request doesn't race it.
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// The permissions an OAuth 2.0 user token can be granted.
+///
+/// These are requested when sending the user to the [`authorize_url`], and the user can see them
+/// before accepting. See Twitter's [documentation on scopes][scopes] for which endpoints need
+/// which scopes.
+///
+/// [`authorize_url`]: fn.authorize_url.html
+/// [scopes]: https://developer.twitter.com/en/docs/authentication/guides/v2-authentication-mapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scope {
+    /// All the tweets you can view, including tweets from protected accounts.
+    #[serde(rename = "tweet.read")]
+    TweetRead,
+    /// Tweet and retweet for you.
+    #[serde(rename = "tweet.write")]
+    TweetWrite,
+    /// Hide and unhide replies to your tweets.
+    #[serde(rename = "tweet.moderate.write")]
+    TweetModerateWrite,
+    /// Any account you can view, including protected accounts.
+    #[serde(rename = "users.read")]
+    UsersRead,
+    /// People who follow you and people who you follow.
+    #[serde(rename = "follows.read")]
+    FollowsRead,
+    /// Follow and unfollow people for you.
+    #[serde(rename = "follows.write")]
+    FollowsWrite,
+    /// Stay connected to your account until you revoke access, by issuing a refresh token.
+    #[serde(rename = "offline.access")]
+    OfflineAccess,
+    /// All the Spaces you can view.
+    #[serde(rename = "space.read")]
+    SpaceRead,
+    /// Accounts you've muted.
+    #[serde(rename = "mute.read")]
+    MuteRead,
+    /// Mute and unmute accounts for you.
+    #[serde(rename = "mute.write")]
+    MuteWrite,
+    /// Tweets you've liked and likes you can view.
+    #[serde(rename = "like.read")]
+    LikeRead,
+    /// Like and unlike tweets for you.
+    #[serde(rename = "like.write")]
+    LikeWrite,
+    /// Lists, list members, and list followers of lists you've created or are a member of,
+    /// including private lists.
+    #[serde(rename = "list.read")]
+    ListRead,
+    /// Create and manage lists for you.
+    #[serde(rename = "list.write")]
+    ListWrite,
+    /// Accounts you've blocked.
+    #[serde(rename = "block.read")]
+    BlockRead,
+    /// Block and unblock accounts for you.
+    #[serde(rename = "block.write")]
+    BlockWrite,
+    /// Get bookmarked tweets from an authenticated user.
+    #[serde(rename = "bookmark.read")]
+    BookmarkRead,
+    /// Bookmark and remove bookmarks from tweets.
+    #[serde(rename = "bookmark.write")]
+    BookmarkWrite,
+    /// All your Direct Messages.
+    #[serde(rename = "dm.read")]
+    DmRead,
+    /// Send and manage Direct Messages for you.
+    #[serde(rename = "dm.write")]
+    DmWrite,
+}
+
+impl Scope {
+    /// Returns the name Twitter uses for this scope, like `tweet.read`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::TweetRead => "tweet.read",
+            Scope::TweetWrite => "tweet.write",
+            Scope::TweetModerateWrite => "tweet.moderate.write",
+            Scope::UsersRead => "users.read",
+            Scope::FollowsRead => "follows.read",
+            Scope::FollowsWrite => "follows.write",
+            Scope::OfflineAccess => "offline.access",
+            Scope::SpaceRead => "space.read",
+            Scope::MuteRead => "mute.read",
+            Scope::MuteWrite => "mute.write",
+            Scope::LikeRead => "like.read",
+            Scope::LikeWrite => "like.write",
+            Scope::ListRead => "list.read",
+            Scope::ListWrite => "list.write",
+            Scope::BlockRead => "block.read",
+            Scope::BlockWrite => "block.write",
+            Scope::BookmarkRead => "bookmark.read",
+            Scope::BookmarkWrite => "bookmark.write",
+            Scope::DmRead => "dm.read",
+            Scope::DmWrite => "dm.write",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The settings of an app that signs users in with OAuth 2.0.
+///
+/// The client ID and secret can be found in the "Keys and tokens" section of your app's settings
+/// in the developer portal. The client secret is only given to "confidential clients", like web
+/// apps that run on a server; apps that run on the user's device are "public clients", and only
+/// use the client ID. The redirect URI needs to match one of the callback URLs registered for the
+/// app.
+///
+/// **Note** that the client secret works like a password, and should be handled with care.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuth2Client {
+    /// The client ID of the app.
+    pub client_id: String,
+    /// The client secret of the app, for confidential clients.
+    pub client_secret: Option<String>,
+    /// The URL that Twitter redirects the user to after they accept or reject the authorization.
+    pub redirect_uri: String,
+}
+
+impl OAuth2Client {
+    /// Creates a new `OAuth2Client` for a public client, with the given client ID and redirect
+    /// URI.
+    pub fn new(client_id: impl Into<String>, redirect_uri: impl Into<String>) -> OAuth2Client {
+        OAuth2Client {
+            client_id: client_id.into(),
+            client_secret: None,
+            redirect_uri: redirect_uri.into(),
+        }
+    }
+
+    /// Sets the client secret, for confidential clients.
+    pub fn with_secret(self, client_secret: impl Into<String>) -> Self {
+        OAuth2Client {
+            client_secret: Some(client_secret.into()),
+            ..self
+        }
+    }
+
+    /// Assembles a request to one of the OAuth 2.0 endpoints with the given form parameters.
+    ///
+    /// Confidential clients authenticate these requests with their client ID and secret using
+    /// HTTP Basic authentication. Public clients include their client ID in the form instead.
+    fn request(&self, uri: &str, params: ParamList) -> Request<Body> {
+        let content = "application/x-www-form-urlencoded;charset=UTF-8";
+        let params = params.add_param("client_id", self.client_id.clone());
+        let builder =
+            RequestBuilder::new(Method::POST, uri).with_body(params.to_urlencoded(), content);
+
+        match &self.client_secret {
+            Some(secret) => {
+                let keys = KeyPair::new(self.client_id.clone(), secret.clone());
+                builder.request_consumer_bearer(&keys)
+            }
+            None => builder.request_unauthenticated(),
+        }
+    }
+}
+
+/// The pieces of an OAuth 2.0 authorization request, as returned by [`authorize_url`].
+///
+/// Send the user to `url`, and save `state` and `code_verifier` until they come back to your
+/// redirect URI. Since these are needed to finish signing in, this type can be serialized, so it
+/// can be kept in a session store if the user comes back to a different server.
+///
+/// [`authorize_url`]: fn.authorize_url.html
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PkceAuthorization {
+    /// The URL to send the user to, so they can accept or reject the authorization.
+    pub url: String,
+    /// A random string that Twitter passes back to the redirect URI as the `state` parameter.
+    /// Check that it matches before using the code, to make sure the redirect came from a sign-in
+    /// your app started.
+    pub state: String,
+    /// The secret half of the PKCE challenge, which needs to be given to [`access_token`] along
+    /// with the code.
+    ///
+    /// [`access_token`]: fn.access_token.html
+    pub code_verifier: String,
+}
+
+/// Returns a URL that the user can visit to grant your app the given scopes, along with the state
+/// and code verifier needed to finish signing them in.
+///
+/// Each call generates a new random `state` and PKCE `code_verifier`. The URL only includes the
+/// `code_challenge` derived from the verifier with SHA-256, so the verifier proves to Twitter that
+/// the code is being exchanged by the same app that asked for it.
+///
+/// To be able to refresh the token after it expires, include `Scope::OfflineAccess`.
+pub fn authorize_url(client: &OAuth2Client, scopes: &[Scope]) -> PkceAuthorization {
+    let state = random_string(32);
+    let code_verifier = random_string(64);
+    let code_challenge = base64::encode_config(
+        Sha256::digest(code_verifier.as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    );
+    let scopes = scopes
+        .iter()
+        .map(|s| s.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let params = ParamList::new()
+        .add_param("response_type", "code")
+        .add_param("client_id", client.client_id.clone())
+        .add_param("redirect_uri", client.redirect_uri.clone())
+        .add_param("scope", scopes)
+        .add_param("state", state.clone())
+        .add_param("code_challenge", code_challenge)
+        .add_param("code_challenge_method", "S256");
+
+    PkceAuthorization {
+        url: format!(
+            "{}?{}",
+            links::auth::OAUTH2_AUTHORIZE,
+            params.to_urlencoded()
+        ),
+        state,
+        code_verifier,
+    }
+}
+
+/// Exchanges the code given to your redirect URI for an OAuth 2.0 user token.
+///
+/// The `code_verifier` is the one from the [`PkceAuthorization`] that the user was sent to sign in
+/// with. The request is sent with the `Client` given as `http`, whose own token isn't used. The
+/// returned `Token` is an `OAuth2User` token.
+///
+/// [`PkceAuthorization`]: struct.PkceAuthorization.html
+pub async fn access_token(
+    client: &OAuth2Client,
+    code: &str,
+    code_verifier: &str,
+    http: impl Into<Client>,
+) -> Result<Token> {
+    let http: Client = http.into();
+    let params = ParamList::new()
+        .add_param("code", code.to_string())
+        .add_param("grant_type", "authorization_code")
+        .add_param("redirect_uri", client.redirect_uri.clone())
+        .add_param("code_verifier", code_verifier.to_string());
+    let uri = http.endpoints().resolve(links::auth::OAUTH2_TOKEN);
+    let request = client.request(&uri, params);
+
+    let (headers, body) = http.response_raw_bytes(request).await?;
+    let response = json_response::<TokenResponse>(&headers, &body)?.response;
+
+    let credentials = OAuth2Credentials {
+        client: client.clone(),
+        access_token: response.access_token,
+        refresh_token: response.refresh_token,
+        expires_at: response.expires_in.map(expires_at),
+        scopes: response.scope.map(parse_scopes).unwrap_or_default(),
+    };
+
+    Ok(Token::OAuth2User(OAuth2Token::new(credentials)))
+}
+
+/// Revokes the given OAuth 2.0 user token, so that neither its access token nor its refresh token
+/// can be used again.
+///
+/// The requests are sent with the `Client` given as `http`, whose own token isn't used.
+pub async fn revoke(token: &OAuth2Token, http: impl Into<Client>) -> Result<()> {
+    let http: Client = http.into();
+    let uri = http.endpoints().resolve(links::auth::OAUTH2_REVOKE);
+    let credentials = token.credentials();
+    let tokens = [
+        ("refresh_token", credentials.refresh_token.as_ref()),
+        ("access_token", Some(&credentials.access_token)),
+    ];
+
+    for &(hint, value) in tokens.iter() {
+        if let Some(value) = value {
+            let params = ParamList::new()
+                .add_param("token", value.clone())
+                .add_param("token_type_hint", hint);
+            let request = credentials.client.request(&uri, params);
+            http.response_raw_bytes(request).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The saved state of an OAuth 2.0 user token.
+///
+/// This is what an [`OAuth2Token`] serializes to, and can be used to create one again with
+/// [`OAuth2Token::new`].
+///
+/// [`OAuth2Token`]: struct.OAuth2Token.html
+/// [`OAuth2Token::new`]: struct.OAuth2Token.html#method.new
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuth2Credentials {
+    /// The app the token was issued to, which is needed to refresh or revoke it.
+    pub client: OAuth2Client,
+    /// The access token sent with each request.
+    pub access_token: String,
+    /// The token used to get a new access token after this one expires, if the `offline.access`
+    /// scope was granted.
+    pub refresh_token: Option<String>,
+    /// When the access token expires, if Twitter said.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The scopes that were granted to the token. Scopes that egg-mode doesn't know about are
+    /// left out.
+    pub scopes: Vec<Scope>,
+}
+
+/// An OAuth 2.0 user token, held in `Token::OAuth2User`.
+///
+/// The credentials in this token are shared between its clones, so that when one of them is
+/// refreshed, all of them use the new access token. See the [module docs][oauth2] for details.
+///
+/// [oauth2]: index.html
+#[derive(Clone)]
+pub struct OAuth2Token {
+    credentials: Arc<RwLock<OAuth2Credentials>>,
+    /// Held while refreshing, so that concurrent requests don't each use up the refresh token.
+    refreshing: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl OAuth2Token {
+    /// Creates a new `OAuth2Token` from the given credentials.
+    pub fn new(credentials: OAuth2Credentials) -> OAuth2Token {
+        OAuth2Token {
+            credentials: Arc::new(RwLock::new(credentials)),
+            refreshing: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// Returns a copy of the current credentials of this token.
+    pub fn credentials(&self) -> OAuth2Credentials {
+        self.credentials.read().unwrap().clone()
+    }
+
+    /// Returns the current access token.
+    pub fn access_token(&self) -> String {
+        self.credentials.read().unwrap().access_token.clone()
+    }
+
+    /// Returns whether the given scope was granted to this token.
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.credentials.read().unwrap().scopes.contains(&scope)
+    }
+
+    /// Returns whether the access token has expired, or is about to.
+    pub fn is_expired(&self) -> bool {
+        let expires_at = self.credentials.read().unwrap().expires_at;
+        matches!(expires_at, Some(at) if at - Duration::seconds(EXPIRY_MARGIN_SECS) <= Utc::now())
+    }
+
+    /// Returns whether this token has a refresh token.
+    pub fn can_refresh(&self) -> bool {
+        self.credentials.read().unwrap().refresh_token.is_some()
+    }
+
+    /// Uses the refresh token to get a new access token, replacing the one in this token and all
+    /// its clones.
+    ///
+    /// This happens automatically when a `Client` sends a request with an expired token, so it's
+    /// only necessary to call this yourself when using the functions in the `raw` module. The
+    /// request is sent with the `Client` given as `http`, whose own token isn't used.
+    pub async fn refresh(&self, http: impl Into<Client>) -> Result<()> {
+        let http: Client = http.into();
+        let uri = http.endpoints().resolve(links::auth::OAUTH2_TOKEN);
+        self.refresh_with(&uri, |request| http.response_raw_bytes(request))
+            .await
+    }
+
+    /// Refreshes this token if it has expired, sending the refresh request to the given URL with
+    /// the given function.
+    pub(crate) async fn refresh_if_expired<F, Fut>(&self, uri: &str, send: F) -> Result<()>
+    where
+        F: FnOnce(Request<Body>) -> Fut,
+        Fut: Future<Output = Result<(Headers, Vec<u8>)>>,
+    {
+        if !self.is_expired() || !self.can_refresh() {
+            return Ok(());
+        }
+
+        let _refreshing = self.refreshing.lock().await;
+        // another request may have refreshed the token while this one was waiting
+        if self.is_expired() {
+            self.refresh_locked(uri, send).await
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn refresh_with<F, Fut>(&self, uri: &str, send: F) -> Result<()>
+    where
+        F: FnOnce(Request<Body>) -> Fut,
+        Fut: Future<Output = Result<(Headers, Vec<u8>)>>,
+    {
+        let _refreshing = self.refreshing.lock().await;
+        self.refresh_locked(uri, send).await
+    }
+
+    async fn refresh_locked<F, Fut>(&self, uri: &str, send: F) -> Result<()>
+    where
+        F: FnOnce(Request<Body>) -> Fut,
+        Fut: Future<Output = Result<(Headers, Vec<u8>)>>,
+    {
+        let credentials = self.credentials();
+        let refresh_token = credentials
+            .refresh_token
+            .ok_or(error::Error::MissingValue("refresh_token"))?;
+        let params = ParamList::new()
+            .add_param("refresh_token", refresh_token)
+            .add_param("grant_type", "refresh_token");
+        let request = credentials.client.request(uri, params);
+
+        let (headers, body) = send(request).await.map_err(refresh_rejected)?;
+        let response = json_response::<TokenResponse>(&headers, &body)?.response;
+
+        let mut credentials = self.credentials.write().unwrap();
+        credentials.access_token = response.access_token;
+        if let Some(refresh_token) = response.refresh_token {
+            credentials.refresh_token = Some(refresh_token);
+        }
+        credentials.expires_at = response.expires_in.map(expires_at);
+        if let Some(scope) = response.scope {
+            credentials.scopes = parse_scopes(scope);
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for OAuth2Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("OAuth2Token")
+            .field(&*self.credentials.read().unwrap())
+            .finish()
+    }
+}
+
+impl Serialize for OAuth2Token {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.credentials().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for OAuth2Token {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        OAuth2Credentials::deserialize(deserializer).map(OAuth2Token::new)
+    }
+}
+
+/// The response from the `oauth2/token` endpoint.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<i64>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+/// Turns an error status from the token endpoint into `Error::RefreshRejected`. Twitter answers a
+/// refresh token that was already used or revoked with `400 Bad Request` and an `invalid_request`
+/// error, rather than `401 Unauthorized`.
+fn refresh_rejected(err: error::Error) -> error::Error {
+    match err {
+        error::Error::BadStatus(status, response)
+            if status == hyper::StatusCode::BAD_REQUEST
+                || status == hyper::StatusCode::UNAUTHORIZED =>
+        {
+            error::Error::RefreshRejected(status, response)
+        }
+        err => err,
+    }
+}
+
+fn expires_at(expires_in: i64) -> DateTime<Utc> {
+    Utc::now() + Duration::seconds(expires_in)
+}
+
+/// Parses the space-separated list of scopes returned by Twitter, skipping unknown ones.
+fn parse_scopes(scope: String) -> Vec<Scope> {
+    scope
+        .split_whitespace()
+        .filter_map(|s| serde_json::from_value(serde_json::Value::String(s.to_string())).ok())
+        .collect()
+}
+
+/// Generates a random string of letters and numbers, which are safe to use unescaped as either a
+/// `state` or a PKCE code verifier.
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::scripted::{Reply, Scripted};
+    use crate::client::Client;
+
+    #[test]
+    fn authorize_url_has_challenge() {
+        let client = OAuth2Client::new("my-client", "https://example.com/callback");
+        let auth = authorize_url(&client, &[Scope::TweetRead, Scope::OfflineAccess]);
+
+        assert_eq!(auth.state.len(), 32);
+        assert_eq!(auth.code_verifier.len(), 64);
+        assert!(auth
+            .url
+            .starts_with("https://twitter.com/i/oauth2/authorize?"));
+        assert!(auth.url.contains("client_id=my-client"));
+        assert!(auth.url.contains("scope=tweet.read%20offline.access"));
+        assert!(auth.url.contains("code_challenge_method=S256"));
+        assert!(auth.url.contains(&format!("state={}", auth.state)));
+
+        let challenge = base64::encode_config(
+            Sha256::digest(auth.code_verifier.as_bytes()),
+            base64::URL_SAFE_NO_PAD,
+        );
+        assert!(auth.url.contains(&format!("code_challenge={}", challenge)));
+        assert!(!auth.url.contains(&auth.code_verifier));
+    }
+
+    #[tokio::test]
+    async fn token_requests_use_the_client() {
+        let transport = Scripted::new()
+            .then(Reply::ok(
+                r#"{"access_token": "access", "refresh_token": "refresh", "expires_in": 7200}"#,
+            ))
+            .then(Reply::ok(
+                r#"{"access_token": "new-access", "refresh_token": "new-refresh"}"#,
+            ))
+            .otherwise(Reply::ok("{}"));
+        let http = Client::new(Token::Bearer(String::new()))
+            .with_endpoints(crate::client::Endpoints::with_base("http://127.0.0.1:8080"))
+            .with_transport(transport.clone());
+        let client = OAuth2Client::new("my-client", "https://example.com/callback");
+
+        let token = match access_token(&client, "code", "verifier", &http).await.unwrap() {
+            Token::OAuth2User(token) => token,
+            other => panic!("unexpected token: {:?}", other),
+        };
+        assert_eq!(token.access_token(), "access");
+        token.refresh(&http).await.unwrap();
+        assert_eq!(token.access_token(), "new-access");
+        revoke(&token, &http).await.unwrap();
+
+        let sent = transport.sent();
+        let uris = sent.iter().map(|sent| sent.uri.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            uris,
+            [
+                "http://127.0.0.1:8080/2/oauth2/token",
+                "http://127.0.0.1:8080/2/oauth2/token",
+                "http://127.0.0.1:8080/2/oauth2/revoke",
+                "http://127.0.0.1:8080/2/oauth2/revoke",
+            ]
+        );
+        assert!(sent[0].body_str().contains("code_verifier=verifier"));
+        assert!(sent[1].body_str().contains("refresh_token=refresh"));
+        assert!(sent.iter().all(|sent| sent.header("authorization").is_none()));
+    }
+
+    #[tokio::test]
+    async fn expired_tokens_are_refreshed() {
+        let token = OAuth2Token::new(OAuth2Credentials {
+            client: OAuth2Client::new("my-client", "https://example.com/callback"),
+            access_token: "old-access".to_string(),
+            refresh_token: Some("old-refresh".to_string()),
+            expires_at: Some(Utc::now() - Duration::seconds(1)),
+            scopes: vec![Scope::TweetRead],
+        });
+        assert!(token.is_expired());
+
+        let transport = Scripted::new()
+            .then(Reply::ok(
+                r#"{
+                    "token_type": "bearer",
+                    "expires_in": 7200,
+                    "access_token": "new-access",
+                    "refresh_token": "new-refresh",
+                    "scope": "tweet.read offline.access"
+                }"#,
+            ))
+            .otherwise(Reply::ok("{}"));
+        let client =
+            Client::new(Token::OAuth2User(token.clone())).with_transport(transport.clone());
+
+        let req = client.request_get(links::v2::search::RECENT, None);
+        client.response_empty(req).await.unwrap();
+        let req = client.request_get(links::v2::search::RECENT, None);
+        client.response_empty(req).await.unwrap();
+
+        let sent = transport.sent();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[0].uri, "https://api.twitter.com/2/oauth2/token");
+        assert!(sent[0].header("authorization").is_none());
+        assert!(sent[0].body_str().contains("refresh_token=old-refresh"));
+        for sent in &sent[1..] {
+            assert_eq!(sent.header("authorization"), Some("Bearer new-access"));
+        }
+        assert!(!token.is_expired());
+        let credentials = token.credentials();
+        assert_eq!(credentials.access_token, "new-access");
+        assert_eq!(credentials.refresh_token.as_deref(), Some("new-refresh"));
+        assert_eq!(credentials.scopes, [Scope::TweetRead, Scope::OfflineAccess]);
+
+        let saved = serde_json::to_string(&Token::OAuth2User(token)).unwrap();
+        match serde_json::from_str::<Token>(&saved).unwrap() {
+            Token::OAuth2User(token) => assert_eq!(token.access_token(), "new-access"),
+            other => panic!("unexpected token: {:?}", other),
+        }
+    }
+}
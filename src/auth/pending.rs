@@ -0,0 +1,295 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Keeping track of sign-ins that are waiting for Twitter's redirect.
+//!
+//! When a website signs a user in, the request token from [`request_token`] has to be kept
+//! somewhere between sending the user to Twitter and receiving the redirect back, since its secret
+//! is needed to get the access token. If the site runs as several processes, the redirect may
+//! arrive at a different process than the one that sent the user away, so the request token needs
+//! to be saved somewhere they can all reach.
+//!
+//! [`request_token`]: ../fn.request_token.html
+//!
+//! [`SignInFlow`] handles this. Its `begin` method gets a request token and saves it in a
+//! [`PendingStore`] as a [`PendingAuthorization`], keyed by the request token's key. When Twitter
+//! redirects back with `oauth_token` and `oauth_verifier` in the query string, `complete` looks up
+//! the saved request token and finishes signing in. Saved request tokens expire after a while, so
+//! abandoned sign-ins don't pile up, and each one can only be completed once.
+//!
+//! [`SignInFlow`]: struct.SignInFlow.html
+//! [`PendingStore`]: trait.PendingStore.html
+//! [`PendingAuthorization`]: struct.PendingAuthorization.html
+//!
+//! egg-mode includes [`MemoryPendingStore`], which only works if every request reaches the same
+//! process. Sites with several processes can implement `PendingStore` on top of a shared database
+//! or cache, saving `PendingAuthorization`s with serde.
+//!
+//! [`MemoryPendingStore`]: struct.MemoryPendingStore.html
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # #[tokio::main]
+//! # async fn main() {
+//! use egg_mode::auth::pending::{MemoryPendingStore, SignInFlow};
+//!
+//! let con_token = egg_mode::KeyPair::new("consumer key", "consumer secret");
+//! let flow = SignInFlow::new(con_token, "https://myapp.io/auth", MemoryPendingStore::new());
+//!
+//! // when the user clicks "Sign In With Twitter"
+//! let pending = flow.begin().await.unwrap();
+//! // redirect the user to pending.authenticate_url()
+//!
+//! // when Twitter redirects the user to https://myapp.io/auth
+//! # let (oauth_token, oauth_verifier) = ("", "");
+//! let (token, user_id, screen_name) = flow.complete(oauth_token, oauth_verifier).await.unwrap();
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+use super::{KeyPair, Token};
+
+/// How long a `PendingAuthorization` can be completed for, unless `SignInFlow::with_expiry` says
+/// otherwise.
+pub const DEFAULT_EXPIRY: Duration = Duration::from_secs(15 * 60);
+
+/// A sign-in that's waiting for Twitter to redirect the user back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingAuthorization {
+    /// The request token Twitter gave for this sign-in.
+    pub request_token: KeyPair,
+    /// When the sign-in was started.
+    pub created_at: DateTime<Utc>,
+    /// When the sign-in can no longer be completed.
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PendingAuthorization {
+    /// Creates a new `PendingAuthorization` for the given request token, which expires after the
+    /// given amount of time.
+    pub fn new(request_token: KeyPair, expiry: Duration) -> PendingAuthorization {
+        let created_at = Utc::now();
+        let expires_at = chrono::Duration::from_std(expiry)
+            .ok()
+            .and_then(|expiry| created_at.checked_add_signed(expiry))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        PendingAuthorization {
+            request_token,
+            created_at,
+            expires_at,
+        }
+    }
+
+    /// Returns the `oauth_token` Twitter will send back in its redirect, which is the key of the
+    /// request token.
+    pub fn oauth_token(&self) -> &str {
+        &self.request_token.key
+    }
+
+    /// Returns whether this sign-in can no longer be completed.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    /// Returns the URL to send the user to, to approve your app. See [`authorize_url`] for more
+    /// information.
+    ///
+    /// [`authorize_url`]: ../fn.authorize_url.html
+    pub fn authorize_url(&self) -> String {
+        super::authorize_url(&self.request_token)
+    }
+
+    /// Returns the URL to send the user to with "Sign In With Twitter". See
+    /// [`authenticate_url`] for more information.
+    ///
+    /// [`authenticate_url`]: ../fn.authenticate_url.html
+    pub fn authenticate_url(&self) -> String {
+        super::authenticate_url(&self.request_token)
+    }
+}
+
+/// Somewhere to save sign-ins that are waiting for Twitter's redirect, keyed by their
+/// `oauth_token`.
+///
+/// Stores don't need to check whether a `PendingAuthorization` has expired, since `SignInFlow`
+/// checks that itself, but they should delete expired ones now and then so they don't fill up.
+pub trait PendingStore: Send + Sync {
+    /// Saves the given sign-in.
+    fn insert(&self, pending: &PendingAuthorization) -> Result<()>;
+
+    /// Removes the sign-in with the given `oauth_token` and returns it, if there is one.
+    fn take(&self, oauth_token: &str) -> Result<Option<PendingAuthorization>>;
+}
+
+/// A `PendingStore` that keeps sign-ins in memory.
+///
+/// Expired sign-ins are deleted whenever a new one is saved. Clones of a `MemoryPendingStore` share
+/// the same sign-ins.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryPendingStore {
+    pending: Arc<Mutex<HashMap<String, PendingAuthorization>>>,
+}
+
+impl MemoryPendingStore {
+    /// Creates a new, empty `MemoryPendingStore`.
+    pub fn new() -> MemoryPendingStore {
+        MemoryPendingStore::default()
+    }
+}
+
+impl PendingStore for MemoryPendingStore {
+    fn insert(&self, pending: &PendingAuthorization) -> Result<()> {
+        let mut map = self.pending.lock().unwrap();
+        map.retain(|_, p| !p.is_expired());
+        map.insert(pending.oauth_token().to_string(), pending.clone());
+        Ok(())
+    }
+
+    fn take(&self, oauth_token: &str) -> Result<Option<PendingAuthorization>> {
+        Ok(self.pending.lock().unwrap().remove(oauth_token))
+    }
+}
+
+/// Signs users in through Twitter's redirect, saving sign-ins in a `PendingStore` in between.
+///
+/// For more information, see the [module documentation][pending].
+///
+/// [pending]: index.html
+#[derive(Clone)]
+pub struct SignInFlow {
+    con_token: KeyPair,
+    callback: String,
+    store: Arc<dyn PendingStore>,
+    expiry: Duration,
+}
+
+impl SignInFlow {
+    /// Creates a new `SignInFlow` that uses the given consumer token and callback URL, and saves
+    /// sign-ins in the given store.
+    pub fn new(
+        con_token: KeyPair,
+        callback: impl Into<String>,
+        store: impl PendingStore + 'static,
+    ) -> SignInFlow {
+        SignInFlow {
+            con_token,
+            callback: callback.into(),
+            store: Arc::new(store),
+            expiry: DEFAULT_EXPIRY,
+        }
+    }
+
+    /// Sets how long a sign-in can be completed for after it begins. The default is
+    /// `DEFAULT_EXPIRY`, 15 minutes.
+    pub fn with_expiry(self, expiry: Duration) -> Self {
+        SignInFlow { expiry, ..self }
+    }
+
+    /// Gets a request token from Twitter and saves it in the store.
+    ///
+    /// The user should then be sent to the returned sign-in's `authenticate_url` or
+    /// `authorize_url`.
+    pub async fn begin(&self) -> Result<PendingAuthorization> {
+        let request_token = super::request_token(&self.con_token, self.callback.as_str()).await?;
+        let pending = PendingAuthorization::new(request_token, self.expiry);
+        self.store.insert(&pending)?;
+        Ok(pending)
+    }
+
+    /// Finishes the sign-in with the given `oauth_token`, using the `oauth_verifier` Twitter sent
+    /// along with it.
+    ///
+    /// The sign-in is removed from the store before calling [`access_token`], so it can't be
+    /// completed twice. If there isn't a sign-in saved for `oauth_token`, or it has expired, this
+    /// returns `Error::InvalidResponse` with the `oauth_token`.
+    ///
+    /// On success, this returns the same thing as `access_token`: the access token, the ID of the
+    /// user who signed in, and their screen name.
+    ///
+    /// [`access_token`]: ../fn.access_token.html
+    pub async fn complete(
+        &self,
+        oauth_token: &str,
+        oauth_verifier: &str,
+    ) -> Result<(Token, u64, String)> {
+        let pending = self.take(oauth_token)?;
+        super::access_token(
+            self.con_token.clone(),
+            &pending.request_token,
+            oauth_verifier,
+        )
+        .await
+    }
+
+    fn take(&self, oauth_token: &str) -> Result<PendingAuthorization> {
+        match self.store.take(oauth_token)? {
+            Some(pending) if !pending.is_expired() => Ok(pending),
+            Some(_) => Err(Error::InvalidResponse(
+                "sign-in for oauth_token has expired",
+                Some(oauth_token.to_string()),
+            )),
+            None => Err(Error::InvalidResponse(
+                "no sign-in is waiting for oauth_token",
+                Some(oauth_token.to_string()),
+            )),
+        }
+    }
+}
+
+impl std::fmt::Debug for SignInFlow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignInFlow")
+            .field("con_token", &self.con_token)
+            .field("callback", &self.callback)
+            .field("expiry", &self.expiry)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_sign_ins_expire() {
+        let store = MemoryPendingStore::new();
+        let flow = SignInFlow::new(KeyPair::new("key", "secret"), "oob", store.clone());
+
+        let pending = PendingAuthorization::new(KeyPair::new("fresh", "secret"), DEFAULT_EXPIRY);
+        let json = serde_json::to_string(&pending).unwrap();
+        let pending: PendingAuthorization = serde_json::from_str(&json).unwrap();
+        assert!(!pending.is_expired());
+        store.insert(&pending).unwrap();
+
+        let stale =
+            PendingAuthorization::new(KeyPair::new("stale", "secret"), Duration::from_secs(0));
+        assert!(stale.is_expired());
+        store.insert(&stale).unwrap();
+
+        assert_eq!(flow.take("fresh").unwrap().request_token.secret, "secret");
+        // each sign-in can only be completed once
+        assert!(matches!(
+            flow.take("fresh"),
+            Err(Error::InvalidResponse(_, Some(_)))
+        ));
+        assert!(matches!(
+            flow.take("stale"),
+            Err(Error::InvalidResponse(_, Some(_)))
+        ));
+
+        // expired sign-ins are cleared out when new ones are saved
+        store.insert(&stale).unwrap();
+        store.insert(&pending).unwrap();
+        assert!(store.take("stale").unwrap().is_none());
+    }
+}
@@ -4,20 +4,13 @@
 
 //! Internal mechanisms for the `auth` module.
 
-use std::borrow::Cow;
-use std::collections::BTreeMap;
-use std::fmt;
-use std::time::{SystemTime, UNIX_EPOCH};
-
 use base64;
-use hmac::{Hmac, Mac, NewMac};
 use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
 use hyper::{Body, Method, Request};
-use rand::{self, Rng};
-use sha1::Sha1;
 
 use crate::common::*;
 
+use super::oauth1::{OAuthAddOn, Signer};
 use super::{Token, KeyPair};
 
 // n.b. this type is exported in `raw::auth` - these docs are public!
@@ -32,6 +25,7 @@ pub struct RequestBuilder<'a> {
     query: Option<String>,
     body: Option<(Body, &'static str)>,
     addon: OAuthAddOn,
+    signer: Signer,
 }
 
 impl<'a> RequestBuilder<'a> {
@@ -44,6 +38,7 @@ impl<'a> RequestBuilder<'a> {
             query: None,
             body: None,
             addon: OAuthAddOn::None,
+            signer: Signer::new(),
         }
     }
 
@@ -140,15 +135,29 @@ impl<'a> RequestBuilder<'a> {
         }
     }
 
+    /// Uses the given `Signer` to sign this request with OAuth 1.0a, instead of one using the
+    /// system time and a random nonce.
+    pub fn with_signer(self, signer: Signer) -> Self {
+        RequestBuilder {
+            signer,
+            ..self
+        }
+    }
+
     /// Formats this `RequestBuilder` into a complete `Request`, signing it with the given keys.
     ///
     /// While the `token` parameter is an Option here, it should only be `None` when generating a
     /// request token; all other calls must have two sets of keys (or be authenticated in a
     /// different way, i.e. a Bearer token).
     pub fn request_keys(self, consumer_key: &KeyPair, token: Option<&KeyPair>) -> Request<Body> {
-        let oauth = OAuthParams::from_keys(consumer_key.clone(), token.cloned())
-            .with_addon(self.addon.clone())
-            .sign_request(self.method.clone(), self.base_uri, self.params.as_ref());
+        let oauth = self.signer.sign(
+            &self.method,
+            self.base_uri,
+            self.params.as_ref(),
+            consumer_key,
+            token,
+            &self.addon,
+        );
         self.request_authorization(oauth.to_string())
     }
 
@@ -159,10 +168,16 @@ impl<'a> RequestBuilder<'a> {
     ///
     /// If the given `Token` is a Bearer token, the request will be authenticated using OAuth 2.0,
     /// specifying the given Bearer token as authorization.
+    ///
+    /// If the given `Token` is an OAuth 2.0 user token, its current access token will be given as
+    /// a Bearer token the same way.
     pub fn request_token(self, token: &Token) -> Request<Body> {
         match token {
             Token::Access { consumer, access } => self.request_keys(consumer, Some(access)),
             Token::Bearer(bearer) => self.request_authorization(format!("Bearer {}", bearer)),
+            Token::OAuth2User(token) => {
+                self.request_authorization(format!("Bearer {}", token.access_token()))
+            }
         }
     }
 
@@ -181,19 +196,31 @@ impl<'a> RequestBuilder<'a> {
         self.request_authorization(bearer_request(consumer_key))
     }
 
+    /// Formats this `RequestBuilder` into a complete `Request` without an Authorization header,
+    /// for OAuth 2.0 public clients that only identify themselves with their client ID.
+    pub(crate) fn request_unauthenticated(self) -> Request<Body> {
+        self.assemble(None)
+    }
+
     /// Assembles the final `Request` with the given Authorization header. This is private to
     /// require that a well-formed header is constructed given, as constructed from the other
     /// `request_*` methods.
     fn request_authorization(self, authorization: String) -> Request<Body> {
+        self.assemble(Some(authorization))
+    }
+
+    fn assemble(self, authorization: Option<String>) -> Request<Body> {
         let full_url = if let Some(query) = self.query {
             format!("{}?{}", self.base_uri, query)
         } else {
             self.base_uri.to_string()
         };
-        let request = Request::builder()
+        let mut request = Request::builder()
             .method(self.method)
-            .uri(full_url)
-            .header(AUTHORIZATION, authorization);
+            .uri(full_url);
+        if let Some(authorization) = authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
 
         if let Some((body, content)) = self.body {
             request.header(CONTENT_TYPE, content)
@@ -204,202 +231,6 @@ impl<'a> RequestBuilder<'a> {
     }
 }
 
-/// OAuth header set used to create an OAuth signature.
-#[derive(Clone, Debug)]
-struct OAuthParams {
-    /// The consumer key that represents the app making the API request.
-    consumer_key: KeyPair,
-    /// The token that represents the user authorizing the request (or the access request
-    /// representing a user authorizing the app).
-    token: Option<KeyPair>,
-    /// A random token representing the request itself. Used to de-duplicate requests on Twitter's
-    /// end.
-    nonce: String,
-    /// A Unix timestamp for when the request was created.
-    timestamp: u64,
-    /// A callback or verifier parameter, if necessary.
-    addon: OAuthAddOn,
-}
-
-impl OAuthParams {
-    /// Creates an empty `OAuthParams` header with a new `timestamp` and `nonce`.
-    ///
-    /// **Note**: This should only be used as part of another constructor that populates the tokens!
-    /// Attempting to sign a request with an empty consumer and access token will result in an
-    /// invalid request.
-    fn empty() -> OAuthParams {
-        let timestamp = match SystemTime::now().duration_since(UNIX_EPOCH) {
-            Ok(dur) => dur,
-            Err(err) => err.duration(),
-        }
-        .as_secs();
-        let mut rng = rand::thread_rng();
-        let nonce = ::std::iter::repeat(())
-            .map(|()| rng.sample(rand::distributions::Alphanumeric))
-            .map(char::from)
-            .take(32)
-            .collect::<String>();
-        OAuthParams {
-            consumer_key: KeyPair::empty(),
-            token: None,
-            nonce,
-            timestamp,
-            addon: OAuthAddOn::None,
-        }
-    }
-
-    /// Creates a new `OAuthParams` header with the given keys. The `token` is optional
-    /// specifically for when you're generating a request token; otherwise it should be the request
-    /// token (for when you're generating an access token) or an access token (for when you're
-    /// requesting a regular API function).
-    fn from_keys(consumer_key: KeyPair, token: Option<KeyPair>) -> OAuthParams {
-        OAuthParams {
-            consumer_key,
-            token,
-            ..OAuthParams::empty()
-        }
-    }
-
-    /// Adds the given callback or verifier to this `OAuthParams` header.
-    fn with_addon(self, addon: OAuthAddOn) -> OAuthParams {
-        OAuthParams {
-            addon,
-            ..self
-        }
-    }
-
-    /// Uses the parameters in this `OAuthParams` instance to generate a signature for the given
-    /// request, returning it as a `SignedHeader`.
-    fn sign_request(self, method: Method, uri: &str, params: Option<&ParamList>) -> SignedHeader {
-        let query_string = {
-            let sig_params = params
-                .cloned()
-                .unwrap_or_default()
-                .add_param("oauth_consumer_key", self.consumer_key.key.clone())
-                .add_param("oauth_nonce", self.nonce.clone())
-                .add_param("oauth_signature_method", "HMAC-SHA1")
-                .add_param("oauth_timestamp", format!("{}", self.timestamp.clone()))
-                .add_param("oauth_version", "1.0")
-                .add_opt_param("oauth_token", self.token.clone().map(|k| k.key))
-                .add_opt_param("oauth_callback", self.addon.as_callback().map(|s| s.to_string()))
-                .add_opt_param("oauth_verifier", self.addon.as_verifier().map(|s| s.to_string()));
-
-            let mut query = sig_params
-                .iter()
-                .map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(v)))
-                .collect::<Vec<_>>();
-            query.sort();
-
-            query.join("&")
-        };
-
-        let base_str = format!(
-            "{}&{}&{}",
-            percent_encode(method.as_str()),
-            percent_encode(uri),
-            percent_encode(&query_string)
-        );
-        let key = format!(
-            "{}&{}",
-            percent_encode(&self.consumer_key.secret),
-            percent_encode(&self.token.as_ref().unwrap_or(&KeyPair::new("", "")).secret)
-        );
-
-        // TODO check if key is correct length? Can this fail?
-        let mut digest = Hmac::<Sha1>::new_varkey(key.as_bytes()).expect("Wrong key length");
-        digest.update(base_str.as_bytes());
-
-        let mut params: BTreeMap<&'static str, Cow<'static, str>> = BTreeMap::new();
-        params.insert("oauth_signature_method", "HMAC-SHA1".into());
-        params.insert("oauth_version", "1.0".into());
-
-        params.insert("oauth_consumer_key", self.consumer_key.key);
-        if let Some(token) = self.token {
-            params.insert("oauth_token", token.key);
-        }
-
-        params.insert("oauth_nonce", self.nonce.into());
-        params.insert("oauth_timestamp", self.timestamp.to_string().into());
-
-        match self.addon {
-            OAuthAddOn::Callback(c) => {
-                params.insert("oauth_callback", c.into());
-            }
-            OAuthAddOn::Verifier(v) => {
-                params.insert("oauth_verifier", v.into());
-            }
-            OAuthAddOn::None => (),
-        }
-
-        params.insert("oauth_signature", base64::encode(&digest.finalize().into_bytes()).into());
-
-        SignedHeader {
-            params,
-        }
-    }
-}
-
-/// Represents an "addon" to an OAuth header.
-#[derive(Clone, Debug)]
-enum OAuthAddOn {
-    /// An `oauth_callback` parameter, used when generating a request token.
-    Callback(String),
-    /// An `oauth_verifier` parameter, used when generating an access token.
-    Verifier(String),
-    /// Neither an `oauth_callback` nor an `oauth_verifier` parameter are present in this header.
-    /// This is the default used when signing a regular API request.
-    None,
-}
-
-impl OAuthAddOn {
-    /// Returns the `oauth_callback` parameter, if present.
-    fn as_callback(&self) -> Option<&str> {
-        match self {
-            OAuthAddOn::Callback(c) => Some(c),
-            _ => None,
-        }
-    }
-
-    /// Returns the `oauth_verifier` parameter, if present.
-    fn as_verifier(&self) -> Option<&str> {
-        match self {
-            OAuthAddOn::Verifier(v) => Some(v),
-            _ => None,
-        }
-    }
-}
-
-/// A set of `OAuthParams` parameters combined with a request signature, ready to be attached to a
-/// request.
-struct SignedHeader {
-    /// The OAuth parameters used to create the signature.
-    params: BTreeMap<&'static str, Cow<'static, str>>,
-}
-
-/// The `Display` impl for `SignedHeader` formats it as an `Authorization` header for an HTTP
-/// request.
-impl fmt::Display for SignedHeader {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // authorization scheme
-        write!(f, "OAuth ")?;
-
-        // authorization data
-
-        let mut first = true;
-        for (k, v) in &self.params {
-            if first {
-                first = false;
-            } else {
-                write!(f, ", ")?;
-            }
-
-            write!(f, "{}=\"{}\"", k, percent_encode(v))?;
-        }
-
-        Ok(())
-    }
-}
-
 /// Creates a basic `Authorization` header based on the given consumer token.
 ///
 /// The authorization created by this function can only be used with requests to generate or
@@ -0,0 +1,546 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Saving tokens between runs of a program.
+//!
+//! Once a user has signed in, their `Token` can be saved and used again until they revoke it, so
+//! they don't need to go through the sign-in process every time your program starts. The
+//! [`TokenStore`] trait describes somewhere tokens can be saved, keyed by a name for each account,
+//! along with what egg-mode knows about the account. egg-mode includes two implementations:
+//! [`JsonFileStore`], which saves tokens to a JSON file, and [`MemoryStore`], which keeps them in
+//! memory for as long as the program runs.
+//!
+//! [`TokenStore`]: trait.TokenStore.html
+//! [`JsonFileStore`]: struct.JsonFileStore.html
+//! [`MemoryStore`]: struct.MemoryStore.html
+//!
+//! The [`load_verified`] function ties this together: it loads the saved token for an account,
+//! checks with Twitter that it still works, and if there isn't one or Twitter rejects it, calls
+//! back into your code to sign the user in again, saving the new token.
+//!
+//! [`load_verified`]: fn.load_verified.html
+//!
+//! OAuth 2.0 user tokens need a little more care. A `Client` refreshes an expired `OAuth2User`
+//! token on its own, and Twitter only allows each refresh token to be used once, so after the first
+//! refresh the credentials in the store no longer work. The new credentials are shared with every
+//! clone of the token, including the one in the `StoredToken` returned by `load_verified`, so save
+//! that `StoredToken` again once you're done making calls, like in the example below. Otherwise,
+//! the user will need to sign in again the next time your program starts.
+//!
+//! **Note** that tokens work like passwords, and `JsonFileStore` saves them unencrypted. On Unix,
+//! the file is created so that only its owner can read it, but it should still be kept out of
+//! source control and anywhere else it could be seen.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # #[tokio::main]
+//! # async fn main() {
+//! use egg_mode::auth::store::{self, JsonFileStore, TokenStore};
+//!
+//! let con_token = egg_mode::KeyPair::new("consumer key", "consumer secret");
+//! let store = JsonFileStore::new("tokens.json");
+//!
+//! let saved = store::load_verified(&store, "default", || async {
+//!     let request_token = egg_mode::auth::request_token(&con_token, "oob").await?;
+//!     println!("Sign in at {}", egg_mode::auth::authorize_url(&request_token));
+//!     let pin = "1234567"; // read the PIN from the user here
+//!     let (token, _, _) =
+//!         egg_mode::auth::access_token(con_token.clone(), &request_token, pin).await?;
+//!     Ok(token)
+//! })
+//! .await
+//! .unwrap();
+//!
+//! println!("Welcome, @{}!", saved.screen_name.as_ref().unwrap());
+//!
+//! // ...make some calls with saved.token...
+//!
+//! // OAuth 2.0 user tokens may have been refreshed, so save the new credentials
+//! store.save("default", &saved).unwrap();
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::*;
+use crate::error::Result;
+
+use super::{AccessLevel, Token};
+
+/// A saved `Token`, along with what's known about the account it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredToken {
+    /// The token itself.
+    pub token: Token,
+    /// The numeric ID of the account, if known.
+    #[serde(default)]
+    pub user_id: Option<u64>,
+    /// The screen name of the account, if known.
+    #[serde(default)]
+    pub screen_name: Option<String>,
+    /// The permissions the token was granted, if known.
+    #[serde(default)]
+    pub access_level: Option<AccessLevel>,
+}
+
+impl StoredToken {
+    /// Creates a new `StoredToken` for the given token, without any information about its account.
+    pub fn new(token: Token) -> StoredToken {
+        StoredToken {
+            token,
+            user_id: None,
+            screen_name: None,
+            access_level: None,
+        }
+    }
+}
+
+/// Somewhere to save tokens between runs of a program.
+///
+/// Tokens are saved under a name for each account, which can be anything that makes sense to your
+/// program, like the user's screen name, or a fixed name like `"default"` if it only signs in one
+/// account.
+///
+/// These methods are called from async code, so they should return quickly. Stores that need to
+/// do slow work, like reaching a database over the network, can keep a cache in memory and save
+/// to the backing store in the background.
+pub trait TokenStore: Send + Sync {
+    /// Loads the token saved for the given account, if there is one.
+    fn load(&self, account: &str) -> Result<Option<StoredToken>>;
+
+    /// Saves the given token for the given account, replacing any that was saved before.
+    fn save(&self, account: &str, token: &StoredToken) -> Result<()>;
+
+    /// Removes the token saved for the given account, if there is one.
+    fn remove(&self, account: &str) -> Result<()>;
+
+    /// Returns the names of all the accounts that have a token saved.
+    fn accounts(&self) -> Result<Vec<String>>;
+}
+
+/// A `TokenStore` that saves tokens to a JSON file.
+///
+/// The whole file is read for each call to `load`, and written again for each call to `save` or
+/// `remove`. Writes go to a temporary file next to the real one first, which then replaces it, so
+/// the file isn't left half-written if the program stops partway through.
+#[derive(Debug, Clone)]
+pub struct JsonFileStore {
+    path: PathBuf,
+    /// Held while reading and writing the file, so that saves from different tasks don't
+    /// overwrite each other.
+    lock: Arc<Mutex<()>>,
+}
+
+impl JsonFileStore {
+    /// Creates a new `JsonFileStore` that saves tokens to the given file. The file will be created
+    /// the first time a token is saved.
+    pub fn new(path: impl Into<PathBuf>) -> JsonFileStore {
+        JsonFileStore {
+            path: path.into(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Returns the path of the file tokens are saved to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, StoredToken>> {
+        match fs::read(&self.path) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write(&self, tokens: &BTreeMap<String, StoredToken>) -> Result<()> {
+        let contents = serde_json::to_vec_pretty(tokens)?;
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        io::Write::write_all(&mut options.open(&temp)?, &contents)?;
+        fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl TokenStore for JsonFileStore {
+    fn load(&self, account: &str) -> Result<Option<StoredToken>> {
+        let _lock = self.lock.lock().unwrap();
+        Ok(self.read()?.remove(account))
+    }
+
+    fn save(&self, account: &str, token: &StoredToken) -> Result<()> {
+        let _lock = self.lock.lock().unwrap();
+        let mut tokens = self.read()?;
+        tokens.insert(account.to_string(), token.clone());
+        self.write(&tokens)
+    }
+
+    fn remove(&self, account: &str) -> Result<()> {
+        let _lock = self.lock.lock().unwrap();
+        let mut tokens = self.read()?;
+        if tokens.remove(account).is_some() {
+            self.write(&tokens)?;
+        }
+        Ok(())
+    }
+
+    fn accounts(&self) -> Result<Vec<String>> {
+        let _lock = self.lock.lock().unwrap();
+        Ok(self.read()?.into_keys().collect())
+    }
+}
+
+/// A `TokenStore` that keeps tokens in memory.
+///
+/// Tokens saved here are lost when the program exits, which makes this mainly useful for tests,
+/// or as a cache in front of another store. Clones of a `MemoryStore` share the same tokens.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    tokens: Arc<Mutex<HashMap<String, StoredToken>>>,
+}
+
+impl MemoryStore {
+    /// Creates a new, empty `MemoryStore`.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+}
+
+impl TokenStore for MemoryStore {
+    fn load(&self, account: &str) -> Result<Option<StoredToken>> {
+        Ok(self.tokens.lock().unwrap().get(account).cloned())
+    }
+
+    fn save(&self, account: &str, token: &StoredToken) -> Result<()> {
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.insert(account.to_string(), token.clone());
+        Ok(())
+    }
+
+    fn remove(&self, account: &str) -> Result<()> {
+        self.tokens.lock().unwrap().remove(account);
+        Ok(())
+    }
+
+    fn accounts(&self) -> Result<Vec<String>> {
+        let mut accounts = self
+            .tokens
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        accounts.sort();
+        Ok(accounts)
+    }
+}
+
+/// Loads the token saved for the given account and checks that it still works, signing the user
+/// in again with the given function if it doesn't.
+///
+/// If a token is saved for the account, it's checked with Twitter the same way as
+/// [`verify_tokens`]. If Twitter accepts it, the information about its account is updated and
+/// saved, and the token is returned. If there isn't a token saved, or Twitter rejects the saved one
+/// as invalid, `reauth` is called to get a new token, which is checked and saved the same way. Any
+/// other error while checking the saved token, like a network error, is returned without calling
+/// `reauth`, so that a connection problem doesn't throw away a working token.
+///
+/// Access tokens are checked with the v1.1 `account/verify_credentials` endpoint, and OAuth 2.0
+/// user tokens are checked with the v2 `users/me` endpoint. Bearer tokens don't belong to an
+/// account, so they're returned as they were saved.
+///
+/// Checking an expired OAuth 2.0 user token refreshes it, and so can any later request made with
+/// it. The refreshed credentials are saved before this returns, but to keep the ones from later
+/// refreshes, save the returned `StoredToken` again after making calls. See the [module
+/// docs][store] for details.
+///
+/// [store]: index.html
+///
+/// [`verify_tokens`]: ../fn.verify_tokens.html
+pub async fn load_verified<F, Fut>(
+    store: &dyn TokenStore,
+    account: &str,
+    reauth: F,
+) -> Result<StoredToken>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Token>>,
+{
+    load_verified_with(store, account, reauth, Client::new).await
+}
+
+/// Implementation of `load_verified` that creates the `Client`s used to check tokens with the given
+/// function.
+async fn load_verified_with<F, Fut>(
+    store: &dyn TokenStore,
+    account: &str,
+    reauth: F,
+    client: impl Fn(Token) -> Client,
+) -> Result<StoredToken>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Token>>,
+{
+    if let Some(saved) = store.load(account)? {
+        match verify(&client(saved.token.clone()), saved.clone()).await {
+            Ok(verified) => {
+                store.save(account, &verified)?;
+                return Ok(verified);
+            }
+            Err(err) if err.is_auth_failure() => {
+                #[cfg(feature = "tracing")]
+                tracing::info!(error = %err, "saved token was rejected, signing in again");
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    let token = reauth().await?;
+    let verified = verify(&client(token.clone()), StoredToken::new(token)).await?;
+    store.save(account, &verified)?;
+    Ok(verified)
+}
+
+/// Checks the given token with Twitter, and fills in what's known about its account.
+async fn verify(client: &Client, saved: StoredToken) -> Result<StoredToken> {
+    if let Token::Bearer(_) = saved.token {
+        return Ok(saved);
+    }
+
+    let verified = super::verify_tokens(client).await?.response;
+    Ok(StoredToken {
+        user_id: Some(verified.id),
+        screen_name: Some(verified.user.screen_name),
+        access_level: verified.access_level.or(saved.access_level),
+        ..saved
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::KeyPair;
+    use crate::client::scripted::{Reply, Scripted};
+    use crate::error::{KnownErrorCode, TwitterErrorCode, TwitterErrors};
+
+    use hyper::StatusCode;
+
+    fn access_token(key: &'static str) -> Token {
+        Token::Access {
+            consumer: KeyPair::new("consumer-key", "consumer-secret"),
+            access: KeyPair::new(key, "access-secret"),
+        }
+    }
+
+    fn verified() -> Reply {
+        let users = crate::common::tests::load_file("sample_payloads/user_array.json");
+        let mut user: serde_json::Value = serde_json::from_str(&users).unwrap();
+        let user = user[0].as_object_mut().unwrap();
+        user.insert("id".to_string(), 1234.into());
+        user.insert("screen_name".to_string(), "egg_mode".into());
+        Reply::ok(serde_json::to_string(user).unwrap()).header("x-access-level", "read-write")
+    }
+
+    fn rejected() -> Reply {
+        let errors = TwitterErrors {
+            errors: vec![TwitterErrorCode {
+                message: "Invalid or expired token.".to_string(),
+                code: KnownErrorCode::InvalidToken.code(),
+            }],
+        };
+        Reply::status(StatusCode::UNAUTHORIZED, serde_json::to_string(&errors).unwrap())
+    }
+
+    #[tokio::test]
+    async fn load_verified_reauths_rejected_tokens() {
+        let transport = Scripted::new()
+            .then(rejected())
+            .then(verified())
+            .then(verified())
+            .then(rejected());
+        let client = |token| Client::new(token).with_transport(transport.clone());
+        let store = MemoryStore::new();
+        store
+            .save("me", &StoredToken::new(access_token("revoked")))
+            .unwrap();
+
+        let saved = load_verified_with(&store, "me", || async { Ok(access_token("good")) }, client)
+            .await
+            .unwrap();
+        assert_eq!(saved.user_id, Some(1234));
+        assert_eq!(saved.screen_name.as_deref(), Some("egg_mode"));
+        assert_eq!(saved.access_level, Some(AccessLevel::ReadWrite));
+        assert_eq!(store.accounts().unwrap(), ["me"]);
+
+        // now that a good token is saved, reauth isn't called
+        let reauth = || async { panic!("reauth called with a good token saved") };
+        let saved = load_verified_with(&store, "me", reauth, client)
+            .await
+            .unwrap();
+        assert_eq!(saved.user_id, Some(1234));
+
+        // a new token that doesn't work is an error
+        let reauth = || async { Ok(access_token("bad")) };
+        let err = load_verified_with(&store, "other", reauth, client)
+            .await
+            .unwrap_err();
+        assert!(err.is_auth_failure());
+        assert!(store.load("other").unwrap().is_none());
+
+        let tokens = transport
+            .sent()
+            .iter()
+            .map(|sent| {
+                let auth = sent.header("authorization").unwrap();
+                let auth = crate::auth::oauth1::SignedHeader::parse(auth).unwrap();
+                auth.token().unwrap().to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, ["revoked", "good", "good", "bad"]);
+    }
+
+    #[tokio::test]
+    async fn load_verified_checks_oauth2_tokens() {
+        use crate::auth::oauth2::{OAuth2Client, OAuth2Credentials, OAuth2Token};
+
+        let transport = Scripted::new()
+            .then(Reply::ok(
+                r#"{"access_token": "new-access", "refresh_token": "new-refresh"}"#,
+            ))
+            .then(Reply::ok(
+                r#"{
+                    "data": {
+                        "id": "1234",
+                        "name": "egg-mode",
+                        "username": "egg_mode",
+                        "created_at": "2013-12-14T04:35:55.000Z",
+                        "profile_image_url": "https://abs.twimg.com/a.png",
+                        "protected": false,
+                        "public_metrics": {
+                            "followers_count": 1,
+                            "following_count": 2,
+                            "tweet_count": 3,
+                            "listed_count": 4
+                        },
+                        "verified": false
+                    }
+                }"#,
+            ));
+        let client = |token| Client::new(token).with_transport(transport.clone());
+        let store = MemoryStore::new();
+        let token = OAuth2Token::new(OAuth2Credentials {
+            client: OAuth2Client::new("my-client", "https://example.com/callback"),
+            access_token: "old-access".to_string(),
+            refresh_token: Some("old-refresh".to_string()),
+            expires_at: Some(chrono::Utc::now()),
+            scopes: vec![],
+        });
+        store
+            .save("me", &StoredToken::new(Token::OAuth2User(token)))
+            .unwrap();
+
+        let reauth = || async { panic!("reauth called with a good token saved") };
+        let saved = load_verified_with(&store, "me", reauth, client)
+            .await
+            .unwrap();
+        assert_eq!(saved.user_id, Some(1234));
+        assert_eq!(saved.screen_name.as_deref(), Some("egg_mode"));
+        assert!(transport.sent()[1]
+            .uri
+            .starts_with("https://api.twitter.com/2/users/me?"));
+
+        // the token was refreshed while checking it, and the new credentials were saved
+        let saved = serde_json::to_value(store.load("me").unwrap().unwrap()).unwrap();
+        assert_eq!(saved["token"]["OAuth2User"]["access_token"], "new-access");
+        assert_eq!(saved["token"]["OAuth2User"]["refresh_token"], "new-refresh");
+    }
+
+    #[tokio::test]
+    async fn load_verified_reauths_when_refresh_is_rejected() {
+        use crate::auth::oauth2::{OAuth2Client, OAuth2Credentials, OAuth2Token};
+
+        // Twitter answers a refresh token that was already used with 400, not 401
+        let used = r#"{
+            "error": "invalid_request",
+            "error_description": "Value passed for the token was invalid."
+        }"#;
+        let transport = Scripted::new()
+            .then(Reply::status(StatusCode::BAD_REQUEST, used))
+            .then(verified());
+        let client = |token| Client::new(token).with_transport(transport.clone());
+        let store = MemoryStore::new();
+        let token = OAuth2Token::new(OAuth2Credentials {
+            client: OAuth2Client::new("my-client", "https://example.com/callback"),
+            access_token: "old-access".to_string(),
+            refresh_token: Some("used-refresh".to_string()),
+            expires_at: Some(chrono::Utc::now()),
+            scopes: vec![],
+        });
+        store
+            .save("me", &StoredToken::new(Token::OAuth2User(token)))
+            .unwrap();
+
+        let reauth = || async { Ok(access_token("good")) };
+        let saved = load_verified_with(&store, "me", reauth, client)
+            .await
+            .unwrap();
+        assert_eq!(saved.user_id, Some(1234));
+        assert!(matches!(saved.token, Token::Access { .. }));
+
+        let sent = transport.sent();
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].body_str().contains("refresh_token=used-refresh"));
+        let stored = store.load("me").unwrap().unwrap();
+        assert!(matches!(stored.token, Token::Access { .. }));
+    }
+
+    #[test]
+    fn json_file_store() {
+        let path =
+            std::env::temp_dir().join(format!("egg-mode-tokens-{}.json", std::process::id()));
+        let store = JsonFileStore::new(&path);
+        assert!(store.load("me").unwrap().is_none());
+
+        let mut saved = StoredToken::new(access_token("good"));
+        saved.screen_name = Some("egg_mode".to_string());
+        store.save("me", &saved).unwrap();
+        store.save("other", &saved).unwrap();
+        store.remove("other").unwrap();
+
+        let store = JsonFileStore::new(&path);
+        assert_eq!(store.accounts().unwrap(), ["me"]);
+        let loaded = store.load("me").unwrap().unwrap();
+        assert_eq!(loaded.screen_name.as_deref(), Some("egg_mode"));
+        match loaded.token {
+            Token::Access { access, .. } => assert_eq!(access.key, "good"),
+            other => panic!("unexpected token: {:?}", other),
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+}
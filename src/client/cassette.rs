@@ -0,0 +1,595 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Record and replay the traffic of a `Client`, for deterministic tests.
+//!
+//! Testing code that talks to Twitter is difficult: it needs live credentials, the responses
+//! change from one run to the next, and there are rate limits to deal with. To help with this,
+//! egg-mode can save every request and response made by a `Client` into a "cassette" file, and
+//! then serve later requests from that file instead of the network.
+//!
+//! Both halves of this are implemented as a [`Transport`], so they work with any `Client`, as well
+//! as the `CursorIter`s, `Timeline`s, and `TwitterStream`s created with it.
+//!
+//! To record a cassette, create a [`Recorder`], give a clone of it to a `Client`, and save it once
+//! you're done:
+//!
+//! ```rust,no_run
+//! # use egg_mode::Token;
+//! # #[tokio::main]
+//! # async fn main() -> egg_mode::error::Result<()> {
+//! # let token: Token = unimplemented!();
+//! use egg_mode::client::Client;
+//! use egg_mode::client::cassette::Recorder;
+//!
+//! let recorder = Recorder::new();
+//! let client = Client::new(token).with_transport(recorder.clone());
+//!
+//! let rustlang = egg_mode::user::show("rustlang", &client).await?;
+//!
+//! recorder.save("tests/cassettes/rustlang.json")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Then, to replay it, load it into a [`Replayer`]:
+//!
+//! ```rust,no_run
+//! # use egg_mode::Token;
+//! # #[tokio::main]
+//! # async fn main() -> egg_mode::error::Result<()> {
+//! # let token: Token = unimplemented!();
+//! use egg_mode::client::Client;
+//! use egg_mode::client::cassette::Replayer;
+//!
+//! let replayer = Replayer::load("tests/cassettes/rustlang.json")?;
+//! let client = Client::new(token).with_transport(replayer);
+//!
+//! let rustlang = egg_mode::user::show("rustlang", &client).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The credentials used when replaying don't need to be valid, since they're never sent anywhere.
+//! When recording, the `Authorization` header of every request is replaced with `[redacted]`
+//! before it's saved, so cassettes can be checked in without leaking your tokens. The same goes for
+//! the tokens and client credentials in the bodies of requests to and responses from the endpoints
+//! that hand out tokens, like the one a `Client` uses to refresh an OAuth 2.0 user token.
+//!
+//! [`Transport`]: ../trait.Transport.html
+//! [`Recorder`]: struct.Recorder.html
+//! [`Replayer`]: struct.Replayer.html
+
+use std::fs;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use hyper::body::Bytes;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, PROXY_AUTHORIZATION};
+use hyper::{Body, Method, Request, StatusCode, Uri};
+use serde::{Deserialize, Serialize};
+
+use crate::common::new_http_client;
+use crate::error::{Error, Result};
+
+use super::{Transport, TransportFuture};
+
+/// The value saved in place of any credentials in a recorded request.
+pub const REDACTED: &str = "[redacted]";
+
+/// A set of recorded requests and responses.
+///
+/// This is the format saved to disk by a [`Recorder`] and loaded by a [`Replayer`]. It's stored as
+/// JSON, so if you need to tweak a response by hand or write one from scratch, you can edit the
+/// file directly, or build a `Cassette` in code and give it to `Replayer::new`.
+///
+/// [`Recorder`]: struct.Recorder.html
+/// [`Replayer`]: struct.Replayer.html
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cassette {
+    /// The requests and responses contained in this cassette, in the order they were completed.
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Loads a `Cassette` from the given JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Cassette> {
+        let contents = fs::read(path)?;
+        Ok(serde_json::from_slice(&contents)?)
+    }
+
+    /// Saves this `Cassette` to the given file as JSON, replacing it if it already exists.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let contents = serde_json::to_vec_pretty(self)?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// A single request and the response that was returned for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The request sent to the server.
+    pub request: RecordedRequest,
+    /// The response returned by the server.
+    pub response: RecordedResponse,
+}
+
+/// A request saved in a `Cassette`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// The HTTP method of the request, like `GET` or `POST`.
+    pub method: String,
+    /// The full URL of the request, including its query string.
+    pub uri: String,
+    /// The headers sent with the request, with any credentials replaced by `[redacted]`.
+    pub headers: Vec<(String, String)>,
+    /// The body of the request.
+    pub body: RecordedBody,
+}
+
+/// A response saved in a `Cassette`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// The HTTP status code of the response.
+    pub status: u16,
+    /// The headers returned with the response.
+    pub headers: Vec<(String, String)>,
+    /// The body of the response.
+    pub body: RecordedBody,
+}
+
+/// The body of a recorded request or response.
+///
+/// Bodies that are valid UTF-8 (which is nearly everything Twitter sends or receives) are saved as
+/// text, so they can be read and edited easily. Anything else is saved as base64.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordedBody {
+    /// A body made up of UTF-8 text.
+    Text(String),
+    /// A body containing binary data, encoded as base64.
+    Base64(String),
+}
+
+impl RecordedBody {
+    fn new(bytes: &[u8]) -> RecordedBody {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => RecordedBody::Text(text.to_string()),
+            Err(_) => RecordedBody::Base64(base64::encode(bytes)),
+        }
+    }
+
+    /// Returns the raw bytes of this body.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            RecordedBody::Text(text) => Ok(text.clone().into_bytes()),
+            RecordedBody::Base64(data) => {
+                base64::decode(data).map_err(|e| Error::TransportError(Box::new(e)))
+            }
+        }
+    }
+}
+
+/// The endpoints whose request and response bodies hold credentials.
+const TOKEN_PATHS: &[&str] = &[
+    "/oauth/request_token",
+    "/oauth/access_token",
+    "/oauth2/token",
+    "/oauth2/invalidate_token",
+    "/oauth2/revoke",
+];
+
+/// The fields of a request or response body to a token endpoint that hold credentials.
+const SECRET_FIELDS: &[&str] = &[
+    "access_token",
+    "client_id",
+    "client_secret",
+    "code",
+    "code_verifier",
+    "oauth_token",
+    "oauth_token_secret",
+    "oauth_verifier",
+    "refresh_token",
+    "token",
+];
+
+fn is_token_endpoint(uri: &Uri) -> bool {
+    TOKEN_PATHS.iter().any(|path| uri.path().ends_with(path))
+}
+
+/// Replaces the values of `SECRET_FIELDS` in the given body, which can either be a JSON object or
+/// a URL-encoded form.
+fn redact_body(body: &[u8]) -> Vec<u8> {
+    let is_secret = |name: &str| SECRET_FIELDS.contains(&name);
+
+    if let Ok(serde_json::Value::Object(mut fields)) = serde_json::from_slice(body) {
+        for (name, value) in fields.iter_mut() {
+            if is_secret(name) {
+                *value = REDACTED.into();
+            }
+        }
+        return serde_json::Value::Object(fields).to_string().into_bytes();
+    }
+
+    String::from_utf8_lossy(body)
+        .split('&')
+        .map(|pair| match pair.split('=').next() {
+            Some(name) if is_secret(name) => format!("{}={}", name, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+        .into_bytes()
+}
+
+fn record_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if name == AUTHORIZATION || name == PROXY_AUTHORIZATION {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.as_str().to_string(), value)
+        })
+        .collect()
+}
+
+/// A `Transport` that records every request and response that passes through it.
+///
+/// A `Recorder` sends requests through another `Transport` - by default, the same HTTP client a
+/// `Client` would use on its own - and keeps a copy of each request and response in a
+/// [`Cassette`]. Cloning a `Recorder` gives you a handle to the same `Cassette`, so you can hand
+/// one clone to a `Client` and keep the other to call `save` when you're done.
+///
+/// Each interaction is added to the cassette once its response body has been read to completion
+/// or dropped. For streams, this means the cassette will hold every message received until the
+/// `TwitterStream` was dropped.
+///
+/// [`Cassette`]: struct.Cassette.html
+#[derive(Clone)]
+pub struct Recorder {
+    inner: Arc<dyn Transport>,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl Recorder {
+    /// Creates a new `Recorder` that sends its requests with egg-mode's default HTTP client.
+    pub fn new() -> Recorder {
+        Recorder::with_transport(new_http_client(None, None))
+    }
+
+    /// Creates a new `Recorder` that sends its requests with the given `Transport`.
+    pub fn with_transport(inner: impl Transport + 'static) -> Recorder {
+        Recorder {
+            inner: Arc::new(inner),
+            cassette: Arc::new(Mutex::new(Cassette::default())),
+        }
+    }
+
+    /// Returns a copy of everything recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    /// Saves everything recorded so far to the given file as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.cassette().save(path)
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Recorder {
+        Recorder::new()
+    }
+}
+
+impl Transport for Recorder {
+    fn send(&self, request: Request<Body>) -> TransportFuture {
+        let inner = self.inner.clone();
+        let cassette = self.cassette.clone();
+
+        Box::pin(async move {
+            // the body needs to be read up front to record it, so rebuild the request afterward
+            let (parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body).await?;
+            let redact = is_token_endpoint(&parts.uri);
+            let recorded = RecordedRequest {
+                method: parts.method.to_string(),
+                uri: parts.uri.to_string(),
+                headers: record_headers(&parts.headers),
+                body: if redact {
+                    RecordedBody::new(&redact_body(&body))
+                } else {
+                    RecordedBody::new(&body)
+                },
+            };
+
+            let response = inner.send(Request::from_parts(parts, Body::from(body))).await?;
+
+            let (parts, body) = response.into_parts();
+            let body = RecordingBody {
+                inner: body,
+                buf: vec![],
+                redact,
+                pending: Some(Interaction {
+                    request: recorded,
+                    response: RecordedResponse {
+                        status: parts.status.as_u16(),
+                        headers: record_headers(&parts.headers),
+                        body: RecordedBody::Text(String::new()),
+                    },
+                }),
+                cassette,
+            };
+
+            Ok(hyper::Response::from_parts(parts, Body::wrap_stream(body)))
+        })
+    }
+}
+
+/// A response body that saves a copy of everything read through it, and adds it to a `Cassette`
+/// once the body is finished or dropped.
+struct RecordingBody {
+    inner: Body,
+    buf: Vec<u8>,
+    /// Whether the credentials in the body need to be redacted before it's saved.
+    redact: bool,
+    pending: Option<Interaction>,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl RecordingBody {
+    fn finish(&mut self) {
+        if let Some(mut interaction) = self.pending.take() {
+            interaction.response.body = if self.redact {
+                RecordedBody::new(&redact_body(&self.buf))
+            } else {
+                RecordedBody::new(&self.buf)
+            };
+
+            if let Ok(mut cassette) = self.cassette.lock() {
+                cassette.interactions.push(interaction);
+            }
+        }
+    }
+}
+
+impl Stream for RecordingBody {
+    type Item = std::result::Result<Bytes, hyper::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.buf.extend_from_slice(&chunk);
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(None) => {
+                self.finish();
+                Poll::Ready(None)
+            }
+            other => other,
+        }
+    }
+}
+
+impl Drop for RecordingBody {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// A `Transport` that answers requests from a `Cassette` instead of the network.
+///
+/// Requests are matched against the recorded interactions by their method and full URL, including
+/// the query string. Each recorded interaction is only used once, and if several were recorded for
+/// the same request, they're handed out in the order they were recorded. This lets a replayed
+/// `CursorIter` or media upload walk through the same sequence of responses it received while
+/// recording.
+///
+/// If a request doesn't match any of the remaining interactions, the `Replayer` returns an
+/// `Error::TransportError` describing the request.
+pub struct Replayer {
+    remaining: Mutex<Vec<Interaction>>,
+}
+
+impl Replayer {
+    /// Creates a new `Replayer` that serves the interactions in the given `Cassette`.
+    pub fn new(cassette: Cassette) -> Replayer {
+        Replayer {
+            remaining: Mutex::new(cassette.interactions),
+        }
+    }
+
+    /// Creates a new `Replayer` from the cassette saved in the given file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Replayer> {
+        Ok(Replayer::new(Cassette::load(path)?))
+    }
+
+    /// Returns the number of recorded interactions that haven't been replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.remaining.lock().unwrap().len()
+    }
+
+    fn take(&self, method: &Method, uri: &str) -> Option<Interaction> {
+        let mut remaining = self.remaining.lock().unwrap();
+        let pos = remaining
+            .iter()
+            .position(|i| i.request.method == method.as_str() && i.request.uri == uri)?;
+        Some(remaining.remove(pos))
+    }
+}
+
+fn replay_response(recorded: RecordedResponse) -> Result<hyper::Response<Body>> {
+    let bad_data = |e: Box<dyn std::error::Error + Send + Sync>| Error::TransportError(e);
+
+    let mut response = hyper::Response::new(Body::from(recorded.body.to_bytes()?));
+    *response.status_mut() = StatusCode::from_u16(recorded.status).map_err(|e| bad_data(e.into()))?;
+
+    let headers = response.headers_mut();
+    for (name, value) in recorded.headers {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| bad_data(e.into()))?;
+        let value = HeaderValue::from_str(&value).map_err(|e| bad_data(e.into()))?;
+        headers.append(name, value);
+    }
+
+    Ok(response)
+}
+
+impl Transport for Replayer {
+    fn send(&self, request: Request<Body>) -> TransportFuture {
+        let method = request.method().clone();
+        let uri = request.uri().to_string();
+        let found = self.take(&method, &uri);
+
+        Box::pin(async move {
+            match found {
+                Some(interaction) => replay_response(interaction.response),
+                None => Err(Error::TransportError(
+                    format!("no recorded response left for {} {}", method, uri).into(),
+                )),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{KeyPair, Token};
+    use crate::client::scripted::{Reply, Scripted};
+    use crate::client::Client;
+    use crate::common::tests::load_file;
+    use crate::common::ParamList;
+
+    use futures::TryStreamExt;
+
+    fn token() -> Token {
+        Token::Access {
+            consumer: KeyPair::new("consumer key", "consumer secret"),
+            access: KeyPair::new("access key", "access secret"),
+        }
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() {
+        let tweet = load_file("sample_payloads/sample-stream.json");
+        let tweet: serde_json::Value = serde_json::from_str(&tweet).unwrap();
+        let transport = Scripted::new()
+            .then(Reply::ok(r#"{"id": 1234}"#).header("x-rate-limit-limit", "15"))
+            .then(Reply::ok(format!("{}\r\n\r\n{}\r\n", tweet, tweet)))
+            .then(Reply::ok(format!("{}\r\n", tweet)));
+        let recorder = Recorder::with_transport(transport);
+        let client = Client::new(token()).with_transport(recorder.clone());
+
+        let req = client.request_get(crate::links::statuses::SHOW, None);
+        let recorded: crate::Response<serde_json::Value> = client.response_json(req).await.unwrap();
+
+        let messages: Vec<_> = crate::stream::sample(&client).try_collect().await.unwrap();
+        assert_eq!(messages.len(), 3);
+
+        // streams opened from a raw request go through the transport too
+        let filter = |client: &Client| {
+            let params = ParamList::new().add_param("track", "rust");
+            client.request_post(crate::links::stream::FILTER, Some(&params))
+        };
+        let raw = crate::raw::response_as_stream(filter(&client), &client);
+        let messages: Vec<_> = raw.try_collect().await.unwrap();
+        assert_eq!(messages.len(), 1);
+
+        let cassette = recorder.cassette();
+        assert_eq!(cassette.interactions.len(), 3);
+        for interaction in &cassette.interactions {
+            let auth = interaction
+                .request
+                .headers
+                .iter()
+                .find(|(name, _)| name == "authorization")
+                .unwrap();
+            assert_eq!(auth.1, REDACTED);
+        }
+
+        let path = format!("egg-mode-cassette-{}.json", std::process::id());
+        let path = std::env::temp_dir().join(path);
+        recorder.save(&path).unwrap();
+        let replayer = Replayer::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let client = Client::new(token()).with_transport(replayer);
+
+        let req = client.request_get(crate::links::statuses::SHOW, None);
+        let replayed: crate::Response<serde_json::Value> = client.response_json(req).await.unwrap();
+        assert_eq!(recorded.response, replayed.response);
+        assert_eq!(replayed.rate_limit_status.limit, 15);
+
+        let replayed: Vec<_> = crate::stream::sample(&client).try_collect().await.unwrap();
+        assert_eq!(replayed.len(), 3);
+
+        let raw = crate::raw::response_as_stream(filter(&client), &client);
+        let replayed: Vec<_> = raw.try_collect().await.unwrap();
+        assert_eq!(replayed.len(), 1);
+
+        // every interaction is used up, so asking again fails
+        let req = client.request_get(crate::links::statuses::SHOW, None);
+        match client.response_raw_bytes(req).await {
+            Err(Error::TransportError(_)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn token_bodies_are_redacted() {
+        use crate::auth::oauth2::{OAuth2Client, OAuth2Credentials, OAuth2Token};
+        use crate::common::ParamList;
+
+        let transport = Scripted::new()
+            .then(Reply::ok(
+                r#"{"access_token": "new-access", "refresh_token": "new-refresh"}"#,
+            ))
+            .then(Reply::ok("{}"))
+            .then(Reply::ok(
+                "oauth_token=access-key&oauth_token_secret=access-secret&screen_name=egg_mode",
+            ));
+        let recorder = Recorder::with_transport(transport);
+
+        // an expired token is refreshed before the request is sent
+        let token = OAuth2Token::new(OAuth2Credentials {
+            client: OAuth2Client::new("my-client", "https://example.com/callback"),
+            access_token: "old-access".to_string(),
+            refresh_token: Some("old-refresh".to_string()),
+            expires_at: Some(chrono::Utc::now()),
+            scopes: vec![],
+        });
+        let client = Client::new(Token::OAuth2User(token)).with_transport(recorder.clone());
+        let req = client.request_get(crate::links::v2::search::RECENT, None);
+        client.response_empty(req).await.unwrap();
+
+        let client = Client::new(self::token()).with_transport(recorder.clone());
+        let params = ParamList::new().add_param("oauth_verifier", "my-verifier");
+        let req = client.request_post(crate::links::auth::ACCESS_TOKEN, Some(&params));
+        client.response_raw_bytes(req).await.unwrap();
+
+        let cassette = recorder.cassette();
+        assert_eq!(cassette.interactions.len(), 3);
+        let saved = serde_json::to_string(&cassette).unwrap();
+        for secret in &[
+            "my-client",
+            "old-refresh",
+            "new-access",
+            "new-refresh",
+            "my-verifier",
+            "access-key",
+            "access-secret",
+        ] {
+            assert!(!saved.contains(secret), "cassette contained {}", secret);
+        }
+        assert!(saved.contains("grant_type=refresh_token"));
+        assert!(saved.contains("screen_name=egg_mode"));
+    }
+}
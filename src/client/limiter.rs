@@ -0,0 +1,255 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hyper::Uri;
+
+use crate::common::*;
+use crate::error::{Error, Result};
+
+/// Keeps track of the rate limits seen by a `Client`, and holds off on requests until their rate
+/// limit window resets.
+///
+/// Every response from Twitter carries [rate-limit information][RateLimit] for the endpoint that
+/// was called. Normally, egg-mode hands this to you in a `Response` and leaves it up to you to
+/// stop calling that endpoint once you've used up its limit. If you give a `RateLimiter` to a
+/// `Client` with [`Client::with_rate_limiter`], it will remember the last rate limit it saw for
+/// each endpoint, and the next time you call an endpoint that has run out of calls, it will wait
+/// until the rate limit resets before sending the request. This means a `CursorIter` or
+/// `Timeline` created with that `Client` can page through large collections without running into
+/// a rate-limit error, at the cost of pausing for up to 15 minutes at a time.
+///
+/// Rate limits are tracked per "resource", which is the path of the endpoint without its `/1.1`
+/// prefix, file extension, or any IDs embedded in it - for example, `/statuses/home_timeline` or
+/// `/statuses/show`. These match the keys returned by [`service::rate_limit_status`], so a
+/// `RateLimiter` can be seeded with the current state of every rate limit by calling
+/// [`load_status`].
+///
+/// A `RateLimiter` starts out knowing nothing, so the first call to each endpoint will always be
+/// sent right away. Cloning a `RateLimiter` creates a handle to the same set of rate limits, so
+/// clones of it can be shared between `Client`s that use the same `Token`. Since Twitter tracks
+/// rate limits separately for each user and application, don't share a `RateLimiter` between
+/// `Client`s that use different tokens.
+///
+/// [RateLimit]: ../struct.RateLimit.html
+/// [`Client::with_rate_limiter`]: struct.Client.html#method.with_rate_limiter
+/// [`service::rate_limit_status`]: ../service/fn.rate_limit_status.html
+/// [`load_status`]: #method.load_status
+#[derive(Clone, Debug, Default)]
+pub struct RateLimiter {
+    limits: Arc<Mutex<HashMap<String, RateLimit>>>,
+}
+
+impl RateLimiter {
+    /// Creates a new, empty `RateLimiter`.
+    pub fn new() -> RateLimiter {
+        RateLimiter::default()
+    }
+
+    /// Returns the last rate limit seen for the given resource, like `/statuses/home_timeline`.
+    ///
+    /// Once the reset time of a rate limit has passed, this returns `None` until that resource is
+    /// called again.
+    pub fn get(&self, resource: &str) -> Option<RateLimit> {
+        let limits = self.limits.lock().unwrap();
+        limits
+            .get(resource)
+            .filter(|limit| i64::from(limit.reset) > now())
+            .copied()
+    }
+
+    /// Loads the current rate-limit status from Twitter with `service::rate_limit_status`, and
+    /// saves the rate limit for every resource it lists.
+    ///
+    /// If the given `Client` was created with this `RateLimiter`, this request will update the
+    /// rate limit for `/application/rate_limit_status` like any other.
+    pub async fn load_status(&self, client: &Client) -> Result<()> {
+        let status = crate::service::rate_limit_status_raw(client).await?;
+
+        let families = status
+            .response
+            .get("resources")
+            .and_then(|r| r.as_object())
+            .ok_or(Error::MissingValue("resources"))?;
+
+        let mut limits = self.limits.lock().unwrap();
+        for (path, limit) in families
+            .values()
+            .filter_map(|family| family.as_object())
+            .flat_map(|family| family.iter())
+        {
+            if let Ok(limit) = serde_json::from_value::<RateLimit>(limit.clone()) {
+                limits.insert(resource_key(path), limit);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Waits until a request to the given resource is allowed, and counts it against the rate
+    /// limit. Returns whether it had to wait.
+    pub(crate) async fn acquire(&self, resource: &str) -> bool {
+        let mut waited = false;
+        while let Some(delay) = self.reserve(resource) {
+            #[cfg(feature = "tracing")]
+            tracing::info!(
+                resource,
+                delay_secs = delay.as_secs(),
+                "waiting for rate limit to reset",
+            );
+
+            tokio::time::sleep(delay).await;
+            waited = true;
+        }
+        waited
+    }
+
+    /// Counts a request to the given resource against its rate limit, or returns how long to wait
+    /// before the rate limit resets.
+    fn reserve(&self, resource: &str) -> Option<Duration> {
+        let mut limits = self.limits.lock().unwrap();
+        let now = now();
+
+        let limit = limits.get_mut(resource)?;
+        if i64::from(limit.reset) <= now {
+            // the window has reset, so there's nothing left to wait for until the next response
+            // tells us the new limit
+            limits.remove(resource);
+            None
+        } else if limit.remaining > 0 {
+            // count this request right away, so that concurrent requests don't all assume
+            // they have the last call available
+            limit.remaining -= 1;
+            None
+        } else {
+            Some(Duration::from_secs((i64::from(limit.reset) - now) as u64))
+        }
+    }
+
+    /// Saves the rate limit from the given result of a request to the given resource.
+    pub(crate) fn update(&self, resource: &str, result: &Result<(Headers, Vec<u8>)>) {
+        let mut limits = self.limits.lock().unwrap();
+
+        match result {
+            Ok((headers, _)) | Err(Error::TwitterError(headers, _)) => {
+                if let Ok(limit) = RateLimit::try_from(headers) {
+                    if limit.limit >= 0 && limit.remaining >= 0 && limit.reset >= 0 {
+                        limits.insert(resource.to_string(), limit);
+                    }
+                }
+            }
+            Err(Error::RateLimit(details)) if details.rate_limit.reset >= 0 => {
+                let limit = match details.rate_limit.limit {
+                    -1 => limits.get(resource).map_or(-1, |l| l.limit),
+                    limit => limit,
+                };
+                limits.insert(
+                    resource.to_string(),
+                    RateLimit {
+                        limit,
+                        remaining: 0,
+                        reset: details.rate_limit.reset,
+                    },
+                );
+            }
+            Err(_) => (),
+        }
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Returns the resource that a request to the given URL counts against.
+pub(crate) fn request_resource(uri: &Uri) -> String {
+    resource_key(uri.path())
+}
+
+/// Reduces the given path into a rate-limit resource, by removing the v1.1 API version, the file
+/// extension, and any segments that stand in for an ID.
+///
+/// `https://api.twitter.com/1.1/statuses/retweets/1234.json` and `/statuses/retweets/:id` both
+/// become `/statuses/retweets`.
+fn resource_key(path: &str) -> String {
+    let path = path.trim_end_matches(".json");
+    let path = match path.find("/1.1/") {
+        Some(idx) => &path[idx + 4..],
+        None => path,
+    };
+
+    let mut key = String::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        // the first segment is kept regardless, so that v2 paths keep their leading `/2`
+        let is_id = segment.starts_with(':') || segment.bytes().all(|b| b.is_ascii_digit());
+        if is_id && !key.is_empty() {
+            continue;
+        }
+        key.push('/');
+        key.push_str(segment);
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::RateLimitDetails;
+    use hyper::header::HeaderValue;
+
+    #[test]
+    fn resource_keys() {
+        let uri: Uri = "https://api.twitter.com/1.1/statuses/retweets/1234.json?count=5"
+            .parse()
+            .unwrap();
+        assert_eq!(request_resource(&uri), "/statuses/retweets");
+        assert_eq!(resource_key("/statuses/retweets/:id"), "/statuses/retweets");
+
+        let uri: Uri = crate::links::users::MUTES_IDS.parse().unwrap();
+        assert_eq!(request_resource(&uri), "/mutes/users/ids");
+
+        let uri: Uri = crate::links::v2::search::RECENT.parse().unwrap();
+        assert_eq!(request_resource(&uri), "/2/tweets/search/recent");
+    }
+
+    #[test]
+    fn limiter_waits_for_reset() {
+        let limiter = RateLimiter::new();
+        let resource = "/statuses/home_timeline";
+        assert_eq!(limiter.reserve(resource), None);
+
+        let reset = now() + 60;
+        let mut headers = Headers::new();
+        headers.insert("x-rate-limit-limit", HeaderValue::from(15));
+        headers.insert("x-rate-limit-remaining", HeaderValue::from(1));
+        headers.insert("x-rate-limit-reset", HeaderValue::from(reset));
+        limiter.update(resource, &Ok((headers, vec![])));
+
+        assert_eq!(limiter.get(resource).unwrap().remaining, 1);
+        assert_eq!(limiter.reserve(resource), None);
+        assert_eq!(limiter.get(resource).unwrap().remaining, 0);
+
+        let delay = limiter.reserve(resource).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60));
+
+        // an expired limit doesn't hold anything up
+        let details = RateLimitDetails {
+            rate_limit: RateLimit {
+                limit: 15,
+                remaining: 0,
+                reset: (now() - 1) as i32,
+            },
+            app_24hour: None,
+            user_24hour: None,
+        };
+        limiter.update(resource, &Err(Error::RateLimit(details)));
+        assert_eq!(limiter.reserve(resource), None);
+        assert!(limiter.get(resource).is_none());
+    }
+}
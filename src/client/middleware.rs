@@ -0,0 +1,162 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use hyper::{Body, Method, Request, Uri};
+
+use crate::common::Headers;
+use crate::error::Result;
+
+/// A hook that sees every request a `Client` sends, and every response it receives.
+///
+/// Middleware lets you add behavior to every call made with a `Client` - adding headers, logging,
+/// collecting metrics, or rewriting responses in tests - without wrapping each function in
+/// egg-mode. Add middleware to a `Client` with [`Client::with_middleware`]. When a `Client` has
+/// several, they form a chain: `on_request` is called on each of them in the order they were
+/// added, and `on_response` is called in the reverse order, so the first middleware added sees the
+/// request first and the response last.
+///
+/// `on_request` receives the request after it has been assembled and signed, right before it's
+/// handed to the `Transport`. Since the request has already been signed, changing its URL or
+/// parameters will cause Twitter to reject it, but headers that aren't part of the signature can
+/// be added freely. If `on_request` returns a result, the request is not sent at all: the rest of
+/// the chain is skipped, and the given result is returned as if it came from Twitter, after
+/// passing through `on_response` of this middleware and the ones before it.
+///
+/// `on_response` receives the headers and body of the response after they've been checked for
+/// errors, or the error that was returned instead, and can replace either of them.
+///
+/// Middleware is called once for every attempt at a request, so if the `Client` has a
+/// `RetryPolicy`, it sees each retry as well. It applies to every request sent with the
+/// `response_*` methods on `Client`, which includes every function in egg-mode that loads a
+/// response in full. It doesn't apply to `Client::response_future` or to `TwitterStream`, since
+/// those hand back the response before its body has been read.
+///
+/// [`Client::with_middleware`]: struct.Client.html#method.with_middleware
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use egg_mode::client::Middleware;
+/// use egg_mode::raw::Headers;
+/// use hyper::{Body, Method, Request, Uri};
+///
+/// /// Tags every request with a header, and counts the requests that failed.
+/// #[derive(Default)]
+/// struct Audit {
+///     failures: AtomicUsize,
+/// }
+///
+/// impl Middleware for Audit {
+///     fn on_request(
+///         &self,
+///         request: &mut Request<Body>,
+///     ) -> Option<egg_mode::error::Result<(Headers, Vec<u8>)>> {
+///         request.headers_mut().insert("x-audit-id", "my-service".parse().unwrap());
+///         None
+///     }
+///
+///     fn on_response(
+///         &self,
+///         _method: &Method,
+///         _uri: &Uri,
+///         response: &mut egg_mode::error::Result<(Headers, Vec<u8>)>,
+///     ) {
+///         if response.is_err() {
+///             self.failures.fetch_add(1, Ordering::Relaxed);
+///         }
+///     }
+/// }
+/// ```
+pub trait Middleware: Send + Sync {
+    /// Called with each signed request before it's sent. Returning a result skips sending the
+    /// request and uses that result instead.
+    ///
+    /// The default implementation does nothing and returns `None`.
+    fn on_request(&self, request: &mut Request<Body>) -> Option<Result<(Headers, Vec<u8>)>> {
+        let _ = request;
+        None
+    }
+
+    /// Called with the result of each request, along with the method and URL it was sent to.
+    ///
+    /// The default implementation does nothing.
+    fn on_response(&self, method: &Method, uri: &Uri, response: &mut Result<(Headers, Vec<u8>)>) {
+        let _ = (method, uri, response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Token;
+    use crate::client::scripted::{Reply, Scripted};
+    use crate::client::Client;
+
+    use std::sync::{Arc, Mutex};
+
+    /// Adds a header to each request, and records the order it was called in.
+    struct Tagger(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Middleware for Tagger {
+        fn on_request(&self, request: &mut Request<Body>) -> Option<Result<(Headers, Vec<u8>)>> {
+            self.0.lock().unwrap().push("tagger request");
+            request.headers_mut().insert("x-tagged", "yes".parse().unwrap());
+            None
+        }
+
+        fn on_response(&self, _: &Method, _: &Uri, response: &mut Result<(Headers, Vec<u8>)>) {
+            self.0.lock().unwrap().push("tagger response");
+            if let Ok((_, body)) = response {
+                *body = br#"{"rewritten": true}"#.to_vec();
+            }
+        }
+    }
+
+    /// Answers requests to `statuses/show` without sending them.
+    struct Canned(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Middleware for Canned {
+        fn on_request(&self, request: &mut Request<Body>) -> Option<Result<(Headers, Vec<u8>)>> {
+            self.0.lock().unwrap().push("canned request");
+            if request.uri().path().ends_with("/statuses/show.json") {
+                Some(Ok((Headers::new(), br#"{"canned": true}"#.to_vec())))
+            } else {
+                None
+            }
+        }
+
+        fn on_response(&self, _: &Method, uri: &Uri, _: &mut Result<(Headers, Vec<u8>)>) {
+            assert!(uri.path().ends_with(".json"));
+            self.0.lock().unwrap().push("canned response");
+        }
+    }
+
+    #[tokio::test]
+    async fn middleware_chain() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let transport = Scripted::new().otherwise(Reply::ok("{}"));
+        let client = Client::new(Token::Bearer("token".to_string()))
+            .with_transport(transport.clone())
+            .with_middleware(Tagger(calls.clone()))
+            .with_middleware(Canned(calls.clone()));
+
+        let req = client.request_get(crate::links::statuses::SHOW, None);
+        let resp = client.response_json::<serde_json::Value>(req).await.unwrap();
+        assert_eq!(resp.response["rewritten"], true);
+        assert!(transport.sent().is_empty());
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["tagger request", "canned request", "canned response", "tagger response"]
+        );
+
+        calls.lock().unwrap().clear();
+        let req = client.request_get(crate::links::statuses::USER_TIMELINE, None);
+        client.response_empty(req).await.unwrap();
+        let sent = transport.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].header("x-tagged"), Some("yes"));
+        assert_eq!(calls.lock().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,1156 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A reusable handle for sending many requests to Twitter.
+//!
+//! Every function in egg-mode that calls Twitter asks for something that can become a [`Client`].
+//! Most of the time, you can hand it a `&Token` and be done with it, but in that case, each call
+//! creates a fresh HTTP connection, which means paying for a new TCP and TLS handshake every time.
+//! For one-off calls that's no big deal, but when crawling a large timeline or list of followers,
+//! those handshakes start to add up.
+//!
+//! A `Client` bundles a `Token` together with a pooled HTTP client, so that connections to
+//! Twitter can be kept alive and reused from one call to the next. Since it can stand in anywhere
+//! a `&Token` is accepted, switching over is a matter of creating one and passing `&client`
+//! instead:
+//!
+//! ```rust,no_run
+//! # use egg_mode::Token;
+//! # #[tokio::main]
+//! # async fn main() {
+//! # let token: Token = unimplemented!();
+//! use egg_mode::Client;
+//!
+//! let client = Client::new(token);
+//!
+//! let rustlang = egg_mode::user::show("rustlang", &client).await.unwrap();
+//! let tweets = egg_mode::tweet::user_timeline(rustlang.id, true, true, &client);
+//! let (_tweets, feed) = tweets.start().await.unwrap();
+//! # }
+//! ```
+//!
+//! [`Client`]: struct.Client.html
+//!
+//! ## Pointing egg-mode somewhere else
+//!
+//! By default, a `Client` sends its requests to Twitter itself. If you'd like to run your code
+//! against a local stand-in for Twitter instead (for example, in integration tests), you can give
+//! the `Client` a different set of [`Endpoints`] to use. Every request made with that `Client` -
+//! including the ones made by `CursorIter`, `Timeline`, `StreamBuilder`, and media uploads - will
+//! then be sent to the hosts you provide:
+//!
+//! ```rust,no_run
+//! # use egg_mode::Token;
+//! # let token: Token = unimplemented!();
+//! use egg_mode::client::{Client, Endpoints};
+//!
+//! let client = Client::new(token).with_endpoints(Endpoints::with_base("http://127.0.0.1:8080"));
+//! ```
+//!
+//! [`Endpoints`]: struct.Endpoints.html
+//!
+//! ## Using a different HTTP client
+//!
+//! The connections themselves are managed by a [`Transport`], which takes a signed request and
+//! returns the server's response. The default transport is a hyper `Client` with the connector
+//! selected by egg-mode's TLS features, but you can substitute your own with
+//! `Client::with_transport`. See the docs for `Transport` for details.
+//!
+//! The [`cassette`] module uses this to record the traffic of a `Client` to a file, and to replay
+//! it later in tests.
+//!
+//! [`Transport`]: trait.Transport.html
+//! [`cassette`]: cassette/index.html
+//!
+//! ## Connecting through a proxy
+//!
+//! If your network requires it, the default transport can open its connections through an HTTP
+//! or SOCKS5 proxy. Give the `Client` a [`Proxy`] with `Client::with_proxy`, and every request it
+//! sends - including media uploads and streams - will go through it. See the docs for `Proxy` for
+//! details.
+//!
+//! [`Proxy`]: struct.Proxy.html
+//!
+//! ## Waiting out rate limits
+//!
+//! If you give a `Client` a [`RateLimiter`] with `Client::with_rate_limiter`, it will keep track
+//! of the rate limits returned by Twitter, and wait for an endpoint's rate limit to reset instead
+//! of sending a request that would be rejected. See the docs for `RateLimiter` for details.
+//!
+//! [`RateLimiter`]: struct.RateLimiter.html
+//!
+//! ## Retrying failed requests
+//!
+//! If you give a `Client` a [`RetryPolicy`] with `Client::with_retry_policy`, it will try
+//! requests again when they fail because of a network error or a temporary problem on Twitter's
+//! end. Only requests that are safe to send twice are retried. See the docs for `RetryPolicy` for
+//! details.
+//!
+//! [`RetryPolicy`]: struct.RetryPolicy.html
+//!
+//! ## Middleware
+//!
+//! To add behavior to every call made with a `Client` - custom headers, audit logs, metrics, or
+//! canned responses in tests - give it one or more [`Middleware`] with `Client::with_middleware`.
+//! Each middleware sees every signed request before it's sent and every response after it's
+//! received, and can answer a request itself instead of sending it. See the docs for `Middleware`
+//! for details.
+//!
+//! [`Middleware`]: trait.Middleware.html
+//!
+//! ## Timeouts
+//!
+//! By default, a `Client` waits as long as it takes for Twitter to respond. To put a limit on how
+//! long it can take to connect, to read each part of a response, or to finish a request as a
+//! whole, give it a set of [`Timeouts`] with `Client::with_timeouts`. The same limits can be set on
+//! individual requests, and a stall limit can end a `TwitterStream` that stops receiving data. See
+//! the docs for `Timeouts` for details.
+//!
+//! [`Timeouts`]: struct.Timeouts.html
+//!
+//! ## Clock skew
+//!
+//! Requests signed with OAuth 1.0a include the current time, and Twitter rejects them if the
+//! system clock is too far off. When that happens, a `Client` reads the time from the `Date` header
+//! of Twitter's response, remembers how far off the system clock is, and signs and sends the
+//! request again with the corrected time. Later requests use the corrected time from the start.
+//! The correction is shared by every clone of the `Client`, and by the `Client`s made from it with
+//! `with_token`.
+
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+
+use hyper::{Body, Method, Request};
+use serde::de::DeserializeOwned;
+
+use crate::auth::oauth1::{ClockOffset, Signer};
+use crate::auth::raw::RequestBuilder;
+use crate::auth::{AccessLevel, Token};
+use crate::common::*;
+use crate::error::{Error, KnownErrorCode, Result};
+use crate::links;
+use crate::stream::TwitterStream;
+
+pub mod cassette;
+mod limiter;
+mod middleware;
+mod proxy;
+mod retry;
+#[cfg(test)]
+pub(crate) mod scripted;
+mod timeout;
+mod transport;
+
+pub use self::limiter::RateLimiter;
+pub use self::middleware::Middleware;
+pub use self::proxy::Proxy;
+pub use self::retry::{RetryOn, RetryPolicy};
+pub use self::timeout::Timeouts;
+pub use self::transport::{Transport, TransportFuture};
+
+pub(crate) use self::proxy::Dialer;
+pub(crate) use self::retry::idempotent;
+pub(crate) use self::timeout::within;
+
+/// The set of base URLs that a `Client` sends its requests to.
+///
+/// Every endpoint egg-mode knows about lives under one of four hosts: the REST API at
+/// `https://api.twitter.com`, the media upload API at `https://upload.twitter.com`, the streaming
+/// API at `https://stream.twitter.com`, and the v2 API at `https://api.twitter.com/2`. When a
+/// `Client` assembles a request, it replaces the default host in the URL with the matching one from
+/// its `Endpoints`, so the rest of the path stays the same. URLs that don't start with one of the
+/// default hosts are left alone.
+///
+/// Each base URL can include a scheme, host, port, and path prefix, like
+/// `http://127.0.0.1:8080/twitter`. A trailing slash is ignored.
+///
+/// `Endpoints::default()` points everything at Twitter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    /// The base URL for the v1.1 REST API. Defaults to `https://api.twitter.com`.
+    pub rest: String,
+    /// The base URL for media uploads. Defaults to `https://upload.twitter.com`.
+    pub upload: String,
+    /// The base URL for the streaming API. Defaults to `https://stream.twitter.com`.
+    pub stream: String,
+    /// The base URL for the v2 API. Defaults to `https://api.twitter.com/2`.
+    pub v2: String,
+}
+
+impl Endpoints {
+    /// Creates a new `Endpoints` that sends every request to the same host.
+    ///
+    /// The v2 API is placed under `/2` on the given host, to match the layout of Twitter's own
+    /// URLs.
+    pub fn with_base(base: impl Into<String>) -> Endpoints {
+        let base = base.into();
+        let base = base.trim_end_matches('/');
+        Endpoints {
+            rest: base.to_string(),
+            upload: base.to_string(),
+            stream: base.to_string(),
+            v2: format!("{}/2", base),
+        }
+    }
+
+    /// Rewrites the given URL so that it points at these `Endpoints` instead of Twitter.
+    pub fn resolve<'a>(&self, uri: &'a str) -> Cow<'a, str> {
+        // the v2 check needs to come first, since it shares a host with the REST API
+        let hosts = [
+            (links::base::V2, &self.v2),
+            (links::base::REST, &self.rest),
+            (links::base::UPLOAD, &self.upload),
+            (links::base::STREAM, &self.stream),
+        ];
+
+        for &(default, base) in hosts.iter() {
+            if let Some(path) = uri.strip_prefix(default) {
+                if !path.is_empty() && !path.starts_with('/') {
+                    continue;
+                }
+
+                let base = base.trim_end_matches('/');
+                if base == default {
+                    return Cow::Borrowed(uri);
+                } else {
+                    return Cow::Owned(format!("{}{}", base, path));
+                }
+            }
+        }
+
+        Cow::Borrowed(uri)
+    }
+}
+
+impl Default for Endpoints {
+    fn default() -> Endpoints {
+        Endpoints {
+            rest: links::base::REST.to_string(),
+            upload: links::base::UPLOAD.to_string(),
+            stream: links::base::STREAM.to_string(),
+            v2: links::base::V2.to_string(),
+        }
+    }
+}
+
+/// A `Token` paired with a pool of connections to Twitter.
+///
+/// For an introduction to why you would want to use a `Client`, see the [module
+/// documentation][client].
+///
+/// [client]: index.html
+///
+/// Cloning a `Client` is cheap, and the clone shares the same connection pool as the original. This
+/// means you can hand clones to as many tasks as you like, and they'll all draw from the same set
+/// of connections. If you need to make calls on behalf of several users, `with_token` creates a
+/// new `Client` that shares the connection pool but signs its requests with a different `Token`.
+///
+/// Every function in egg-mode that accepts a token takes `impl Into<Client>`, so you can pass
+/// either `&token` or `&client` to them. Types like `CursorIter` and `Timeline` hold onto the
+/// `Client` they were created with, so every page they load reuses its connections.
+///
+/// The methods on `Client` mirror the functions in the [`raw`] module, and can be used the same
+/// way to call endpoints egg-mode doesn't wrap, while still reusing the `Client`'s connections.
+///
+/// [`raw`]: ../raw/index.html
+///
+/// To send requests somewhere other than Twitter, see [`with_endpoints`]. To send them with
+/// something other than egg-mode's default HTTP client, see [`with_transport`].
+///
+/// [`with_endpoints`]: #method.with_endpoints
+/// [`with_transport`]: #method.with_transport
+#[derive(Clone)]
+pub struct Client {
+    token: Token,
+    transport: Arc<dyn Transport>,
+    endpoints: Arc<Endpoints>,
+    limiter: Option<RateLimiter>,
+    retry: Option<RetryPolicy>,
+    timeouts: Timeouts,
+    proxy: Option<Proxy>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    /// How far off the system clock is, according to Twitter.
+    clock: ClockOffset,
+    /// The access level of `token`, from the last response that included it.
+    access_level: Arc<Mutex<Option<AccessLevel>>>,
+    /// Whether `transport` is egg-mode's own connection pool, which needs to be rebuilt when the
+    /// connect timeout or proxy changes.
+    builtin_transport: bool,
+}
+
+/// The pieces of a request assembled by a `Client`, saved in the request's extensions so that it
+/// can be signed again if it needs to be sent more than once.
+#[derive(Clone)]
+struct Unsigned {
+    method: Method,
+    uri: String,
+    params: Option<ParamList>,
+    json: Option<serde_json::Value>,
+}
+
+impl Client {
+    /// Creates a new `Client` with the given `Token` and a fresh connection pool.
+    pub fn new(token: Token) -> Client {
+        Client {
+            token,
+            transport: Arc::new(new_http_client(None, None)),
+            endpoints: Arc::new(Endpoints::default()),
+            limiter: None,
+            retry: None,
+            timeouts: Timeouts::default(),
+            proxy: None,
+            middleware: vec![],
+            clock: ClockOffset::new(),
+            access_level: Arc::default(),
+            builtin_transport: true,
+        }
+    }
+
+    /// Creates a new `Client` that shares this `Client`'s connection pool and `Endpoints`, but
+    /// authenticates its requests with the given `Token`.
+    ///
+    /// Since rate limits are tracked separately for each `Token`, if this `Client` has a
+    /// `RateLimiter`, the new `Client` will be given a new, empty one.
+    pub fn with_token(&self, token: Token) -> Client {
+        Client {
+            token,
+            transport: self.transport.clone(),
+            endpoints: self.endpoints.clone(),
+            limiter: self.limiter.as_ref().map(|_| RateLimiter::new()),
+            retry: self.retry.clone(),
+            timeouts: self.timeouts,
+            proxy: self.proxy.clone(),
+            middleware: self.middleware.clone(),
+            clock: self.clock.clone(),
+            access_level: Arc::default(),
+            builtin_transport: self.builtin_transport,
+        }
+    }
+
+    /// Sets the base URLs that this `Client` sends its requests to.
+    ///
+    /// See the docs for [`Endpoints`] for details.
+    ///
+    /// [`Endpoints`]: struct.Endpoints.html
+    pub fn with_endpoints(self, endpoints: Endpoints) -> Client {
+        Client {
+            endpoints: Arc::new(endpoints),
+            ..self
+        }
+    }
+
+    /// Sets the `Transport` that this `Client` sends its requests with, replacing its connection
+    /// pool.
+    ///
+    /// See the docs for [`Transport`] for details.
+    ///
+    /// [`Transport`]: trait.Transport.html
+    pub fn with_transport(self, transport: impl Transport + 'static) -> Client {
+        Client {
+            transport: Arc::new(transport),
+            builtin_transport: false,
+            ..self
+        }
+    }
+
+    /// Sets a `RateLimiter` that this `Client` uses to wait for rate limits to reset before sending
+    /// requests.
+    ///
+    /// See the docs for [`RateLimiter`] for details.
+    ///
+    /// [`RateLimiter`]: struct.RateLimiter.html
+    pub fn with_rate_limiter(self, limiter: RateLimiter) -> Client {
+        Client {
+            limiter: Some(limiter),
+            ..self
+        }
+    }
+
+    /// Returns the `RateLimiter` used by this `Client`, if it has one.
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.limiter.as_ref()
+    }
+
+    /// Sets the `RetryPolicy` that this `Client` uses to retry requests that failed because of a
+    /// network error or a temporary problem on Twitter's end.
+    ///
+    /// See the docs for [`RetryPolicy`] for details.
+    ///
+    /// [`RetryPolicy`]: struct.RetryPolicy.html
+    pub fn with_retry_policy(self, retry: RetryPolicy) -> Client {
+        Client {
+            retry: Some(retry),
+            ..self
+        }
+    }
+
+    /// Returns the `RetryPolicy` used by this `Client`, if it has one.
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry.as_ref()
+    }
+
+    /// Sets the `Timeouts` that limit how long this `Client`'s requests can take.
+    ///
+    /// Since the connect timeout is set on the connection pool, changing it gives the new `Client`
+    /// a fresh connection pool. Changing the other limits keeps the same pool, so it's cheap to
+    /// create a `Client` with different limits for a single call. If this `Client` was given a
+    /// custom `Transport`, the connect timeout is ignored.
+    ///
+    /// See the docs for [`Timeouts`] for details.
+    ///
+    /// [`Timeouts`]: struct.Timeouts.html
+    pub fn with_timeouts(self, timeouts: Timeouts) -> Client {
+        let transport = if self.builtin_transport
+            && timeouts.connect_timeout() != self.timeouts.connect_timeout()
+        {
+            Arc::new(new_http_client(timeouts.connect_timeout(), self.proxy.clone()))
+        } else {
+            self.transport
+        };
+
+        Client {
+            transport,
+            timeouts,
+            ..self
+        }
+    }
+
+    /// Sets the `Proxy` that this `Client` opens its connections through, replacing its connection
+    /// pool.
+    ///
+    /// If this `Client` was given a custom `Transport`, the proxy is ignored.
+    ///
+    /// See the docs for [`Proxy`] for details.
+    ///
+    /// [`Proxy`]: struct.Proxy.html
+    pub fn with_proxy(self, proxy: Proxy) -> Client {
+        let transport = if self.builtin_transport {
+            Arc::new(new_http_client(self.timeouts.connect_timeout(), Some(proxy.clone())))
+        } else {
+            self.transport
+        };
+
+        Client {
+            transport,
+            proxy: Some(proxy),
+            ..self
+        }
+    }
+
+    /// Returns the `Proxy` used by this `Client`, if it has one.
+    pub fn proxy(&self) -> Option<&Proxy> {
+        self.proxy.as_ref()
+    }
+
+    /// Adds a `Middleware` to the end of this `Client`'s middleware chain.
+    ///
+    /// See the docs for [`Middleware`] for details.
+    ///
+    /// [`Middleware`]: trait.Middleware.html
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Client {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Returns the `Timeouts` used by this `Client`.
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
+    /// Returns the base URLs that this `Client` sends its requests to.
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// Returns the offset added to the system time when signing requests with OAuth 1.0a.
+    ///
+    /// This starts at zero, and is set when Twitter rejects a request because its timestamp was out
+    /// of bounds. It can also be set by hand, for example to restore an offset found earlier.
+    pub fn clock_offset(&self) -> &ClockOffset {
+        &self.clock
+    }
+
+    /// Returns the access level of this `Client`'s `Token`, if it's known.
+    ///
+    /// Twitter reports the access level of OAuth 1.0a tokens in the `x-access-level` header of its
+    /// responses, and the `Client` remembers the last one it saw. Calling [`verify_tokens`] with
+    /// this `Client` is a simple way to find it out. Once it's known, requests that need more
+    /// access than the token has, like sending a Tweet with a read-only token, return
+    /// `Error::InsufficientAccess` without being sent.
+    ///
+    /// [`verify_tokens`]: ../auth/fn.verify_tokens.html
+    pub fn access_level(&self) -> Option<AccessLevel> {
+        *self.access_level.lock().unwrap()
+    }
+
+    /// Returns an error if this `Client`'s token is known not to have the given access level.
+    pub(crate) fn require_access(&self, required: AccessLevel) -> Result<()> {
+        match self.access_level() {
+            Some(level) if !level.allows(required) => {
+                Err(Error::InsufficientAccess(required, level))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the `Token` used to sign requests sent with this `Client`.
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    /// Assemble a signed GET request to the given URL with the given parameters, using this
+    /// `Client`'s `Token`.
+    ///
+    /// See [`raw::request_get`](../raw/fn.request_get.html) for details.
+    pub fn request_get(&self, uri: &str, params: Option<&ParamList>) -> Request<Body> {
+        self.sign(Unsigned {
+            method: Method::GET,
+            uri: self.endpoints.resolve(uri).into_owned(),
+            params: params.cloned(),
+            json: None,
+        })
+    }
+
+    /// Assemble a signed DELETE request to the given URL with the given parameters, using this
+    /// `Client`'s `Token`.
+    ///
+    /// See [`raw::request_delete`](../raw/fn.request_delete.html) for details.
+    pub fn request_delete(&self, uri: &str, params: Option<&ParamList>) -> Request<Body> {
+        self.sign(Unsigned {
+            method: Method::DELETE,
+            uri: self.endpoints.resolve(uri).into_owned(),
+            params: params.cloned(),
+            json: None,
+        })
+    }
+
+    /// Assemble a signed POST request to the given URL with the given parameters, using this
+    /// `Client`'s `Token`.
+    ///
+    /// See [`raw::request_post`](../raw/fn.request_post.html) for details.
+    pub fn request_post(&self, uri: &str, params: Option<&ParamList>) -> Request<Body> {
+        self.sign(Unsigned {
+            method: Method::POST,
+            uri: self.endpoints.resolve(uri).into_owned(),
+            params: params.cloned(),
+            json: None,
+        })
+    }
+
+    /// Assemble a signed PUT request to the given URL with the given parameters, using this
+    /// `Client`'s `Token`.
+    ///
+    /// The parameters are sent in the request body, like with `request_post`.
+    pub(crate) fn request_put(&self, uri: &str, params: Option<&ParamList>) -> Request<Body> {
+        self.sign(Unsigned {
+            method: Method::PUT,
+            uri: self.endpoints.resolve(uri).into_owned(),
+            params: params.cloned(),
+            json: None,
+        })
+    }
+
+    /// Assemble a signed POST request to the given URL with the given JSON body, using this
+    /// `Client`'s `Token`.
+    ///
+    /// See [`raw::request_post_json`](../raw/fn.request_post_json.html) for details.
+    pub fn request_post_json<B: serde::Serialize>(&self, uri: &str, body: B) -> Request<Body> {
+        self.sign(Unsigned {
+            method: Method::POST,
+            uri: self.endpoints.resolve(uri).into_owned(),
+            params: None,
+            json: Some(serde_json::to_value(body).unwrap()),
+        })
+    }
+
+    /// Signs the given request with this `Client`'s `Token`, keeping a copy of its pieces in case
+    /// it needs to be signed again.
+    fn sign(&self, unsigned: Unsigned) -> Request<Body> {
+        let builder = RequestBuilder::new(unsigned.method.clone(), &unsigned.uri)
+            .with_signer(Signer::new().with_clock(self.clock.clone()));
+        let builder = match (&unsigned.method, &unsigned.json, &unsigned.params) {
+            (_, Some(json), _) => builder.with_body_json(json),
+            (&Method::GET, None, Some(params)) | (&Method::DELETE, None, Some(params)) => {
+                builder.with_query_params(params)
+            }
+            (_, None, Some(params)) => builder.with_body_params(params),
+            (_, None, None) => builder,
+        };
+        let mut request = builder.request_token(&self.token);
+
+        request.extensions_mut().insert(unsigned);
+        request
+    }
+
+    /// Sends the given request over this `Client`'s connection pool, returning the raw response
+    /// from its `Transport`.
+    ///
+    /// See [`raw::response_future`](../raw/fn.response_future.html) for details.
+    pub fn response_future(&self, request: Request<Body>) -> TransportFuture {
+        self.transport.send(request)
+    }
+
+    /// Opens the given request as a `TwitterStream` over this `Client`'s `Transport`.
+    ///
+    /// If this `Client` or the request has a stall timeout in its `Timeouts`, the stream closes
+    /// when it goes that long without receiving data.
+    ///
+    /// See [`raw::response_as_stream`](../raw/fn.response_as_stream.html) for details.
+    pub fn response_as_stream(&self, request: Request<Body>) -> TwitterStream {
+        let stall = self.timeouts.for_request(&request).stall_timeout();
+        TwitterStream::new(request, self.transport.clone(), stall)
+    }
+
+    /// Sends the given request over this `Client`'s connection pool, parses the response for
+    /// errors given by Twitter, and returns the headers and raw bytes returned from the response.
+    ///
+    /// If this `Client` has a `RateLimiter`, this will wait for the request's rate limit to reset
+    /// before sending it, if necessary. If this `Client` has a `RetryPolicy` and the request was
+    /// assembled with one of this `Client`'s `request_*` methods, it will be signed and sent again
+    /// if it fails in a way the policy allows. If this `Client` or the request has any `Timeouts`,
+    /// each attempt is limited by them.
+    ///
+    /// See [`raw::response_raw_bytes`](../raw/fn.response_raw_bytes.html) for details.
+    pub async fn response_raw_bytes(&self, request: Request<Body>) -> Result<(Headers, Vec<u8>)> {
+        let span = trace::request_span(&request);
+        trace::instrument(self.send_with_retries(request), span).await
+    }
+
+    /// Sends the given request, signing and sending it again if it fails and the `RetryPolicy`
+    /// allows it.
+    async fn send_with_retries(&self, request: Request<Body>) -> Result<(Headers, Vec<u8>)> {
+        let timeouts = self.timeouts.for_request(&request);
+        let request = self.refresh_token(request, timeouts).await?;
+        let resignable = request.extensions().get::<Unsigned>().cloned();
+        let unsigned = match &self.retry {
+            Some(_) if retry::is_idempotent(&request) => resignable.clone(),
+            _ => None,
+        };
+
+        let mut request = request;
+        let mut attempt = 1;
+        let mut clock_corrected = false;
+        loop {
+            let result = self.send(request, timeouts).await;
+
+            // Twitter rejects requests with a bad timestamp before acting on them, so any request
+            // can be sent again once the clock has been corrected
+            if let (Err(err), Some(resign)) = (&result, &resignable) {
+                if !clock_corrected && self.correct_clock(err) {
+                    clock_corrected = true;
+                    request = self.sign(resign.clone());
+                    continue;
+                }
+            }
+
+            match (&result, &self.retry, &unsigned) {
+                (Err(err), Some(retry), Some(unsigned)) if retry.should_retry(attempt, err) => {
+                    let delay = retry.delay(attempt);
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %err,
+                        "retrying request",
+                    );
+
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    request = self.sign(unsigned.clone());
+                }
+                _ => return result,
+            }
+        }
+    }
+
+    /// If the given error says a request's timestamp was out of bounds, sets this `Client`'s clock
+    /// offset from the `Date` header of the response. Returns whether the offset was changed.
+    fn correct_clock(&self, err: &Error) -> bool {
+        let headers = match (&self.token, err) {
+            (Token::Access { .. }, Error::TwitterError(headers, errors))
+                if errors
+                    .errors
+                    .iter()
+                    .any(|e| e.known() == Some(KnownErrorCode::TimestampOutOfBounds)) =>
+            {
+                headers
+            }
+            _ => return false,
+        };
+        let server_time = match headers
+            .get(hyper::header::DATE)
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| chrono::DateTime::parse_from_rfc2822(date).ok())
+        {
+            Some(date) => date.timestamp(),
+            None => return false,
+        };
+
+        let previous = self.clock.get();
+        self.clock.sync_to(server_time);
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            offset_secs = self.clock.get(),
+            previous_offset_secs = previous,
+            "corrected clock skew",
+        );
+        previous != self.clock.get()
+    }
+
+    /// Refreshes this `Client`'s token if it's an expired OAuth 2.0 user token, and signs the given
+    /// request again with the new token.
+    ///
+    /// Only requests assembled with this `Client`'s `request_*` methods can be signed again, so
+    /// any others are returned as-is.
+    async fn refresh_token(
+        &self,
+        request: Request<Body>,
+        timeouts: Timeouts,
+    ) -> Result<Request<Body>> {
+        let token = match &self.token {
+            Token::OAuth2User(token) if token.is_expired() => token,
+            _ => return Ok(request),
+        };
+        let unsigned = match request.extensions().get::<Unsigned>() {
+            Some(unsigned) => unsigned.clone(),
+            None => return Ok(request),
+        };
+
+        let uri = self.endpoints.resolve(links::auth::OAUTH2_TOKEN);
+        token
+            .refresh_if_expired(&uri, |req| async move {
+                self.wait_for_rate_limit(&req).await;
+                self.transmit(req, timeouts).await
+            })
+            .await?;
+        Ok(self.sign(unsigned))
+    }
+
+    /// Sends the given request once through this `Client`'s middleware.
+    async fn send(&self, request: Request<Body>, timeouts: Timeouts) -> Result<(Headers, Vec<u8>)> {
+        let mut request = request;
+        let mut canned = None;
+        let mut called = 0;
+        for middleware in self.middleware.iter() {
+            called += 1;
+            canned = middleware.on_request(&mut request);
+            if canned.is_some() {
+                break;
+            }
+        }
+
+        let method = request.method().clone();
+        let uri = request.uri().clone();
+        let mut result = match canned {
+            Some(result) => result,
+            None => self.dispatch(request, timeouts).await,
+        };
+
+        for middleware in self.middleware[..called].iter().rev() {
+            middleware.on_response(&method, &uri, &mut result);
+        }
+
+        result
+    }
+
+    /// Sends the given request once, keeping the `RateLimiter` and the token's access level up to
+    /// date.
+    ///
+    /// If the request has to wait for its rate limit to reset and it was assembled with one of this
+    /// `Client`'s `request_*` methods, it's signed again after the wait, refreshing the token
+    /// first if it expired in the meantime. Otherwise it would go out with the timestamp from
+    /// before the wait, which Twitter may reject.
+    async fn dispatch(
+        &self,
+        request: Request<Body>,
+        timeouts: Timeouts,
+    ) -> Result<(Headers, Vec<u8>)> {
+        let mut request = request;
+        if self.wait_for_rate_limit(&request).await {
+            if let Some(unsigned) = request.extensions().get::<Unsigned>().cloned() {
+                request = self.refresh_token(self.sign(unsigned), timeouts).await?;
+            }
+        }
+        self.transmit(request, timeouts).await
+    }
+
+    /// Waits until the `RateLimiter` allows the given request, if this `Client` has one. Returns
+    /// whether it had to wait.
+    async fn wait_for_rate_limit(&self, request: &Request<Body>) -> bool {
+        match &self.limiter {
+            Some(limiter) => {
+                let resource = limiter::request_resource(request.uri());
+                limiter.acquire(&resource).await
+            }
+            None => false,
+        }
+    }
+
+    /// Sends the given request once without waiting for its rate limit, then updates the
+    /// `RateLimiter` and the token's access level from the response.
+    async fn transmit(
+        &self,
+        request: Request<Body>,
+        timeouts: Timeouts,
+    ) -> Result<(Headers, Vec<u8>)> {
+        let resource = limiter::request_resource(request.uri());
+        let result = http_request(&*self.transport, request, timeouts).await;
+        if let Some(limiter) = &self.limiter {
+            limiter.update(&resource, &result);
+        }
+
+        if let Ok((headers, _)) = &result {
+            if let Some(level) = AccessLevel::from_headers(headers) {
+                *self.access_level.lock().unwrap() = Some(level);
+            }
+        }
+        result
+    }
+
+    /// Sends the given request over this `Client`'s connection pool and parses the response as
+    /// JSON into the given type, including rate-limit headers.
+    ///
+    /// See [`raw::response_json`](../raw/fn.response_json.html) for details.
+    pub async fn response_json<T: DeserializeOwned>(
+        &self,
+        request: Request<Body>,
+    ) -> Result<Response<T>> {
+        let (headers, body) = self.response_raw_bytes(request).await?;
+        json_response(&headers, &body)
+    }
+
+    /// Sends the given request over this `Client`'s connection pool and discards the response body
+    /// after parsing it for rate-limit and error information.
+    ///
+    /// See [`raw::response_empty`](../raw/fn.response_empty.html) for details.
+    pub async fn response_empty(&self, request: Request<Body>) -> Result<Response<()>> {
+        let (headers, _) = self.response_raw_bytes(request).await?;
+        empty_response(&headers)
+    }
+}
+
+/// Creates a `Client` with its own connection pool, which will be dropped along with the `Client`.
+impl From<Token> for Client {
+    fn from(token: Token) -> Client {
+        Client::new(token)
+    }
+}
+
+/// Creates a `Client` with a clone of the given `Token` and its own connection pool.
+///
+/// This is what happens when you pass a `&Token` to a function in egg-mode, which means each
+/// call opens its own connection. To share connections between calls, create a `Client` up front
+/// and pass that instead.
+impl From<&Token> for Client {
+    fn from(token: &Token) -> Client {
+        Client::new(token.clone())
+    }
+}
+
+/// Clones the given `Client`, sharing its connection pool.
+impl From<&Client> for Client {
+    fn from(client: &Client) -> Client {
+        client.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scripted::{Reply, Scripted};
+    use super::{Client, Endpoints};
+    use crate::auth::Token;
+    use crate::error::Error;
+
+    use hyper::StatusCode;
+    use std::time::Duration;
+
+    #[test]
+    fn default_endpoints_leave_links_alone() {
+        let endpoints = Endpoints::default();
+
+        for &link in [
+            crate::links::statuses::SHOW,
+            crate::links::media::UPLOAD,
+            crate::links::stream::FILTER,
+            crate::links::v2::search::RECENT,
+            "https://example.com/some/endpoint.json",
+        ]
+        .iter()
+        {
+            assert_eq!(endpoints.resolve(link), link);
+        }
+    }
+
+    #[test]
+    fn endpoints_rewrite_links() {
+        let endpoints = Endpoints {
+            rest: "http://localhost:1234/".to_string(),
+            upload: "http://localhost:1235".to_string(),
+            stream: "http://localhost:1236/stream".to_string(),
+            v2: "http://localhost:1237/v2".to_string(),
+        };
+
+        assert_eq!(
+            endpoints.resolve(crate::links::statuses::SHOW),
+            "http://localhost:1234/1.1/statuses/show.json"
+        );
+        assert_eq!(
+            endpoints.resolve(crate::links::media::UPLOAD),
+            "http://localhost:1235/1.1/media/upload.json"
+        );
+        assert_eq!(
+            endpoints.resolve(crate::links::stream::FILTER),
+            "http://localhost:1236/stream/1.1/statuses/filter.json"
+        );
+        assert_eq!(
+            endpoints.resolve(crate::links::v2::search::RECENT),
+            "http://localhost:1237/v2/tweets/search/recent"
+        );
+        assert_eq!(
+            endpoints.resolve("https://api.twitter.com.example.com/1.1/statuses/show.json"),
+            "https://api.twitter.com.example.com/1.1/statuses/show.json"
+        );
+
+        let local = Endpoints::with_base("http://127.0.0.1:8080/");
+        assert_eq!(
+            local.resolve(crate::links::v2::search::RECENT),
+            "http://127.0.0.1:8080/2/tweets/search/recent"
+        );
+    }
+
+    #[tokio::test]
+    async fn custom_transport() {
+        let transport = Scripted::new()
+            .then(Reply::ok(r#"{"id": 1234}"#))
+            .then(Reply::status(StatusCode::INTERNAL_SERVER_ERROR, ""));
+        let client = Client::new(Token::Bearer("token".to_string()))
+            .with_endpoints(Endpoints::with_base("http://127.0.0.1:8080"))
+            .with_transport(transport.clone());
+
+        let req = client.request_get(crate::links::statuses::SHOW, None);
+        let resp: crate::Response<serde_json::Value> = client.response_json(req).await.unwrap();
+        assert_eq!(resp.response["id"], 1234);
+
+        let req = client.request_get(crate::links::statuses::SHOW, None);
+        match client.response_empty(req).await {
+            Err(Error::BadStatus(StatusCode::INTERNAL_SERVER_ERROR, _)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        // requests reach the transport after being resolved against the `Endpoints`
+        for sent in transport.sent() {
+            assert_eq!(sent.method, hyper::Method::GET);
+            assert!(sent.uri.starts_with("http://127.0.0.1:8080/1.1/"));
+            assert_eq!(sent.header("authorization"), Some("Bearer token"));
+            assert!(sent.body.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_only_idempotent_requests() {
+        let policy = super::RetryPolicy::default()
+            .backoff(Duration::from_millis(1), Duration::from_millis(1))
+            .jitter(false);
+        let client = || {
+            let transport = Scripted::new()
+                .then(Reply::status(StatusCode::SERVICE_UNAVAILABLE, "{}"))
+                .otherwise(Reply::ok("{}"));
+            let client = Client::new(Token::Bearer("token".to_string()))
+                .with_transport(transport.clone())
+                .with_retry_policy(policy.clone());
+            (client, transport)
+        };
+
+        let (get, transport) = client();
+        let req = get.request_get(crate::links::statuses::SHOW, None);
+        get.response_empty(req).await.unwrap();
+        assert_eq!(transport.sent().len(), 2);
+
+        let (post, transport) = client();
+        let req = post.request_post(crate::links::statuses::UPDATE, None);
+        match post.response_empty(req).await {
+            Err(Error::BadStatus(StatusCode::SERVICE_UNAVAILABLE, _)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        assert_eq!(transport.sent().len(), 1);
+
+        let (append, transport) = client();
+        let req = super::idempotent(append.request_post(crate::links::media::UPLOAD, None));
+        append.response_empty(req).await.unwrap();
+        assert_eq!(transport.sent().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn timeouts_end_slow_requests() {
+        use crate::error::TimeoutKind;
+
+        let timeouts = super::Timeouts::new()
+            .read(Duration::from_millis(50))
+            .total(Duration::from_millis(200))
+            .stall(Duration::from_millis(50));
+        let client = |reply| {
+            Client::new(Token::Bearer("token".to_string()))
+                .with_transport(Scripted::new().otherwise(reply))
+                .with_timeouts(timeouts)
+        };
+
+        let hanging = client(Reply::hang());
+        let req = hanging.request_get(crate::links::statuses::SHOW, None);
+        match hanging.response_empty(req).await {
+            Err(Error::Timeout(TimeoutKind::Read)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        let trickling = client(Reply::trickle());
+        let req = trickling.request_get(crate::links::statuses::SHOW, None);
+        match trickling.response_empty(req).await {
+            Err(Error::Timeout(TimeoutKind::Total)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        // a stream is allowed to trickle, but not to hang
+        use futures::StreamExt;
+        let mut stream = crate::stream::sample(&hanging);
+        match stream.next().await {
+            Some(Err(Error::Timeout(TimeoutKind::Stall))) => (),
+            other => panic!("unexpected result: {:?}", other.map(|r| r.map(|_| ()))),
+        }
+
+        let stream = crate::stream::sample(&trickling);
+        let result = tokio::time::timeout(Duration::from_millis(200), stream.into_future()).await;
+        assert!(result.is_err(), "trickling stream should stay open");
+    }
+
+    #[tokio::test]
+    async fn clock_skew_is_corrected() {
+        use crate::auth::oauth1::SignedHeader;
+        use crate::auth::KeyPair;
+
+        // Twitter rejects requests whose timestamp is more than five minutes away from its clock,
+        // which is set to 1600000000 here
+        let out_of_bounds = || {
+            Reply::status(
+                StatusCode::UNAUTHORIZED,
+                r#"{"errors": [{"code": 135, "message": "Timestamp out of bounds."}]}"#,
+            )
+            .header("date", "Sun, 13 Sep 2020 12:26:40 GMT")
+        };
+        let transport = Scripted::new()
+            .then(out_of_bounds())
+            .then(Reply::ok("{}"))
+            .then(Reply::ok("{}"))
+            .then(out_of_bounds());
+        let token = Token::Access {
+            consumer: KeyPair::new("consumer-key", "consumer-secret"),
+            access: KeyPair::new("access-key", "access-secret"),
+        };
+        let client = Client::new(token).with_transport(transport.clone());
+        let timestamp = |n: usize| {
+            let sent = &transport.sent()[n];
+            let auth = SignedHeader::parse(sent.header("authorization").unwrap()).unwrap();
+            auth.timestamp().unwrap() as i64
+        };
+        let now = chrono::Utc::now().timestamp();
+
+        // the request that fails is sent again, even though it's a POST
+        let req = client.request_post(crate::links::statuses::UPDATE, None);
+        client.response_empty(req).await.unwrap();
+        assert_eq!(transport.sent().len(), 2);
+        assert!((timestamp(0) - now).abs() <= 1);
+        assert!((timestamp(1) - 1600000000).abs() <= 1);
+        assert!((client.clock_offset().get() - (1600000000 - now)).abs() <= 1);
+
+        // later requests are signed with the corrected time, including ones from `with_token`
+        let other = client.with_token(client.token().clone());
+        let req = other.request_get(crate::links::statuses::SHOW, None);
+        other.response_empty(req).await.unwrap();
+        assert!((timestamp(2) - 1600000000).abs() <= 1);
+
+        // requests that weren't assembled by the `Client` can't be signed again
+        let req = crate::auth::raw::get(crate::links::statuses::SHOW, client.token(), None);
+        match client.response_empty(req).await {
+            Err(Error::TwitterError(_, errors)) => assert_eq!(errors.errors[0].code, 135),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        assert_eq!(transport.sent().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn requests_are_signed_after_waiting_for_rate_limit() {
+        use super::RateLimiter;
+        use crate::auth::oauth1::SignedHeader;
+        use crate::auth::KeyPair;
+        use crate::common::Headers;
+        use hyper::header::HeaderValue;
+
+        let reset = chrono::Utc::now().timestamp() + 2;
+        let mut headers = Headers::new();
+        headers.insert("x-rate-limit-limit", HeaderValue::from(15));
+        headers.insert("x-rate-limit-remaining", HeaderValue::from(0));
+        headers.insert("x-rate-limit-reset", HeaderValue::from(reset));
+        let limiter = RateLimiter::new();
+        limiter.update("/statuses/show", &Ok((headers, vec![])));
+
+        let transport = Scripted::new().then(Reply::ok("{}"));
+        let token = Token::Access {
+            consumer: KeyPair::new("consumer-key", "consumer-secret"),
+            access: KeyPair::new("access-key", "access-secret"),
+        };
+        let client = Client::new(token)
+            .with_rate_limiter(limiter)
+            .with_transport(transport.clone());
+        let timestamp = |header: &str| SignedHeader::parse(header).unwrap().timestamp().unwrap();
+
+        let req = client.request_get(crate::links::statuses::SHOW, None);
+        let signed = timestamp(req.headers()["authorization"].to_str().unwrap());
+        client.response_empty(req).await.unwrap();
+
+        let sent = transport.sent();
+        assert_eq!(sent.len(), 1);
+        let sent = timestamp(sent[0].header("authorization").unwrap());
+        assert!(sent > signed, "sent at {}, signed at {}", sent, signed);
+        assert!(sent as i64 >= reset - 1);
+    }
+
+    #[tokio::test]
+    async fn writes_need_access() {
+        use crate::auth::AccessLevel;
+
+        let users = crate::common::tests::load_file("sample_payloads/user_array.json");
+        let users: serde_json::Value = serde_json::from_str(&users).unwrap();
+        let transport = Scripted::new()
+            .otherwise(Reply::ok(users[0].to_string()).header("x-access-level", "read"));
+        let calls = || transport.sent().len();
+        let client =
+            Client::new(Token::Bearer("token".to_string())).with_transport(transport.clone());
+        assert_eq!(client.access_level(), None);
+
+        let verified = crate::auth::verify_tokens(&client).await.unwrap();
+        assert_eq!(verified.access_level, Some(AccessLevel::Read));
+        assert!(!verified.screen_name.is_empty());
+        assert_eq!(client.access_level(), Some(AccessLevel::Read));
+        assert_eq!(calls(), 1);
+
+        match crate::tweet::DraftTweet::new("hello").send(&client).await {
+            Err(Error::InsufficientAccess(AccessLevel::ReadWrite, AccessLevel::Read)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        match crate::user::follow("rustlang", false, &client).await {
+            Err(Error::InsufficientAccess(AccessLevel::ReadWrite, AccessLevel::Read)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        let dm = crate::direct::DraftMessage::new("hello", 1234);
+        match dm.send(&client).await {
+            Err(Error::InsufficientAccess(AccessLevel::ReadWriteDirectMessages, _)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        assert_eq!(calls(), 1);
+
+        // a different token might have a different access level
+        let other = client.with_token(Token::Bearer("other".to_string()));
+        assert_eq!(other.access_level(), None);
+        crate::user::follow("rustlang", false, &other).await.unwrap();
+        assert_eq!(calls(), 2);
+    }
+}
@@ -0,0 +1,365 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use hyper::client::HttpConnector;
+use hyper::service::Service;
+use hyper::Uri;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The longest response to a `CONNECT` request that will be read before giving up on the proxy.
+const MAX_CONNECT_RESPONSE: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scheme {
+    Http,
+    Socks5,
+}
+
+/// A proxy server that a `Client` opens its connections through.
+///
+/// If your network doesn't allow direct connections to Twitter, you can give a `Proxy` to a
+/// `Client` with [`Client::with_proxy`], and every connection it opens - for REST calls, media
+/// uploads, and streams alike - will go through that proxy instead. Two kinds of proxies are
+/// supported:
+///
+/// * An HTTP proxy, created with [`Proxy::http`], which is asked to open a tunnel with a `CONNECT`
+///   request.
+/// * A SOCKS5 proxy, created with [`Proxy::socks5`]. Host names are sent to the proxy to be
+///   resolved there, rather than being looked up locally.
+///
+/// In both cases, the TLS connection to Twitter is made through the tunnel, so the proxy never sees
+/// the contents of your requests. If the proxy needs a username and password, add them with
+/// [`with_credentials`]. The password is not included in the `Debug` output of a `Proxy`.
+///
+/// Since a proxy is part of the `Client`'s connection pool, it doesn't apply to a `Client` that was
+/// given a custom `Transport`, or to the functions in the `raw` module that create their own
+/// connections.
+///
+/// [`Client::with_proxy`]: struct.Client.html#method.with_proxy
+/// [`Proxy::http`]: #method.http
+/// [`Proxy::socks5`]: #method.socks5
+/// [`with_credentials`]: #method.with_credentials
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use egg_mode::Token;
+/// # let token: Token = unimplemented!();
+/// use egg_mode::client::{Client, Proxy};
+///
+/// let proxy = Proxy::http("proxy.example.com", 3128).with_credentials("user", "hunter2");
+/// let client = Client::new(token).with_proxy(proxy);
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Proxy {
+    scheme: Scheme,
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+}
+
+impl Proxy {
+    /// Creates a new `Proxy` that connects through the HTTP proxy at the given host and port.
+    pub fn http(host: impl Into<String>, port: u16) -> Proxy {
+        Proxy {
+            scheme: Scheme::Http,
+            host: host.into(),
+            port,
+            credentials: None,
+        }
+    }
+
+    /// Creates a new `Proxy` that connects through the SOCKS5 proxy at the given host and port.
+    pub fn socks5(host: impl Into<String>, port: u16) -> Proxy {
+        Proxy {
+            scheme: Scheme::Socks5,
+            host: host.into(),
+            port,
+            credentials: None,
+        }
+    }
+
+    /// Sets the username and password used to log into the proxy.
+    ///
+    /// For an HTTP proxy, these are sent in a `Proxy-Authorization` header with Basic
+    /// authentication. For a SOCKS5 proxy, they're used for username/password authentication.
+    pub fn with_credentials(
+        self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        Proxy {
+            credentials: Some((username.into(), password.into())),
+            ..self
+        }
+    }
+
+    /// Opens a connection to the proxy, and asks it to connect to the given host and port.
+    async fn connect(
+        &self,
+        mut http: HttpConnector,
+        host: &str,
+        port: u16,
+    ) -> io::Result<TcpStream> {
+        let proxy_uri = format!("http://{}:{}", self.host, self.port)
+            .parse::<Uri>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let stream = http
+            .call(proxy_uri)
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e))?;
+
+        match self.scheme {
+            Scheme::Http => self.http_connect(stream, host, port).await,
+            Scheme::Socks5 => {
+                // SOCKS5 wants IPv6 addresses without the brackets used in URLs
+                let host = host.trim_start_matches('[').trim_end_matches(']');
+                let stream = match &self.credentials {
+                    Some((user, pass)) => {
+                        Socks5Stream::connect_with_password_and_socket(
+                            stream,
+                            (host, port),
+                            user,
+                            pass,
+                        )
+                        .await
+                    }
+                    None => Socks5Stream::connect_with_socket(stream, (host, port)).await,
+                };
+                stream
+                    .map(Socks5Stream::into_inner)
+                    .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e))
+            }
+        }
+    }
+
+    /// Asks an HTTP proxy to open a tunnel to the given host and port.
+    async fn http_connect(
+        &self,
+        mut stream: TcpStream,
+        host: &str,
+        port: u16,
+    ) -> io::Result<TcpStream> {
+        let mut request = format!(
+            "CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n",
+            host = host,
+            port = port
+        );
+        if let Some((user, pass)) = &self.credentials {
+            let auth = base64::encode(format!("{}:{}", user, pass));
+            request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", auth));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        // read the response one byte at a time, so that nothing past the end of the headers is
+        // taken out of the tunnel
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            if response.len() >= MAX_CONNECT_RESPONSE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "proxy response headers were too long",
+                ));
+            }
+            response.push(stream.read_u8().await?);
+        }
+
+        let status_line = String::from_utf8_lossy(&response);
+        let status_line = status_line.lines().next().unwrap_or_default();
+        match status_line.split_whitespace().nth(1) {
+            Some(status) if status.starts_with('2') => Ok(stream),
+            _ => Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("proxy refused to open a tunnel: {}", status_line),
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Proxy")
+            .field("scheme", &self.scheme)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.credentials.as_ref().map(|(user, _)| user))
+            .finish()
+    }
+}
+
+/// Opens the TCP connections used by a `Client`, either directly or through a `Proxy`.
+///
+/// This stands in for hyper's `HttpConnector` underneath the TLS connector, so that the TLS
+/// handshake happens inside the proxy's tunnel.
+#[derive(Clone)]
+pub(crate) struct Dialer {
+    http: HttpConnector,
+    proxy: Option<Arc<Proxy>>,
+}
+
+impl Dialer {
+    pub(crate) fn new(proxy: Option<Proxy>) -> Dialer {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        Dialer {
+            http,
+            proxy: proxy.map(Arc::new),
+        }
+    }
+}
+
+impl Service<Uri> for Dialer {
+    type Response = TcpStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<TcpStream, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.http.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let proxy = match &self.proxy {
+            Some(proxy) => proxy.clone(),
+            None => {
+                let connecting = self.http.call(uri);
+                return Box::pin(async move { Ok(connecting.await?) });
+            }
+        };
+
+        let http = self.http.clone();
+        Box::pin(async move {
+            let host = uri
+                .host()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "URL has no host"))?;
+            let port = match (uri.port_u16(), uri.scheme_str()) {
+                (Some(port), _) => port,
+                (None, Some("http")) => 80,
+                (None, _) => 443,
+            };
+
+            Ok(proxy.connect(http, host, port).await?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Token;
+    use crate::client::{Client, Endpoints};
+
+    use tokio::net::TcpListener;
+
+    /// Reads from the given stream until the end of an HTTP request's headers.
+    async fn read_head(stream: &mut TcpStream) -> String {
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await.unwrap());
+        }
+        String::from_utf8(head).unwrap()
+    }
+
+    /// Answers a single HTTP request that arrives through the tunnel.
+    async fn serve_origin(mut stream: TcpStream) {
+        let head = read_head(&mut stream).await;
+        assert!(head.starts_with("GET /1.1/statuses/show.json"), "{}", head);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 12\r\n\r\n{\"id\": 1234}")
+            .await
+            .unwrap();
+    }
+
+    /// Sends a request for a host that doesn't exist, which can only succeed if it goes through
+    /// the proxy.
+    async fn call_through(proxy: Proxy) {
+        let client = Client::new(Token::Bearer("token".to_string()))
+            .with_endpoints(Endpoints::with_base("http://twitter.invalid"))
+            .with_proxy(proxy);
+        let req = client.request_get(crate::links::statuses::SHOW, None);
+        let resp = client.response_json::<serde_json::Value>(req).await.unwrap();
+        assert_eq!(resp.response["id"], 1234);
+    }
+
+    #[tokio::test]
+    async fn http_proxy() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let head = read_head(&mut stream).await;
+            assert!(head.starts_with("CONNECT twitter.invalid:80 HTTP/1.1\r\n"), "{}", head);
+            // "user:pass" in base64
+            assert!(head.contains("Proxy-Authorization: Basic dXNlcjpwYXNz\r\n"), "{}", head);
+
+            stream.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").await.unwrap();
+            serve_origin(stream).await;
+        });
+
+        call_through(Proxy::http("127.0.0.1", port).with_credentials("user", "pass")).await;
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn socks5_proxy() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            // greeting: version 5, with username/password authentication on offer
+            let mut greeting = [0u8; 2];
+            stream.read_exact(&mut greeting).await.unwrap();
+            let mut methods = vec![0u8; greeting[1] as usize];
+            stream.read_exact(&mut methods).await.unwrap();
+            assert!(methods.contains(&2));
+            stream.write_all(&[5, 2]).await.unwrap();
+
+            // username/password authentication
+            assert_eq!(stream.read_u8().await.unwrap(), 1);
+            let mut user = vec![0u8; stream.read_u8().await.unwrap() as usize];
+            stream.read_exact(&mut user).await.unwrap();
+            let mut pass = vec![0u8; stream.read_u8().await.unwrap() as usize];
+            stream.read_exact(&mut pass).await.unwrap();
+            assert_eq!((&user[..], &pass[..]), (&b"user"[..], &b"pass"[..]));
+            stream.write_all(&[1, 0]).await.unwrap();
+
+            // connect request, with the host name left for the proxy to resolve
+            let mut request = [0u8; 4];
+            stream.read_exact(&mut request).await.unwrap();
+            assert_eq!(request, [5, 1, 0, 3]);
+            let mut host = vec![0u8; stream.read_u8().await.unwrap() as usize];
+            stream.read_exact(&mut host).await.unwrap();
+            assert_eq!(host, b"twitter.invalid");
+            assert_eq!(stream.read_u16().await.unwrap(), 80);
+            stream.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 80]).await.unwrap();
+
+            serve_origin(stream).await;
+        });
+
+        call_through(Proxy::socks5("127.0.0.1", port).with_credentials("user", "pass")).await;
+        server.await.unwrap();
+    }
+
+    #[test]
+    fn debug_hides_password() {
+        let proxy = Proxy::socks5("127.0.0.1", 1080).with_credentials("user", "hunter2");
+        let debug = format!("{:?}", proxy);
+        assert!(debug.contains("user"));
+        assert!(!debug.contains("hunter2"));
+    }
+}
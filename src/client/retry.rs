@@ -0,0 +1,234 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::time::Duration;
+
+use hyper::{Body, Method, Request};
+use rand::Rng;
+
+use crate::error::{Error, KnownErrorCode};
+
+/// A class of errors that a [`RetryPolicy`] can retry.
+///
+/// [`RetryPolicy`]: struct.RetryPolicy.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryOn {
+    /// The connection to Twitter failed, as reported by `Error::NetError`, or the request ran out
+    /// of time, as reported by `Error::Timeout`.
+    Network,
+    /// Twitter responded with a 5xx status code, as reported by `Error::BadStatus`.
+    ServerError,
+    /// Twitter returned error code 130, "Over capacity".
+    OverCapacity,
+    /// Twitter returned error code 131, "Internal error".
+    InternalError,
+}
+
+impl RetryOn {
+    /// Returns the class that the given error falls under, if any.
+    fn classify(err: &Error) -> Option<RetryOn> {
+        match err {
+            Error::NetError(_) | Error::Timeout(_) => Some(RetryOn::Network),
+            Error::BadStatus(status, _) if status.is_server_error() => Some(RetryOn::ServerError),
+            Error::TwitterError(_, errors) => {
+                let has_code = |code| errors.errors.iter().any(|e| e.known() == Some(code));
+                if has_code(KnownErrorCode::OverCapacity) {
+                    Some(RetryOn::OverCapacity)
+                } else if has_code(KnownErrorCode::InternalError) {
+                    Some(RetryOn::InternalError)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Describes when and how often a `Client` should try a request again after it fails.
+///
+/// Sometimes a request to Twitter fails for reasons that have nothing to do with the request
+/// itself: the network drops out, Twitter is over capacity, or something goes wrong on Twitter's
+/// end. If you give a `RetryPolicy` to a `Client` with [`Client::with_retry_policy`], it will send
+/// those requests again after waiting a bit, instead of immediately returning the error. This is
+/// especially helpful when crawling a long `CursorIter` or `Timeline`, since otherwise a single
+/// hiccup would end the whole crawl.
+///
+/// Only requests that are safe to send more than once are retried. This includes every GET
+/// request, as well as the `APPEND` segments of a media upload. Other requests, like posting a
+/// tweet with `DraftTweet::send`, are never retried, since there's no way to know whether the
+/// first attempt went through. Requests assembled with the functions in the `raw` module are also
+/// never retried; use the `request_*` methods on `Client` instead so the `Client` can sign each
+/// attempt anew.
+///
+/// Between attempts, the `Client` waits for a delay that starts at `initial_backoff` and doubles
+/// after each attempt, up to `max_backoff`. With jitter enabled, each delay is randomly shortened
+/// by up to half, so that many clients that failed at the same time don't all retry at the same
+/// time.
+///
+/// The default policy makes up to 3 attempts, starting with a delay of 1 second and capping it at
+/// 30 seconds, with jitter enabled, and retries every class of error in [`RetryOn`].
+///
+/// [`Client::with_retry_policy`]: struct.Client.html#method.with_retry_policy
+/// [`RetryOn`]: enum.RetryOn.html
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use egg_mode::client::{RetryOn, RetryPolicy};
+///
+/// let policy = RetryPolicy::default()
+///     .max_attempts(5)
+///     .backoff(Duration::from_millis(500), Duration::from_secs(60))
+///     .retry_on(&[RetryOn::Network, RetryOn::OverCapacity]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    retry_on: Vec<RetryOn>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+            retry_on: vec![
+                RetryOn::Network,
+                RetryOn::ServerError,
+                RetryOn::OverCapacity,
+                RetryOn::InternalError,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sets the maximum number of times a request will be sent, including the first attempt.
+    ///
+    /// Setting this to 1 or 0 disables retries.
+    pub fn max_attempts(self, max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts,
+            ..self
+        }
+    }
+
+    /// Sets the delay before the first retry, and the longest delay that the doubling backoff can
+    /// reach.
+    pub fn backoff(self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        RetryPolicy {
+            initial_backoff,
+            max_backoff,
+            ..self
+        }
+    }
+
+    /// Sets whether to randomly shorten each delay.
+    pub fn jitter(self, jitter: bool) -> Self {
+        RetryPolicy { jitter, ..self }
+    }
+
+    /// Sets the classes of errors that will be retried. Any other error is returned immediately.
+    pub fn retry_on(self, retry_on: &[RetryOn]) -> Self {
+        RetryPolicy {
+            retry_on: retry_on.to_vec(),
+            ..self
+        }
+    }
+
+    /// Returns whether a request that failed with the given error on the given attempt (starting
+    /// from 1) should be sent again.
+    pub(crate) fn should_retry(&self, attempt: u32, err: &Error) -> bool {
+        attempt < self.max_attempts
+            && matches!(RetryOn::classify(err), Some(class) if self.retry_on.contains(&class))
+    }
+
+    /// Returns how long to wait after the given attempt (starting from 1) before trying again.
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self
+            .initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |d| d.min(self.max_backoff));
+
+        if self.jitter {
+            delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+        } else {
+            delay
+        }
+    }
+}
+
+/// Marks a request as safe to send more than once, even though it isn't a GET.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Idempotent;
+
+/// Marks the given request as safe to retry.
+pub(crate) fn idempotent(mut request: Request<Body>) -> Request<Body> {
+    request.extensions_mut().insert(Idempotent);
+    request
+}
+
+/// Returns whether the given request is safe to send more than once.
+pub(crate) fn is_idempotent(request: &Request<Body>) -> bool {
+    request.method() == Method::GET || request.extensions().get::<Idempotent>().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{TwitterErrorCode, TwitterErrors};
+
+    use hyper::StatusCode;
+
+    #[test]
+    fn retry_classes() {
+        let policy =
+            RetryPolicy::default().retry_on(&[RetryOn::ServerError, RetryOn::OverCapacity]);
+
+        let bad_status = |status| Error::BadStatus(status, Default::default());
+        assert!(policy.should_retry(1, &bad_status(StatusCode::BAD_GATEWAY)));
+        assert!(!policy.should_retry(1, &bad_status(StatusCode::NOT_FOUND)));
+        assert!(!policy.should_retry(3, &bad_status(StatusCode::BAD_GATEWAY)));
+
+        let twitter_error = |code| {
+            let errors = TwitterErrors {
+                errors: vec![TwitterErrorCode {
+                    message: String::new(),
+                    code,
+                }],
+            };
+            Error::TwitterError(Default::default(), errors)
+        };
+        assert!(policy.should_retry(1, &twitter_error(130)));
+        assert!(!policy.should_retry(1, &twitter_error(131)));
+        assert!(!policy.should_retry(1, &twitter_error(187)));
+    }
+
+    #[test]
+    fn backoff_doubles() {
+        let policy = RetryPolicy::default()
+            .backoff(Duration::from_secs(1), Duration::from_secs(5))
+            .jitter(false);
+
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(4), Duration::from_secs(5));
+        assert_eq!(policy.delay(40), Duration::from_secs(5));
+
+        let policy = policy.jitter(true);
+        for _ in 0..20 {
+            let delay = policy.delay(2);
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2));
+        }
+    }
+}
@@ -0,0 +1,194 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A `Transport` for tests, which answers requests from a script and records what it was sent.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hyper::header::HeaderMap;
+use hyper::{Body, Method, Request, StatusCode};
+
+use super::{Transport, TransportFuture};
+
+/// A response for `Scripted` to send.
+#[derive(Debug, Clone)]
+pub(crate) struct Reply {
+    status: StatusCode,
+    headers: Vec<(&'static str, String)>,
+    body: ReplyBody,
+}
+
+#[derive(Debug, Clone)]
+enum ReplyBody {
+    /// Send the body in these chunks.
+    Chunks(Vec<Vec<u8>>),
+    /// Never answer the request.
+    Hang,
+    /// Answer right away, but send the body one byte at a time, forever.
+    Trickle,
+}
+
+impl Reply {
+    /// A `200 OK` response with the given body.
+    pub(crate) fn ok(body: impl Into<Vec<u8>>) -> Reply {
+        Reply::status(StatusCode::OK, body)
+    }
+
+    /// A response with the given status and body.
+    pub(crate) fn status(status: StatusCode, body: impl Into<Vec<u8>>) -> Reply {
+        Reply {
+            status,
+            headers: vec![],
+            body: ReplyBody::Chunks(vec![body.into()]),
+        }
+    }
+
+    /// A `200 OK` response whose body is sent in the given chunks.
+    pub(crate) fn chunks(chunks: &[&str]) -> Reply {
+        Reply {
+            status: StatusCode::OK,
+            headers: vec![],
+            body: ReplyBody::Chunks(chunks.iter().map(|c| c.as_bytes().to_vec()).collect()),
+        }
+    }
+
+    /// A request that is never answered.
+    pub(crate) fn hang() -> Reply {
+        Reply {
+            body: ReplyBody::Hang,
+            ..Reply::ok("")
+        }
+    }
+
+    /// A response that arrives right away, but whose body never finishes.
+    pub(crate) fn trickle() -> Reply {
+        Reply {
+            body: ReplyBody::Trickle,
+            ..Reply::ok("")
+        }
+    }
+
+    /// Adds a header to the response.
+    pub(crate) fn header(mut self, name: &'static str, value: impl Into<String>) -> Reply {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    fn into_response(self) -> TransportFuture {
+        let body = match self.body {
+            ReplyBody::Hang => return Box::pin(futures::future::pending()),
+            ReplyBody::Chunks(chunks) => {
+                let chunks = chunks.into_iter().map(Ok::<_, std::io::Error>);
+                Body::wrap_stream(futures::stream::iter(chunks))
+            }
+            ReplyBody::Trickle => {
+                let (mut sender, body) = Body::channel();
+                tokio::spawn(async move {
+                    loop {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        if sender.send_data(" ".into()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+                body
+            }
+        };
+
+        let mut response = hyper::Response::builder().status(self.status);
+        for (name, value) in self.headers {
+            response = response.header(name, value);
+        }
+        let response = response.body(body).unwrap();
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// A request that `Scripted` was sent.
+#[derive(Debug, Clone)]
+pub(crate) struct Sent {
+    pub(crate) method: Method,
+    pub(crate) uri: String,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Sent {
+    /// Returns the value of the given header, if it was sent.
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|v| v.to_str().unwrap())
+    }
+
+    /// Returns the body of the request as a string.
+    pub(crate) fn body_str(&self) -> &str {
+        std::str::from_utf8(&self.body).unwrap()
+    }
+}
+
+#[derive(Default)]
+struct Script {
+    replies: VecDeque<Reply>,
+    otherwise: Option<Reply>,
+    sent: Vec<Sent>,
+}
+
+/// A `Transport` that answers requests with the replies it was given, in order, and records the
+/// requests it was sent.
+///
+/// Once the queued replies run out, requests are answered with the reply given to `otherwise`, or
+/// cause a panic if there isn't one. Clones share the same script, so a test can keep one to
+/// check on the requests after handing another to a `Client`.
+#[derive(Clone, Default)]
+pub(crate) struct Scripted(Arc<Mutex<Script>>);
+
+impl Scripted {
+    pub(crate) fn new() -> Scripted {
+        Scripted::default()
+    }
+
+    /// Queues a reply for the next request that doesn't have one yet.
+    pub(crate) fn then(self, reply: Reply) -> Scripted {
+        self.0.lock().unwrap().replies.push_back(reply);
+        self
+    }
+
+    /// Sets the reply for every request after the queued replies run out.
+    pub(crate) fn otherwise(self, reply: Reply) -> Scripted {
+        self.0.lock().unwrap().otherwise = Some(reply);
+        self
+    }
+
+    /// Returns the requests sent so far.
+    pub(crate) fn sent(&self) -> Vec<Sent> {
+        self.0.lock().unwrap().sent.clone()
+    }
+}
+
+impl Transport for Scripted {
+    fn send(&self, request: Request<Body>) -> TransportFuture {
+        let script = self.0.clone();
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body).await?.to_vec();
+            let uri = parts.uri.to_string();
+            let reply = {
+                let mut script = script.lock().unwrap();
+                script.sent.push(Sent {
+                    uri: uri.clone(),
+                    method: parts.method,
+                    headers: parts.headers,
+                    body,
+                });
+                script
+                    .replies
+                    .pop_front()
+                    .or_else(|| script.otherwise.clone())
+            };
+            let reply = reply.unwrap_or_else(|| panic!("unexpected request to {}", uri));
+            reply.into_response().await
+        })
+    }
+}
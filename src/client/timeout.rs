@@ -0,0 +1,189 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::future::Future;
+use std::time::Duration;
+
+use hyper::{Body, Request};
+
+use crate::error::{Error, Result, TimeoutKind};
+
+/// Limits on how long a request is allowed to take.
+///
+/// By default, egg-mode will wait as long as it takes for Twitter to answer a request, which means
+/// a connection that hangs partway through can hold up a task forever. Giving a `Timeouts` to a
+/// `Client` with [`Client::with_timeouts`] puts an upper bound on each stage of a request. Any
+/// request that goes over one of these limits is dropped, and returns `Error::Timeout` with the
+/// [`TimeoutKind`] of the limit that was reached.
+///
+/// * `connect` limits how long it can take to open a connection to Twitter, including the TLS
+///   handshake. Since connections are pooled, this is set on the `Client`'s connection pool, and
+///   doesn't apply to a custom `Transport`.
+/// * `read` limits how long to wait for each part of the response, starting with the headers and
+///   then for each chunk of the body.
+/// * `total` limits how long a single attempt at a request can take from start to finish. If the
+///   `Client` has a `RetryPolicy`, each attempt gets its own time limit, and the time spent waiting
+///   between attempts or for a rate limit to reset is not counted.
+/// * `stall` limits how long a `TwitterStream` can go without receiving any data, including while
+///   waiting for Twitter to accept the connection. Twitter sends a blank line every 30 seconds to
+///   keep an idle stream alive, and recommends treating 90 seconds of silence as a stalled
+///   connection. Streams ignore the `read` and `total` limits, since they're meant to stay open.
+///
+/// Each of these is unset by default. To override the `Client`'s limits for a single request, set
+/// them on the request with [`apply`] before passing it to `Client::response_json` or the like, or
+/// call the function with a `Client` from `with_timeouts`. Limits that are set on the request
+/// take priority over the ones on the `Client`; any that are unset fall back to the `Client`.
+///
+/// [`Client::with_timeouts`]: struct.Client.html#method.with_timeouts
+/// [`TimeoutKind`]: ../error/enum.TimeoutKind.html
+/// [`apply`]: #method.apply
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use egg_mode::Token;
+/// # #[tokio::main]
+/// # async fn main() {
+/// # let token: Token = unimplemented!();
+/// use std::time::Duration;
+/// use egg_mode::client::{Client, Timeouts};
+///
+/// let client = Client::new(token).with_timeouts(
+///     Timeouts::new()
+///         .connect(Duration::from_secs(5))
+///         .read(Duration::from_secs(30))
+///         .stall(Duration::from_secs(90)),
+/// );
+///
+/// // give this one request a little longer to finish
+/// let request = client.request_get("https://api.twitter.com/1.1/help/configuration.json", None);
+/// let request = Timeouts::new().total(Duration::from_secs(120)).apply(request);
+/// let config = client.response_json::<serde_json::Value>(request).await.unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    connect: Option<Duration>,
+    read: Option<Duration>,
+    total: Option<Duration>,
+    stall: Option<Duration>,
+}
+
+impl Timeouts {
+    /// Creates a new `Timeouts` with no limits set.
+    pub fn new() -> Timeouts {
+        Timeouts::default()
+    }
+
+    /// Sets how long it can take to open a connection to Twitter.
+    pub fn connect(self, connect: Duration) -> Self {
+        Timeouts {
+            connect: Some(connect),
+            ..self
+        }
+    }
+
+    /// Sets how long to wait for each part of a response.
+    pub fn read(self, read: Duration) -> Self {
+        Timeouts {
+            read: Some(read),
+            ..self
+        }
+    }
+
+    /// Sets how long a single attempt at a request can take from start to finish.
+    pub fn total(self, total: Duration) -> Self {
+        Timeouts {
+            total: Some(total),
+            ..self
+        }
+    }
+
+    /// Sets how long a stream can go without receiving any data.
+    pub fn stall(self, stall: Duration) -> Self {
+        Timeouts {
+            stall: Some(stall),
+            ..self
+        }
+    }
+
+    /// Returns the limit on opening a connection, if one is set.
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect
+    }
+
+    /// Returns the limit on each part of a response, if one is set.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read
+    }
+
+    /// Returns the limit on each attempt at a request, if one is set.
+    pub fn total_timeout(&self) -> Option<Duration> {
+        self.total
+    }
+
+    /// Returns the limit on how long a stream can stall, if one is set.
+    pub fn stall_timeout(&self) -> Option<Duration> {
+        self.stall
+    }
+
+    /// Attaches these limits to the given request, overriding the limits of the `Client` it's
+    /// sent with.
+    ///
+    /// The connect limit is only used when the request is sent by one of the functions in the
+    /// `raw` module, since those open a new connection for each request.
+    pub fn apply(self, mut request: Request<Body>) -> Request<Body> {
+        request.extensions_mut().insert(self);
+        request
+    }
+
+    /// Returns the limits to use for the given request, preferring the ones attached to it with
+    /// `apply` and falling back to these.
+    pub(crate) fn for_request(&self, request: &Request<Body>) -> Timeouts {
+        match request.extensions().get::<Timeouts>() {
+            Some(overrides) => Timeouts {
+                connect: overrides.connect.or(self.connect),
+                read: overrides.read.or(self.read),
+                total: overrides.total.or(self.total),
+                stall: overrides.stall.or(self.stall),
+            },
+            None => *self,
+        }
+    }
+}
+
+/// Runs the given future, failing with `Error::Timeout` if it takes longer than the given limit.
+pub(crate) async fn within<T>(
+    limit: Option<Duration>,
+    kind: TimeoutKind,
+    fut: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    match limit {
+        Some(limit) => match tokio::time::timeout(limit, fut).await {
+            Ok(result) => result,
+            Err(_) => Err(Error::Timeout(kind)),
+        },
+        None => fut.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_overrides_client() {
+        let client = Timeouts::new()
+            .connect(Duration::from_secs(5))
+            .read(Duration::from_secs(10));
+        let request = Request::new(Body::empty());
+        assert_eq!(client.for_request(&request), client);
+
+        let request = Timeouts::new().read(Duration::from_secs(1)).apply(request);
+        let timeouts = client.for_request(&request);
+        assert_eq!(timeouts.connect_timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(timeouts.read_timeout(), Some(Duration::from_secs(1)));
+        assert_eq!(timeouts.total_timeout(), None);
+    }
+}
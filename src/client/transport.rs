@@ -0,0 +1,69 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use hyper::client::connect::Connect;
+use hyper::{Body, Request};
+
+use crate::error::Result;
+
+/// The future returned by a [`Transport`], which resolves to the response from the server.
+///
+/// [`Transport`]: trait.Transport.html
+pub type TransportFuture = Pin<Box<dyn Future<Output = Result<hyper::Response<Body>>> + Send>>;
+
+/// A way to send a signed request and receive its response.
+///
+/// By default, a `Client` sends its requests using a hyper `Client` with the connector selected
+/// by egg-mode's TLS features. If you need to send requests some other way - through a custom
+/// proxy stack, a mocking layer for tests, or a client with extra instrumentation - you can
+/// implement `Transport` and hand it to [`Client::with_transport`].
+///
+/// By the time a request reaches a `Transport`, it has been fully assembled and signed, and its URL
+/// has been resolved against the `Client`'s [`Endpoints`]. The transport only needs to send it and
+/// hand back the response; egg-mode takes care of reading the body, checking the status, and
+/// parsing any errors returned by Twitter. Streaming endpoints read the response body
+/// incrementally, so transports shouldn't wait for the body to finish before returning.
+///
+/// Errors from hyper can be returned as `Error::NetError`. For any other kind of failure, wrap it
+/// in `Error::TransportError`.
+///
+/// `Transport` is implemented for any hyper `Client` with a body type of `hyper::Body`, so if all
+/// you need is a different connector or different pool settings, you can build a hyper `Client`
+/// yourself and use that directly.
+///
+/// [`Client::with_transport`]: struct.Client.html#method.with_transport
+/// [`Endpoints`]: struct.Endpoints.html
+///
+/// # Example
+///
+/// ```rust
+/// use egg_mode::client::{Transport, TransportFuture};
+/// use hyper::{Body, Request, Response};
+///
+/// /// A transport that answers every request with an empty JSON object.
+/// struct EmptyObject;
+///
+/// impl Transport for EmptyObject {
+///     fn send(&self, _request: Request<Body>) -> TransportFuture {
+///         Box::pin(async { Ok(Response::new(Body::from("{}"))) })
+///     }
+/// }
+/// ```
+pub trait Transport: Send + Sync {
+    /// Sends the given request, returning a future that resolves to the server's response.
+    fn send(&self, request: Request<Body>) -> TransportFuture;
+}
+
+impl<C> Transport for hyper::Client<C, Body>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    fn send(&self, request: Request<Body>) -> TransportFuture {
+        let response = self.request(request);
+        Box::pin(async move { Ok(response.await?) })
+    }
+}
@@ -66,11 +66,6 @@
 //! `std::cmp::{min,max}` require `Ord` and `min_opt` won't reach for the None if it's there,
 //! unlike the derived `PartialOrd` which considers None to be less than Some.
 //!
-//! ## Authentication functions
-//!
-//! The functions `get`, `post`, and `post_json` are re-exported here to keep people from having to
-//! qualify them from `auth::raw`.
-//!
 //! ## `Response`
 //!
 //! Also in its own module, `Response` is a public structure that contains rate-limit information
@@ -98,10 +93,10 @@ use hyper::header::{HeaderMap, HeaderValue};
 use percent_encoding::{utf8_percent_encode, AsciiSet, PercentEncode};
 
 mod response;
-
-pub use crate::auth::raw::{get, post, post_json};
+pub(crate) mod trace;
 
 pub use crate::common::response::*;
+pub use crate::client::Client;
 use crate::{error, list, user};
 
 /// Macro to create a `Serialize`/`Deserialize` implementation allowing for deserialization via the
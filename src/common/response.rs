@@ -1,21 +1,34 @@
 //! Infrastructure types related to packaging rate-limit information alongside responses from
 //! Twitter.
 
+use crate::client::{within, Dialer, Proxy, Timeouts, Transport};
 use crate::error::Error::{self, *};
-use crate::error::{Result, TwitterErrors};
+use crate::error::{
+    ErrorResponse, KnownErrorCode, Problem, RateLimitDetails, Result, TimeoutKind, TwitterErrors,
+};
 
-use hyper::client::{ResponseFuture, HttpConnector};
-use hyper::{self, Body, Request};
+use chrono::{DateTime, TimeZone, Utc};
+use hyper::body::HttpBody;
+use hyper::client::ResponseFuture;
+use hyper::http::response::Parts;
+use hyper::service::Service;
+use hyper::{self, Body, Request, Uri};
 use serde::{de::DeserializeOwned, Deserialize};
 use serde_json;
 
 use std::convert::TryFrom;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
 
-use super::Headers;
+use super::{trace, Headers};
 
 const X_RATE_LIMIT_LIMIT: &'static str = "X-Rate-Limit-Limit";
 const X_RATE_LIMIT_REMAINING: &'static str = "X-Rate-Limit-Remaining";
 const X_RATE_LIMIT_RESET: &'static str = "X-Rate-Limit-Reset";
+const X_APP_LIMIT_24HOUR: &str = "x-app-limit-24hour";
+const X_USER_LIMIT_24HOUR: &str = "x-user-limit-24hour";
 
 fn rate_limit(headers: &Headers, header: &'static str) -> Result<Option<i32>> {
     let val = headers.get(header);
@@ -40,6 +53,45 @@ fn rate_limit_reset(headers: &Headers) -> Result<Option<i32>> {
     rate_limit(headers, X_RATE_LIMIT_RESET)
 }
 
+/// Reads the 24-hour rate limit with the given header prefix, like `x-app-limit-24hour`, if all of
+/// its headers are present.
+fn daily_limit(headers: &Headers, prefix: &str) -> Result<Option<RateLimit>> {
+    let header = |suffix| {
+        headers
+            .get(format!("{}-{}", prefix, suffix))
+            .map(|val| Ok::<_, Error>(val.to_str()?.parse::<i32>()?))
+            .transpose()
+    };
+
+    match (header("limit")?, header("remaining")?, header("reset")?) {
+        (Some(limit), Some(remaining), Some(reset)) => Ok(Some(RateLimit {
+            limit,
+            remaining,
+            reset,
+        })),
+        _ => Ok(None),
+    }
+}
+
+/// Collects the rate limits from the headers of a response that hit one of them, if the headers
+/// say when any of them will reset.
+fn rate_limit_details(headers: &Headers) -> Result<Option<RateLimitDetails>> {
+    let details = RateLimitDetails {
+        rate_limit: RateLimit::try_from(headers)?,
+        app_24hour: daily_limit(headers, X_APP_LIMIT_24HOUR)?,
+        user_24hour: daily_limit(headers, X_USER_LIMIT_24HOUR)?,
+    };
+
+    if details.rate_limit.reset >= 0
+        || details.app_24hour.is_some()
+        || details.user_24hour.is_some()
+    {
+        Ok(Some(details))
+    } else {
+        Ok(None)
+    }
+}
+
 // n.b. this type is re-exported at the crate root - these docs are public!
 ///A helper struct to wrap response data with accompanying rate limit information.
 ///
@@ -95,6 +147,20 @@ impl<T> Response<T> {
         })
     }
 
+    /// Returns the rate-limit information returned with this response, or `None` if Twitter didn't
+    /// send any.
+    ///
+    /// Unlike reading `rate_limit_status` directly, this doesn't need to check its fields for `-1`
+    /// to know whether the information was there.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        let limit = self.rate_limit_status;
+        if limit.limit >= 0 && limit.remaining >= 0 && limit.reset >= 0 {
+            Some(limit)
+        } else {
+            None
+        }
+    }
+
     /// Converts a `Response<T>` into a `Response<U>` using the `Into` trait.
     ///
     /// This is implemented as a type function instead of the `From`/`Into` trait due to
@@ -159,48 +225,247 @@ compile_error!("features `egg_mode/native_tls`, `egg_mode/rustls` and \
 with more than one of these feature flags enabled at the same time");
 
 #[cfg(feature = "native_tls")]
-fn new_https_connector() -> hyper_tls::HttpsConnector<HttpConnector> {
-    hyper_tls::HttpsConnector::new()
+pub(crate) type HttpsConnector = hyper_tls::HttpsConnector<Dialer>;
+
+#[cfg(any(feature = "rustls", feature = "rustls_webpki"))]
+pub(crate) type HttpsConnector = hyper_rustls::HttpsConnector<Dialer>;
+
+/// The hyper `Client` used to send requests to Twitter, with the connector selected by the crate's
+/// TLS features.
+pub(crate) type HttpClient = hyper::Client<Connector>;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Wraps an `HttpsConnector` so that opening a connection (including the TLS handshake) can be
+/// given a time limit, regardless of which TLS implementation is in use.
+#[derive(Clone)]
+pub(crate) struct Connector {
+    https: HttpsConnector,
+    timeout: Option<Duration>,
+}
+
+/// The error returned by a `Connector` when it runs out of time to open a connection.
+#[derive(Debug, thiserror::Error)]
+#[error("connection timed out")]
+struct ConnectTimedOut;
+
+impl Service<Uri> for Connector {
+    type Response = <HttpsConnector as Service<Uri>>::Response;
+    type Error = BoxError;
+    type Future =
+        Pin<Box<dyn Future<Output = std::result::Result<Self::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), BoxError>> {
+        self.https.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connecting = self.https.call(uri);
+        let timeout = self.timeout;
+        Box::pin(async move {
+            match timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, connecting).await {
+                    Ok(conn) => conn,
+                    Err(_) => Err(Box::new(ConnectTimedOut) as BoxError),
+                },
+                None => connecting.await,
+            }
+        })
+    }
 }
 
+/// Returns whether the given error came from a `Connector` running out of time.
+fn is_connect_timeout(err: &hyper::Error) -> bool {
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        if err.is::<ConnectTimedOut>() {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+#[cfg(feature = "native_tls")]
+fn new_https_connector(dialer: Dialer) -> HttpsConnector {
+    hyper_tls::HttpsConnector::new_with_connector(dialer)
+}
+
+// n.b. the rustls connectors are set up the same way as `hyper_rustls::HttpsConnector`'s own
+// constructors, which can't be used here since they always connect directly
+
 #[cfg(feature = "rustls")]
-fn new_https_connector() -> hyper_rustls::HttpsConnector<HttpConnector> {
-    hyper_rustls::HttpsConnector::with_native_roots()
+fn new_https_connector(dialer: Dialer) -> HttpsConnector {
+    let mut config = rustls_lib::ClientConfig::new();
+    config.root_store = match rustls_native_certs::load_native_certs() {
+        Ok(store) | Err((Some(store), _)) => store,
+        Err((None, err)) => panic!("cannot access native cert store: {}", err),
+    };
+    if config.root_store.is_empty() {
+        panic!("no CA certificates found");
+    }
+    rustls_connector(dialer, config)
 }
 
 #[cfg(feature = "rustls_webpki")]
-fn new_https_connector() -> hyper_rustls::HttpsConnector<HttpConnector> {
-    hyper_rustls::HttpsConnector::with_webpki_roots()
+fn new_https_connector(dialer: Dialer) -> HttpsConnector {
+    let mut config = rustls_lib::ClientConfig::new();
+    config
+        .root_store
+        .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+    rustls_connector(dialer, config)
+}
+
+#[cfg(any(feature = "rustls", feature = "rustls_webpki"))]
+fn rustls_connector(dialer: Dialer, mut config: rustls_lib::ClientConfig) -> HttpsConnector {
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    config.ct_logs = Some(&ct_logs::LOGS);
+    (dialer, config).into()
+}
+
+/// How long an idle connection is kept in the pool before it's closed.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// How often an HTTP/2 connection is pinged to keep it alive.
+const HTTP2_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Creates a new hyper `Client` with its own connection pool, which opens its connections through
+/// the given proxy and gives up on opening a connection after the given timeout.
+///
+/// Every `HttpClient` created by this function keeps its own set of connections, so reusing one
+/// (as `egg_mode::Client` does) saves the TCP and TLS handshakes on subsequent requests.
+pub(crate) fn new_http_client(
+    connect_timeout: Option<Duration>,
+    proxy: Option<Proxy>,
+) -> HttpClient {
+    let connector = Connector {
+        https: new_https_connector(Dialer::new(proxy)),
+        timeout: connect_timeout,
+    };
+
+    hyper::Client::builder()
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .http2_keep_alive_interval(HTTP2_KEEP_ALIVE_INTERVAL)
+        .http2_keep_alive_while_idle(true)
+        .build(connector)
 }
 
 // n.b. this function is re-exported in the `raw` module - these docs are public!
 /// Converts the given request into a raw `ResponseFuture` from hyper.
+///
+/// This creates a new connection for every call. To reuse connections between requests, use
+/// [`Client::response_future`](../client/struct.Client.html#method.response_future) instead.
+///
+/// If the request has a connect timeout attached with [`Timeouts::apply`], it's used when opening
+/// the connection. Other timeouts are not applied to the returned future.
+///
+/// [`Timeouts::apply`]: ../client/struct.Timeouts.html#method.apply
 pub fn get_response(request: Request<Body>) -> ResponseFuture {
-    let connector = new_https_connector();
-    let client = hyper::Client::builder().build(connector);
-    client.request(request)
+    let timeouts = Timeouts::default().for_request(&request);
+    new_http_client(timeouts.connect_timeout(), None).request(request)
 }
 
 // n.b. this function is re-exported in the `raw` module - these docs are public!
 /// Loads the given request, parses the headers and response for potential errors given by Twitter,
 /// and returns the headers and raw bytes returned from the response.
+///
+/// This creates a new connection for every call. To reuse connections between requests, use
+/// [`Client::response_raw_bytes`](../client/struct.Client.html#method.response_raw_bytes) instead.
+///
+/// To put a time limit on the request, attach a set of [`Timeouts`] to it with `Timeouts::apply`.
+///
+/// [`Timeouts`]: ../client/struct.Timeouts.html
 pub async fn raw_request(request: Request<Body>) -> Result<(Headers, Vec<u8>)> {
-    let connector = new_https_connector();
-    let client = hyper::Client::builder().build(connector);
-    let resp = client.request(request).await?;
-    let (parts, body) = resp.into_parts();
-    let body: Vec<_> = hyper::body::to_bytes(body).await?.to_vec();
+    let timeouts = Timeouts::default().for_request(&request);
+    let client = new_http_client(timeouts.connect_timeout(), None);
+    let span = trace::request_span(&request);
+    trace::instrument(http_request(&client, request, timeouts), span).await
+}
+
+/// Sends the given request with the given `Transport`, and parses the response for errors given
+/// by Twitter.
+///
+/// The read and total limits of the given `Timeouts` are applied here; the connect limit needs to
+/// be set on the `Transport` itself.
+pub(crate) async fn http_request(
+    transport: &dyn Transport,
+    request: Request<Body>,
+    timeouts: Timeouts,
+) -> Result<(Headers, Vec<u8>)> {
+    #[cfg(feature = "tracing")]
+    let started = std::time::Instant::now();
+
+    let result = within(timeouts.total_timeout(), TimeoutKind::Total, async {
+        let read = timeouts.read_timeout();
+        let resp = within(read, TimeoutKind::Read, transport.send(request))
+            .await
+            .map_err(|err| match err {
+                NetError(err) if is_connect_timeout(&err) => Timeout(TimeoutKind::Connect),
+                err => err,
+            })?;
+
+        let (parts, mut body) = resp.into_parts();
+        let mut bytes = vec![];
+        while let Some(chunk) = within(read, TimeoutKind::Read, async {
+            Ok(body.data().await.transpose()?)
+        })
+        .await?
+        {
+            bytes.extend_from_slice(&chunk);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            status = parts.status.as_u16(),
+            latency_ms = started.elapsed().as_millis() as u64,
+            bytes = bytes.len(),
+            rate_limit.limit = rate_limit_limit(&parts.headers).ok().flatten(),
+            rate_limit.remaining = rate_limit_remaining(&parts.headers).ok().flatten(),
+            rate_limit.reset = rate_limit_reset(&parts.headers).ok().flatten(),
+            "received response",
+        );
+
+        check_response(parts, bytes)
+    })
+    .await;
+
+    #[cfg(feature = "tracing")]
+    if let Err(err) = &result {
+        tracing::debug!(
+            latency_ms = started.elapsed().as_millis() as u64,
+            error = %err,
+            "request failed",
+        );
+    }
+
+    result
+}
+
+/// Parses the given response for errors given by Twitter.
+fn check_response(parts: Parts, body: Vec<u8>) -> Result<(Headers, Vec<u8>)> {
     if let Ok(errors) = serde_json::from_slice::<TwitterErrors>(&body) {
-        if errors.errors.iter().any(|e| e.code == 88)
-            && parts.headers.contains_key(X_RATE_LIMIT_RESET)
+        if errors
+            .errors
+            .iter()
+            .any(|e| e.known() == Some(KnownErrorCode::RateLimitExceeded))
         {
-            return Err(RateLimit(rate_limit_reset(&parts.headers)?.unwrap()));
-        } else {
-            return Err(TwitterError(parts.headers, errors));
+            if let Some(details) = rate_limit_details(&parts.headers)? {
+                return Err(RateLimit(details));
+            }
+        }
+        return Err(TwitterError(parts.headers, errors));
+    }
+    if parts.status == hyper::StatusCode::TOO_MANY_REQUESTS {
+        if let Some(details) = rate_limit_details(&parts.headers)? {
+            return Err(RateLimit(details));
         }
     }
     if !parts.status.is_success() {
-        return Err(BadStatus(parts.status));
+        if let Ok(problem) = serde_json::from_slice::<Problem>(&body) {
+            return Err(TwitterProblem(parts.headers, Box::new(problem)));
+        }
+        let response = ErrorResponse::new(parts.headers, &body);
+        return Err(BadStatus(parts.status, Box::new(response)));
     }
     Ok((parts.headers, body))
 }
@@ -210,11 +475,7 @@ pub async fn raw_request(request: Request<Body>) -> Result<(Headers, Vec<u8>)> {
 /// error information, returning the rate-limit information from the headers.
 pub async fn request_with_empty_response(request: Request<Body>) -> Result<Response<()>> {
     let (headers, _) = raw_request(request).await?;
-    let rate_limit_status = RateLimit::try_from(&headers)?;
-    Ok(Response {
-        rate_limit_status,
-        response: (),
-    })
+    empty_response(&headers)
 }
 
 // n.b. this function is re-exported in the `raw` module - these docs are public!
@@ -224,8 +485,35 @@ pub async fn request_with_json_response<T: DeserializeOwned>(
     request: Request<Body>,
 ) -> Result<Response<T>> {
     let (headers, body) = raw_request(request).await?;
-    let response = serde_json::from_slice(&body)?;
-    let rate_limit_status = RateLimit::try_from(&headers)?;
+    json_response(&headers, &body)
+}
+
+/// Parses the rate-limit headers from a response whose body is not needed.
+pub(crate) fn empty_response(headers: &Headers) -> Result<Response<()>> {
+    let rate_limit_status = RateLimit::try_from(headers)?;
+    Ok(Response {
+        rate_limit_status,
+        response: (),
+    })
+}
+
+/// Parses the given response body as JSON, alongside the rate-limit headers.
+pub(crate) fn json_response<T: DeserializeOwned>(
+    headers: &Headers,
+    body: &[u8],
+) -> Result<Response<T>> {
+    let error = |err, path| {
+        let response = ErrorResponse::new(headers.clone(), body);
+        DeserializeError(err, Some(path), Some(Box::new(response)))
+    };
+    let de = &mut serde_json::Deserializer::from_slice(body);
+    let response = serde_path_to_error::deserialize(&mut *de).map_err(|err| {
+        let path = err.path().to_string();
+        error(err.into_inner(), path)
+    })?;
+    // like `serde_json::from_slice`, don't allow anything but whitespace after the value
+    de.end().map_err(|err| error(err, ".".to_string()))?;
+    let rate_limit_status = RateLimit::try_from(headers)?;
     Ok(Response {
         rate_limit_status,
         response,
@@ -239,7 +527,7 @@ pub async fn request_with_json_response<T: DeserializeOwned>(
 /// that endpoint, and at what point your limit refreshes and allows you to call it more. These are
 /// normally passed through the response headers, and egg-mode reads for these headers when a
 /// function returns a `Response<T>`. If the headers are absent for a given request, the field will
-/// be `-1`.
+/// be `-1`. `Response::rate_limit` checks for this, and returns `None` instead.
 ///
 /// Rate limits are tracked separately based on the kind of `Token` you're using. For Bearer tokens
 /// using Application-only authentication, the rate limit is based on your application as a whole,
@@ -260,6 +548,24 @@ pub struct RateLimit {
     pub reset: i32,
 }
 
+impl RateLimit {
+    /// Returns the time at which the rate window resets, or `None` if it wasn't given.
+    pub fn reset_at(&self) -> Option<DateTime<Utc>> {
+        if self.reset < 0 {
+            None
+        } else {
+            Utc.timestamp_opt(i64::from(self.reset), 0).single()
+        }
+    }
+
+    /// Returns how long it will be until the rate window resets, or `None` if the reset time
+    /// wasn't given. If the window has already reset, this returns a zero `Duration`.
+    pub fn reset_in(&self) -> Option<Duration> {
+        let reset = self.reset_at()?;
+        Some((reset - Utc::now()).to_std().unwrap_or_default())
+    }
+}
+
 impl TryFrom<&Headers> for RateLimit {
     type Error = Error;
     fn try_from(headers: &Headers) -> Result<Self> {
@@ -270,3 +576,87 @@ impl TryFrom<&Headers> for RateLimit {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(status: u16, headers: &[(&str, i64)]) -> Parts {
+        let mut builder = hyper::Response::builder().status(status);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    #[test]
+    fn rate_limit_helpers() {
+        let reset = Utc::now().timestamp() + 120;
+        let headers = parts(
+            200,
+            &[
+                ("x-rate-limit-limit", 15),
+                ("x-rate-limit-remaining", 14),
+                ("x-rate-limit-reset", reset),
+            ],
+        )
+        .headers;
+        let response = json_response::<()>(&headers, b"null").unwrap();
+        let limit = response.rate_limit().unwrap();
+        assert_eq!(limit.reset_at().unwrap().timestamp(), reset);
+        let reset_in = limit.reset_in().unwrap();
+        assert!(reset_in > Duration::from_secs(110) && reset_in <= Duration::from_secs(120));
+
+        let response = json_response::<()>(&Headers::new(), b"null").unwrap();
+        assert!(response.rate_limit().is_none());
+        assert!(response.rate_limit_status.reset_at().is_none());
+    }
+
+    #[test]
+    fn json_response_rejects_trailing_data() {
+        let body = b"{\"id\": 1} {\"id\": 2}";
+        match json_response::<serde_json::Value>(&Headers::new(), body) {
+            Err(DeserializeError(_, Some(path), Some(response))) => {
+                assert_eq!(path, ".");
+                assert_eq!(response.body, &body[..]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let response = json_response::<serde_json::Value>(&Headers::new(), b"{\"id\": 1}\r\n");
+        assert_eq!(response.unwrap().response["id"], 1);
+    }
+
+    #[test]
+    fn rate_limit_error_includes_daily_limits() {
+        let reset = Utc::now().timestamp() + 3600;
+        let parts = parts(
+            429,
+            &[
+                ("x-user-limit-24hour-limit", 200),
+                ("x-user-limit-24hour-remaining", 0),
+                ("x-user-limit-24hour-reset", reset),
+                ("x-app-limit-24hour-limit", 20000),
+                ("x-app-limit-24hour-remaining", 19000),
+                ("x-app-limit-24hour-reset", reset - 600),
+            ],
+        );
+        let body = br#"{"title": "Too Many Requests", "type": "about:blank", "status": 429}"#;
+
+        match check_response(parts, body.to_vec()) {
+            Err(RateLimit(details)) => {
+                assert_eq!(details.rate_limit.reset, -1);
+                assert_eq!(details.user_24hour.unwrap().remaining, 0);
+                assert_eq!(details.app_24hour.unwrap().limit, 20000);
+                let reset_in = details.reset_in().unwrap();
+                assert!(reset_in > Duration::from_secs(3590));
+                assert_eq!(details.reset_at().unwrap().timestamp(), reset);
+
+                // the message uses the daily limit that was reached, not the missing 15-minute one
+                let message = RateLimit(details).to_string();
+                assert_eq!(message, format!("Rate limit reached, hold until {}", reset));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Helpers for the optional `tracing` instrumentation.
+//!
+//! Spans need to be carried around even when the `tracing` feature is off, so this module
+//! provides a stand-in `Span` that does nothing. Events are emitted directly with the macros from
+//! `tracing`, behind `#[cfg(feature = "tracing")]`.
+//!
+//! Nothing that's traced may include a token, a signature, or an `Authorization` header. Requests
+//! are only identified by their method, host, and path, since query strings can include things
+//! like OAuth verifiers.
+
+use std::future::Future;
+
+use hyper::{Body, Request};
+
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::Span;
+
+/// Stands in for `tracing::Span` when the `tracing` feature is off.
+#[cfg(not(feature = "tracing"))]
+#[derive(Debug, Clone)]
+pub(crate) struct Span;
+
+/// Creates the span that a request to Twitter is traced under.
+#[cfg(feature = "tracing")]
+pub(crate) fn request_span(request: &Request<Body>) -> Span {
+    let uri = request.uri();
+    tracing::info_span!(
+        "egg_mode::request",
+        method = %request.method(),
+        host = uri.host().unwrap_or_default(),
+        endpoint = uri.path(),
+    )
+}
+
+/// Creates the span that a request to Twitter is traced under.
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn request_span(_request: &Request<Body>) -> Span {
+    Span
+}
+
+/// Runs the given future inside the given span.
+#[cfg(feature = "tracing")]
+pub(crate) fn instrument<F: Future>(fut: F, span: Span) -> impl Future<Output = F::Output> {
+    tracing::Instrument::instrument(fut, span)
+}
+
+/// Runs the given future inside the given span.
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn instrument<F: Future>(fut: F, _span: Span) -> impl Future<Output = F::Output> {
+    fut
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate::auth::{KeyPair, Token};
+    use crate::client::scripted::{Reply, Scripted};
+    use crate::client::Client;
+    use crate::common::ParamList;
+
+    use tracing_test::traced_test;
+
+    #[tokio::test]
+    #[traced_test]
+    async fn requests_are_traced_without_secrets() {
+        let token = Token::Access {
+            consumer: KeyPair::new("consumer-key", "consumer-secret"),
+            access: KeyPair::new("access-key", "access-secret"),
+        };
+        let transport = Scripted::new().then(
+            Reply::ok("{}")
+                .header("x-rate-limit-limit", "15")
+                .header("x-rate-limit-remaining", "14")
+                .header("x-rate-limit-reset", "1600000000"),
+        );
+        let client = Client::new(token).with_transport(transport.clone());
+        let params = ParamList::new().add_param("id", "8675309");
+        let req = client.request_get(crate::links::statuses::SHOW, Some(&params));
+        client.response_empty(req).await.unwrap();
+        assert!(transport.sent()[0].header("authorization").is_some());
+
+        assert!(logs_contain("egg_mode::request"));
+        assert!(logs_contain("method=GET"));
+        assert!(logs_contain("/1.1/statuses/show.json"));
+        assert!(logs_contain("status=200"));
+        assert!(logs_contain("rate_limit.remaining=14"));
+
+        for secret in &[
+            "consumer-secret",
+            "access-secret",
+            "access-key",
+            "oauth_signature",
+            "authorization",
+            "8675309",
+        ] {
+            assert!(!logs_contain(secret), "logs contained {}", secret);
+        }
+    }
+}
@@ -9,6 +9,7 @@
 //! module. The rest of it is available to make sure consumers of the API can understand precisely
 //! what types come out of functions that return `CursorIter`.
 
+use std::convert::TryFrom;
 use std::fmt::Display;
 use std::future::Future;
 use std::num::NonZeroI64;
@@ -20,8 +21,10 @@ use serde::{de::DeserializeOwned, Deserialize};
 
 use crate::common::*;
 use crate::common::serde_num_string::*;
-use crate::error::Result;
-use crate::{auth, list, user};
+use crate::error::{Error, Result};
+use crate::tweet::v2::{FromRawTweetV2, Includes};
+use crate::tweet::RawTweetV2;
+use crate::{list, user};
 
 ///Trait to generalize over paginated views of API results.
 ///
@@ -165,9 +168,9 @@ impl Cursor for ListCursor {
 #[derive(Deserialize)]
 #[allow(unused)]
 pub struct SearchCursorMetadata {
-    #[serde(deserialize_with = "deserialize_number_from_string")]
+    #[serde(default, deserialize_with = "deserialize_number_from_string")]
     pub(crate) newest_id: u64,
-    #[serde(deserialize_with = "deserialize_number_from_string")]
+    #[serde(default, deserialize_with = "deserialize_number_from_string")]
     pub(crate) oldest_id: u64,
     pub(crate) result_count: usize,
     pub(crate) next_token: Option<String>,
@@ -178,11 +181,58 @@ pub struct SearchCursorMetadata {
 ///
 ///Note: this uses tokens, not `newest_id`/`oldest_id` + `since_id`/`until_id`.
 ///
+///The tweets in each page are converted with [`FromRawTweetV2`], so search results loaded as
+///`Tweet`s have their author, media, place, and referenced tweets filled in from the page's
+///`includes`.
+///
 ///[search]: https://developer.twitter.com/en/docs/twitter-api/tweets/search/api-reference
+///[`FromRawTweetV2`]: ../tweet/v2/trait.FromRawTweetV2.html
 #[derive(Deserialize)]
+#[serde(
+    try_from = "RawSearchCursor",
+    bound(deserialize = "SearchResultItem: FromRawTweetV2")
+)]
 pub struct SearchCursor<SearchResultItem> {
     pub(crate) data: Vec<SearchResultItem>,
     pub(crate) meta: SearchCursorMetadata,
+    pub(crate) includes: Includes,
+}
+
+impl<I> SearchCursor<I> {
+    ///Returns the users, media, places, polls, and tweets that were loaded with this page.
+    pub fn includes(&self) -> &Includes {
+        &self.includes
+    }
+}
+
+#[derive(Deserialize)]
+struct RawSearchCursor {
+    #[serde(default)]
+    data: Vec<RawTweetV2>,
+    meta: SearchCursorMetadata,
+    #[serde(default)]
+    includes: Includes,
+}
+
+impl<I: FromRawTweetV2> TryFrom<RawSearchCursor> for SearchCursor<I> {
+    type Error = Error;
+
+    fn try_from(raw: RawSearchCursor) -> Result<Self> {
+        let RawSearchCursor {
+            data,
+            meta,
+            includes,
+        } = raw;
+        let data = data
+            .into_iter()
+            .map(|tweet| I::from_raw_tweet_v2(tweet, &includes))
+            .collect::<Result<_>>()?;
+        Ok(SearchCursor {
+            data,
+            meta,
+            includes,
+        })
+    }
 }
 
 impl<I> Cursor for SearchCursor<I> {
@@ -302,7 +352,7 @@ where
     T: Cursor + DeserializeOwned,
 {
     link: &'static str,
-    token: auth::Token,
+    client: Client,
     params_base: Option<ParamList>,
     ///The number of results returned in one network call.
     ///
@@ -365,8 +415,9 @@ where
             .add_opt_param(T::STARTING_CURSOR_PARAMETER_NAME, self.next_cursor.map_string())
             .add_opt_param(T::COUNT_PARAMETER_NAME, self.page_size.map_string());
 
-        let req = get(self.link, &self.token, Some(&params));
-        request_with_json_response(req)
+        let req = self.client.request_get(self.link, Some(&params));
+        let client = self.client.clone();
+        async move { client.response_json(req).await }
     }
 
     ///Creates a new instance of CursorIter, with the given parameters and empty initial results.
@@ -375,13 +426,13 @@ where
     ///code.
     pub(crate) fn new(
         link: &'static str,
-        token: &auth::Token,
+        client: Client,
         params_base: Option<ParamList>,
         page_size: Option<i32>,
     ) -> CursorIter<T> {
         CursorIter {
             link,
-            token: token.clone(),
+            client,
             params_base,
             page_size,
             previous_cursor: None,
@@ -413,6 +464,15 @@ where
                     let resp = Response::map(resp, |r| r.into_inner());
                     let rate = resp.rate_limit_status;
 
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        endpoint = self.link,
+                        previous_cursor = self.previous_cursor.as_ref().map(tracing::field::display),
+                        next_cursor = self.next_cursor.as_ref().map(tracing::field::display),
+                        items = resp.response.len(),
+                        "loaded cursor page",
+                    );
+
                     let mut iter = Box::new(resp.response.into_iter().map(move |item| Response {
                         rate_limit_status: rate,
                         response: item,
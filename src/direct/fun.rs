@@ -2,18 +2,17 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::common::*;
-
-use crate::{auth, links};
+use crate::links;
 use crate::user::{self, UserID};
 
 use super::*;
 
 /// Lookup a single DM by its numeric ID.
-pub async fn show(id: u64, token: &auth::Token) -> Result<Response<DirectMessage>, error::Error> {
+pub async fn show(id: u64, token: impl Into<Client>) -> Result<Response<DirectMessage>, error::Error> {
+    let client: Client = token.into();
     let params = ParamList::default().add_param("id", id.to_string());
-    let req = get(links::direct::SHOW, token, Some(&params));
-    let resp: Response<raw::SingleEvent> = request_with_json_response(req).await?;
+    let req = client.request_get(links::direct::SHOW, Some(&params));
+    let resp: Response<raw::SingleEvent> = client.response_json(req).await?;
     Ok(Response::into(resp))
 }
 
@@ -23,8 +22,8 @@ pub async fn show(id: u64, token: &auth::Token) -> Result<Response<DirectMessage
 /// information, see the docs for [`Timeline`].
 ///
 /// [`Timeline`]: struct.Timeline.html
-pub fn list(token: &auth::Token) -> Timeline {
-    Timeline::new(links::direct::LIST, token.clone())
+pub fn list(token: impl Into<Client>) -> Timeline {
+    Timeline::new(links::direct::LIST, token.into())
 }
 
 /// Delete the direct message with the given ID.
@@ -36,10 +35,11 @@ pub fn list(token: &auth::Token) -> Timeline {
 ///
 /// Twitter does not return anything upon a successful deletion, so this function will return an
 /// empty `Response` upon success.
-pub async fn delete(id: u64, token: &auth::Token) -> Result<Response<()>, error::Error> {
+pub async fn delete(id: u64, token: impl Into<Client>) -> Result<Response<()>, error::Error> {
+    let client: Client = token.into();
     let params = ParamList::new().add_param("id", id.to_string());
-    let req = auth::raw::delete(links::direct::DELETE, token, Some(&params));
-    request_with_empty_response(req).await
+    let req = client.request_delete(links::direct::DELETE, Some(&params));
+    client.response_empty(req).await
 }
 
 /// Marks the given message as read in the sender's interface.
@@ -56,20 +56,21 @@ pub async fn delete(id: u64, token: &auth::Token) -> Result<Response<()>, error:
 pub async fn mark_read(
     id: u64,
     sender: impl Into<UserID>,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<()>, error::Error> {
+    let client: Client = token.into();
     let recipient_id = match sender.into() {
         UserID::ID(id) => id,
         UserID::ScreenName(name) => {
-            let user = user::show(name, token).await?;
+            let user = user::show(name, &client).await?;
             user.id
         }
     };
     let params = ParamList::new()
         .add_param("last_read_event_id", id.to_string())
         .add_param("recipient_id", recipient_id.to_string());
-    let req = post(links::direct::MARK_READ, token, Some(&params));
-    request_with_empty_response(req).await
+    let req = client.request_post(links::direct::MARK_READ, Some(&params));
+    client.response_empty(req).await
 }
 
 /// Displays a visual typing indicator for the recipient.
@@ -88,17 +89,18 @@ pub async fn mark_read(
 /// pass a numeric ID (or the `UserID::ID` variant of `UserID`) to this function.
 pub async fn indicate_typing(
     recipient: impl Into<UserID>,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<()>, error::Error> {
+    let client: Client = token.into();
     let recipient_id = match recipient.into() {
         UserID::ID(id) => id,
         UserID::ScreenName(name) => {
-            let user = user::show(name, token).await?;
+            let user = user::show(name, &client).await?;
             user.id
         }
     };
 
     let params = ParamList::new().add_param("recipient_id", recipient_id.to_string());
-    let req = post(links::direct::INDICATE_TYPING, token, Some(&params));
-    request_with_empty_response(req).await
+    let req = client.request_post(links::direct::INDICATE_TYPING, Some(&params));
+    client.response_empty(req).await
 }
@@ -45,8 +45,9 @@ use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use hyper::{Body, Request};
 use serde::{Serialize, Deserialize};
 
+use crate::auth::AccessLevel;
 use crate::common::*;
-use crate::{auth, entities, error, links, media};
+use crate::{entities, error, links, media};
 use crate::user::{self, UserID};
 use crate::tweet::TweetSource;
 
@@ -237,7 +238,7 @@ pub struct QuickReply {
 /// [`DMConversations`]: type.DMConversations.html
 pub struct Timeline {
     link: &'static str,
-    token: auth::Token,
+    client: Client,
     /// The number of messages to request in a single page. The default is 20; the maximum is 50.
     pub count: u32,
     /// The string ID that can be used to load the next page of results. A value of `None`
@@ -249,10 +250,10 @@ pub struct Timeline {
 }
 
 impl Timeline {
-    pub(crate) fn new(link: &'static str, token: auth::Token) -> Timeline {
+    pub(crate) fn new(link: &'static str, client: Client) -> Timeline {
         Timeline {
             link,
-            token,
+            client,
             count: 20,
             next_cursor: None,
             loaded: false,
@@ -279,7 +280,7 @@ impl Timeline {
             .add_param("count", self.count.to_string())
             .add_opt_param("cursor", cursor);
 
-        get(self.link, &self.token, Some(&params))
+        self.client.request_get(self.link, Some(&params))
     }
 
     /// Clear the saved cursor information on this timeline, then return the most recent set of
@@ -298,7 +299,8 @@ impl Timeline {
     {
         let next_cursor = self.next_cursor.take();
         let req = self.request(next_cursor);
-        let loader = request_with_json_response(req);
+        let client = self.client.clone();
+        let loader = async move { client.response_json(req).await };
         loader.map(
             move |resp: Result<Response<raw::EventCursor>, error::Error>| {
                 let mut resp = resp?;
@@ -540,11 +542,13 @@ impl DraftMessage {
     ///
     /// If the message was successfully sent, this function will return the `DirectMessage` that
     /// was just sent.
-    pub async fn send(self, token: &auth::Token) -> Result<Response<DirectMessage>, error::Error> {
+    pub async fn send(self, token: impl Into<Client>) -> Result<Response<DirectMessage>, error::Error> {
+        let client: Client = token.into();
+        client.require_access(AccessLevel::ReadWriteDirectMessages)?;
         let recipient_id = match self.recipient {
             UserID::ID(id) => id,
             UserID::ScreenName(name) => {
-                let user = user::show(name, token).await?;
+                let user = user::show(name, &client).await?;
                 user.id
             }
         };
@@ -586,8 +590,8 @@ impl DraftMessage {
                 }
             }
         });
-        let req = post_json(links::direct::SEND, token, message);
-        let resp: Response<raw::SingleEvent> = request_with_json_response(req).await?;
+        let req = client.request_post_json(links::direct::SEND, message);
+        let resp: Response<raw::SingleEvent> = client.response_json(req).await?;
         Ok(Response::into(resp))
     }
 }
@@ -9,22 +9,34 @@
 //! anything in between, those errors are all represented in the (rather sprawling) [`Error`] enum.
 //! Any errors direct from Twitter are represented as a collection of [`TwitterErrorCode`]s,
 //! contained in a [`TwitterErrors`] wrapper, and held in the `Error::TwitterError` enum variant.
-//! For more information, see the documentation for the [`Error`] enum.
+//! Errors from the v2 API are represented as a [`Problem`], held in the `Error::TwitterProblem`
+//! enum variant. For more information, see the documentation for the [`Error`] enum.
+//!
+//! Rather than matching on individual error codes, most code can use the classification methods
+//! on `Error`, like [`is_retryable`] or [`is_not_found`], to decide how to handle an error. The
+//! codes that Twitter documents are available as the [`KnownErrorCode`] enum.
 //!
 //! [`Error`]: enum.Error.html
 //! [`TwitterErrorCode`]: struct.TwitterErrorCode.html
 //! [`TwitterErrors`]: struct.TwitterErrors.html
+//! [`Problem`]: struct.Problem.html
+//! [`KnownErrorCode`]: enum.KnownErrorCode.html
+//! [`is_retryable`]: enum.Error.html#method.is_retryable
+//! [`is_not_found`]: enum.Error.html#method.is_not_found
 
-use chrono;
+use chrono::{self, DateTime, Utc};
 use hyper;
 #[cfg(feature = "native_tls")]
 use native_tls;
 use serde::{Deserialize, Serialize};
 use serde_json;
+use std::borrow::Cow;
+use std::time::Duration;
 use std::{self, fmt};
 use tokio;
 
-use crate::common::Headers;
+use crate::auth::AccessLevel;
+use crate::common::{Headers, RateLimit};
 
 /// Convenient alias to a Result containing a local Error type
 pub type Result<T> = std::result::Result<T, Error>;
@@ -69,12 +81,281 @@ pub struct TwitterErrorCode {
     pub code: i32,
 }
 
+impl TwitterErrorCode {
+    ///Returns the documented error this code corresponds to, if it's one that egg-mode knows
+    ///about.
+    pub fn known(&self) -> Option<KnownErrorCode> {
+        KnownErrorCode::from_code(self.code)
+    }
+}
+
 impl fmt::Display for TwitterErrorCode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "#{}: {}", self.code, self.message)
     }
 }
 
+macro_rules! known_error_codes {
+    ($($(#[$attr:meta])* $name:ident = $code:expr,)*) => {
+        ///The error codes documented by Twitter for the v1.1 API.
+        ///
+        ///`TwitterErrorCode` holds the raw numeric code that Twitter returned, which can be turned
+        ///into one of these with [`TwitterErrorCode::known`]. Errors from the v2 API are described
+        ///by a problem type instead of a number; the ones that have an equivalent v1.1 code can be
+        ///turned into one of these with [`Problem::known`].
+        ///
+        ///For the full list of codes and their meanings, see the [API documentation][error-codes].
+        ///Most of the time it's easier to use the classification methods on [`Error`], like
+        ///`is_not_found` or `is_retryable`, than to match on individual codes.
+        ///
+        ///[`TwitterErrorCode::known`]: struct.TwitterErrorCode.html#method.known
+        ///[`Problem::known`]: struct.Problem.html#method.known
+        ///[`Error`]: enum.Error.html
+        ///[error-codes]: https://developer.twitter.com/en/docs/basics/response-codes
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum KnownErrorCode {
+            $($(#[$attr])* $name,)*
+        }
+
+        impl KnownErrorCode {
+            ///Returns the known error with the given numeric code, if there is one.
+            pub fn from_code(code: i32) -> Option<KnownErrorCode> {
+                match code {
+                    $($code => Some(KnownErrorCode::$name),)*
+                    _ => None,
+                }
+            }
+
+            ///Returns the numeric code Twitter uses for this error.
+            pub fn code(self) -> i32 {
+                match self {
+                    $(KnownErrorCode::$name => $code,)*
+                }
+            }
+        }
+    };
+}
+
+known_error_codes! {
+    ///3: Invalid coordinates.
+    InvalidCoordinates = 3,
+    ///13: No location associated with the specified IP address.
+    NoLocationForIp = 13,
+    ///17: No user matches for specified terms.
+    NoUserMatches = 17,
+    ///32: Could not authenticate you.
+    CouldNotAuthenticate = 32,
+    ///34: Sorry, that page does not exist.
+    PageNotFound = 34,
+    ///36: You cannot report yourself for spam.
+    CannotReportSelf = 36,
+    ///38: A required parameter is missing.
+    MissingParameter = 38,
+    ///44: The `attachment_url` parameter is invalid.
+    InvalidAttachmentUrl = 44,
+    ///50: User not found.
+    UserNotFound = 50,
+    ///63: User has been suspended.
+    UserSuspended = 63,
+    ///64: Your account is suspended and is not permitted to access this feature.
+    AccountSuspended = 64,
+    ///68: The Twitter REST API v1 is no longer active.
+    ApiRetired = 68,
+    ///87: Client is not permitted to perform this action.
+    ClientNotPermitted = 87,
+    ///88: Rate limit exceeded.
+    RateLimitExceeded = 88,
+    ///89: Invalid or expired token.
+    InvalidToken = 89,
+    ///92: SSL is required.
+    SslRequired = 92,
+    ///93: This application is not allowed to access or delete your direct messages.
+    DirectMessagesNotPermitted = 93,
+    ///99: Unable to verify your credentials.
+    CredentialsNotVerified = 99,
+    ///109: The specified user is not a member of this list.
+    NotListMember = 109,
+    ///120: Account update failed: a value is too long.
+    AccountUpdateFailed = 120,
+    ///130: Over capacity.
+    OverCapacity = 130,
+    ///131: Internal error.
+    InternalError = 131,
+    ///135: Could not authenticate you, because the timestamp was out of bounds.
+    TimestampOutOfBounds = 135,
+    ///139: You have already favorited this status.
+    AlreadyFavorited = 139,
+    ///144: No status found with that ID.
+    StatusNotFound = 144,
+    ///150: You cannot send messages to users who are not following you.
+    RecipientNotFollowing = 150,
+    ///151: There was an error sending your message.
+    MessageSendFailed = 151,
+    ///160: You've already requested to follow this user.
+    FollowAlreadyRequested = 160,
+    ///161: You are unable to follow more people at this time.
+    FollowLimitReached = 161,
+    ///179: Sorry, you are not authorized to see this status.
+    NotAuthorizedForStatus = 179,
+    ///185: User is over the daily status update limit.
+    StatusUpdateLimit = 185,
+    ///186: Tweet needs to be a bit shorter.
+    TweetTooLong = 186,
+    ///187: Status is a duplicate.
+    DuplicateStatus = 187,
+    ///195: Missing or invalid url parameter.
+    InvalidUrlParameter = 195,
+    ///205: You are over the limit for spam reports.
+    SpamReportLimit = 205,
+    ///214: Owner must allow DMs from anyone, or the request is otherwise malformed.
+    BadRequest = 214,
+    ///215: Bad authentication data.
+    BadAuthenticationData = 215,
+    ///220: Your credentials do not allow access to this resource.
+    CredentialsForbidden = 220,
+    ///226: This request looks like it might be automated.
+    AutomatedRequest = 226,
+    ///231: User must verify login.
+    LoginVerificationNeeded = 231,
+    ///251: This endpoint has been retired and should not be used.
+    EndpointRetired = 251,
+    ///261: Application cannot perform write actions.
+    ApplicationWriteRestricted = 261,
+    ///271: You can't mute yourself.
+    CannotMuteSelf = 271,
+    ///272: You are not muting the specified user.
+    NotMuting = 272,
+    ///323: Animated GIFs are not allowed when posting multiple images.
+    GifWithMultipleImages = 323,
+    ///324: The validation of media ids failed.
+    InvalidMediaIds = 324,
+    ///325: A media id was not found.
+    MediaIdNotFound = 325,
+    ///326: This account is temporarily locked.
+    AccountLocked = 326,
+    ///327: You have already retweeted this Tweet.
+    AlreadyRetweeted = 327,
+    ///349: You cannot send messages to this user.
+    CannotMessageUser = 349,
+    ///354: The text of your direct message is over the max character limit.
+    MessageTooLong = 354,
+    ///355: Subscription already exists.
+    SubscriptionExists = 355,
+    ///385: You attempted to reply to a Tweet that is deleted or not visible to you.
+    ReplyToUnavailableTweet = 385,
+    ///386: The Tweet exceeds the number of allowed attachment types.
+    TooManyAttachments = 386,
+    ///407: The given URL is invalid.
+    InvalidUrl = 407,
+    ///415: Callback URL not approved for this client application.
+    CallbackNotApproved = 415,
+    ///416: Invalid or suspended application.
+    InvalidApplication = 416,
+    ///421: This Tweet is no longer available.
+    TweetUnavailable = 421,
+    ///422: This Tweet is no longer available because it violated the Twitter Rules.
+    TweetViolatedRules = 422,
+    ///433: The original Tweet author restricted who can reply to this Tweet.
+    ReplyRestricted = 433,
+}
+
+impl KnownErrorCode {
+    ///Returns the v1.1 error that corresponds to the given v2 problem type, if there is one.
+    ///
+    ///The problem type can be given as either the full URI that Twitter returns, like
+    ///`https://api.twitter.com/2/problems/resource-not-found`, or just its last segment.
+    pub fn from_problem_type(problem_type: &str) -> Option<KnownErrorCode> {
+        let name = problem_type.rsplit('/').next().unwrap_or(problem_type);
+        match name {
+            "resource-not-found" => Some(KnownErrorCode::PageNotFound),
+            "not-authorized-for-resource" => Some(KnownErrorCode::NotAuthorizedForStatus),
+            "unsupported-authentication" => Some(KnownErrorCode::BadAuthenticationData),
+            "client-forbidden" => Some(KnownErrorCode::ClientNotPermitted),
+            _ => None,
+        }
+    }
+}
+
+///Represents an error returned from a call to the v2 API.
+///
+///The v2 API describes errors as "problems", which are identified by a URI in `problem_type`
+///rather than a numeric code. This is returned as part of [`Error::TwitterProblem`][] whenever
+///Twitter has rejected a call to the v2 API.
+///
+///[`Error::TwitterProblem`]: enum.Error.html
+#[derive(Debug, Clone, Deserialize, Serialize, thiserror::Error)]
+pub struct Problem {
+    ///The URI identifying the type of problem, like
+    ///`https://api.twitter.com/2/problems/resource-not-found`.
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    ///A short summary of the problem.
+    pub title: String,
+    ///A longer description of this occurrence of the problem, if given.
+    #[serde(default)]
+    pub detail: Option<String>,
+    ///The HTTP status code of the response, if given.
+    #[serde(default)]
+    pub status: Option<u16>,
+}
+
+impl Problem {
+    ///Returns the v1.1 error that corresponds to this problem's type, if there is one.
+    pub fn known(&self) -> Option<KnownErrorCode> {
+        KnownErrorCode::from_problem_type(&self.problem_type)
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{}: {}", self.title, detail),
+            None => write!(f, "{}", self.title),
+        }
+    }
+}
+
+///The most bytes of a response body that are kept in an [`ErrorResponse`].
+///
+///[`ErrorResponse`]: struct.ErrorResponse.html
+pub const MAX_ERROR_BODY: usize = 16 * 1024;
+
+///A copy of the response that led to an error, kept for debugging.
+///
+///When Twitter returns something egg-mode didn't expect, like an error status with no error code,
+///or a payload that doesn't match the types egg-mode deserializes it into, the headers and the
+///start of the body are kept in an `ErrorResponse` in the returned `Error`, so that it's possible
+///to see what actually came back. Only the first [`MAX_ERROR_BODY`] bytes of the body are kept.
+///
+///[`MAX_ERROR_BODY`]: constant.MAX_ERROR_BODY.html
+#[derive(Debug, Clone, Default)]
+pub struct ErrorResponse {
+    ///The headers of the response.
+    pub headers: Headers,
+    ///The start of the response body, up to `MAX_ERROR_BODY` bytes.
+    pub body: Vec<u8>,
+    ///Whether the body was longer than `MAX_ERROR_BODY`, and was cut short.
+    pub truncated: bool,
+}
+
+impl ErrorResponse {
+    ///Copies the given headers and the start of the given body into a new `ErrorResponse`.
+    pub(crate) fn new(headers: Headers, body: &[u8]) -> ErrorResponse {
+        let truncated = body.len() > MAX_ERROR_BODY;
+        let body = body[..body.len().min(MAX_ERROR_BODY)].to_vec();
+        ErrorResponse {
+            headers,
+            body,
+            truncated,
+        }
+    }
+
+    ///Returns the body as text, replacing any invalid UTF-8 with `U+FFFD`.
+    pub fn body_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+}
+
 /// Represents an error that can occur during media processing.
 #[derive(Debug, Clone, PartialEq, Deserialize, thiserror::Error)]
 #[error("Media error {code} ({name}) - {message}")]
@@ -87,6 +368,75 @@ pub struct MediaError {
     pub message: String,
 }
 
+///The rate limits returned with a rate-limit error, as reported by `Error::RateLimit`.
+///
+///Besides the usual 15-minute rate limit, some endpoints that post content, like sending a tweet
+///or a Direct Message, also have limits on how many times they can be used in 24 hours, both for
+///the app as a whole and for each user. Twitter reports these with the `x-app-limit-24hour-*` and
+///`x-user-limit-24hour-*` headers, which are collected here when they're present.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitDetails {
+    ///The 15-minute rate limit for the endpoint. As with `Response`, any fields that weren't
+    ///returned by Twitter are set to `-1`.
+    pub rate_limit: RateLimit,
+    ///The 24-hour rate limit for the app, if one was returned.
+    pub app_24hour: Option<RateLimit>,
+    ///The 24-hour rate limit for the user, if one was returned.
+    pub user_24hour: Option<RateLimit>,
+}
+
+impl RateLimitDetails {
+    ///Returns the time at which every limit that was reached will have reset, or `None` if
+    ///Twitter didn't say when that would be.
+    pub fn reset_at(&self) -> Option<DateTime<Utc>> {
+        let limits = Some(self.rate_limit)
+            .into_iter()
+            .chain(self.app_24hour)
+            .chain(self.user_24hour);
+        let exhausted = limits
+            .clone()
+            .filter(|limit| limit.remaining == 0)
+            .filter_map(|limit| limit.reset_at())
+            .max();
+        exhausted.or_else(|| limits.filter_map(|limit| limit.reset_at()).max())
+    }
+
+    ///Returns how long to wait until every limit that was reached has reset, or `None` if Twitter
+    ///didn't say when that would be.
+    pub fn reset_in(&self) -> Option<Duration> {
+        let reset = self.reset_at()?;
+        Some((reset - Utc::now()).to_std().unwrap_or_default())
+    }
+}
+
+/// The kind of timeout that ended a request, as reported by `Error::Timeout`.
+///
+/// Each kind corresponds to one of the limits that can be set with [`Timeouts`].
+///
+/// [`Timeouts`]: ../client/struct.Timeouts.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// The connection to Twitter could not be opened in time.
+    Connect,
+    /// Twitter took too long to send the next part of its response.
+    Read,
+    /// The request as a whole took too long to complete.
+    Total,
+    /// A stream went too long without receiving any data.
+    Stall,
+}
+
+impl fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeoutKind::Connect => write!(f, "connect"),
+            TimeoutKind::Read => write!(f, "read"),
+            TimeoutKind::Total => write!(f, "total"),
+            TimeoutKind::Stall => write!(f, "stall"),
+        }
+    }
+}
+
 /// A set of errors that can occur when interacting with Twitter.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -106,6 +456,13 @@ pub enum Error {
     ///always be there, and need to update my parsing to reflect this.
     #[error("Value missing from response: {}", _0)]
     MissingValue(&'static str),
+    ///The request couldn't be sent as given, because some of its options don't work together.
+    ///The enclosed values are an explanatory string and, if applicable, the option that caused the
+    ///error.
+    ///
+    ///This is returned without sending the request.
+    #[error("Invalid request: {} ({:?})", _0, _1)]
+    InvalidRequest(&'static str, Option<String>),
     ///The `Future` being polled has already returned a completed value (or another error). In
     ///order to retry the request, create the `Future` again.
     #[error("Future has already completed")]
@@ -114,11 +471,30 @@ pub enum Error {
     ///enclosed value was the response from Twitter.
     #[error("Errors returned by Twitter: {_1}")]
     TwitterError(Headers, TwitterErrors),
+    ///The response from the v2 API described a problem instead of the expected response. The
+    ///enclosed value was the response from Twitter.
+    #[error("Problem returned by Twitter: {_1}")]
+    TwitterProblem(Headers, Box<Problem>),
     ///The response returned from Twitter contained an error indicating that the rate limit for
-    ///that method has been reached. The enclosed value is the Unix timestamp in UTC when the next
-    ///rate-limit window will open.
-    #[error("Rate limit reached, hold until {}", _0)]
-    RateLimit(i32),
+    ///that method has been reached. The enclosed value holds the rate limits that were returned
+    ///with the error, including the 24-hour limits used by posting endpoints.
+    #[error("Rate limit reached, hold until {}", hold_until(_0))]
+    RateLimit(RateLimitDetails),
+    ///The token doesn't have the access level a request needs. The enclosed values are the access
+    ///level the request needs, and the one the token has.
+    ///
+    ///This is returned without sending the request, when a `Client` has already seen the token's
+    ///access level in the `x-access-level` header of an earlier response. Otherwise, Twitter
+    ///rejects the request with an error code like 261 or 93.
+    #[error("Request needs {_0} access, but the token only has {_1} access")]
+    InsufficientAccess(AccessLevel, AccessLevel),
+    ///Twitter refused to refresh an OAuth 2.0 user token, usually because its refresh token was
+    ///already used or revoked. The enclosed values are the response code and the response from the
+    ///token endpoint.
+    ///
+    ///The token can't be used again, so the user needs to sign in again to get a new one.
+    #[error("Token refresh rejected: {}", _0)]
+    RefreshRejected(hyper::StatusCode, Box<ErrorResponse>),
     ///An attempt to upload a video or gif successfully uploaded the file, but failed in
     ///post-processing. The enclosed value contains the error message from Twitter.
     #[error("Error processing media: {}", _0)]
@@ -127,14 +503,24 @@ pub enum Error {
     ///was the response code.
     ///
     ///This is only returned if Twitter did not also return an [error code][TwitterErrors] in the
-    ///response body. That check is performed before examining the status code.
+    ///response body. That check is performed before examining the status code. The headers and
+    ///the start of the body of the response are kept in the enclosed [`ErrorResponse`].
     ///
     ///[TwitterErrors]: struct.TwitterErrors.html
+    ///[`ErrorResponse`]: struct.ErrorResponse.html
     #[error("Error status received: {}", _0)]
-    BadStatus(hyper::StatusCode),
+    BadStatus(hyper::StatusCode, Box<ErrorResponse>),
     ///The web request experienced an error. The enclosed error was returned from hyper.
     #[error("Network error: {}", _0)]
     NetError(#[from] hyper::Error),
+    ///The request did not complete within one of the limits set with `Client::with_timeouts`.
+    ///The enclosed value says which limit was reached.
+    #[error("Request timed out ({} timeout)", _0)]
+    Timeout(TimeoutKind),
+    ///A custom `Transport` given to a `Client` experienced an error. The enclosed error was
+    ///returned from the transport.
+    #[error("Transport error: {}", _0)]
+    TransportError(Box<dyn std::error::Error + Send + Sync>),
     ///The `native_tls` implementation returned an error. The enclosed error was returned from
     ///`native_tls`.
     #[cfg(feature = "native_tls")]
@@ -146,8 +532,18 @@ pub enum Error {
     IOError(#[from] std::io::Error),
     ///An error occurred while loading the JSON response. The enclosed error was returned from
     ///`serde_json`.
-    #[error("JSON deserialize error: {}", _0)]
-    DeserializeError(#[from] serde_json::Error),
+    ///
+    ///When the error came from parsing a response from Twitter, the second value is the path to
+    ///the part of the JSON that couldn't be parsed, like `statuses[3].user.id`, and the third holds
+    ///the headers and the start of the body of the response, in an [`ErrorResponse`].
+    ///
+    ///[`ErrorResponse`]: struct.ErrorResponse.html
+    #[error("JSON deserialize error: {}{}", _0, at_path(_1))]
+    DeserializeError(
+        #[source] serde_json::Error,
+        Option<String>,
+        Option<Box<ErrorResponse>>,
+    ),
     ///An error occurred when parsing a timestamp from Twitter. The enclosed error was returned
     ///from chrono.
     #[error("Error parsing timestamp: {}", _0)]
@@ -171,3 +567,252 @@ pub enum Error {
     #[error("Error converting headers: {}", _0)]
     HeaderConvertError(#[from] std::num::ParseIntError),
 }
+
+///Formats the path of a `DeserializeError` for display, if there is one.
+fn at_path(path: &Option<String>) -> String {
+    path.as_ref()
+        .map(|path| format!(" at {}", path))
+        .unwrap_or_default()
+}
+
+///Formats the time a `RateLimit` error resets for display, if Twitter said when that would be.
+fn hold_until(details: &RateLimitDetails) -> String {
+    details
+        .reset_at()
+        .map(|reset| reset.timestamp().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::DeserializeError(err, None, None)
+    }
+}
+
+impl Error {
+    ///Returns whether this error is likely to be temporary, so that sending the same request
+    ///again later might succeed.
+    ///
+    ///This includes network errors and timeouts, 5xx status codes, and the "Over capacity" and
+    ///"Internal error" codes. Rate limits are reported separately by `is_rate_limited`, since
+    ///they need to wait until the rate limit resets.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::NetError(_) | Error::Timeout(_) => true,
+            Error::BadStatus(status, _) => status.is_server_error(),
+            _ => self.has_known_code(|code| {
+                matches!(
+                    code,
+                    KnownErrorCode::OverCapacity | KnownErrorCode::InternalError
+                )
+            }),
+        }
+    }
+
+    ///Returns whether this error means the token used for the request was rejected, for example
+    ///because it was revoked, or the request wasn't signed correctly.
+    pub fn is_auth_failure(&self) -> bool {
+        match self {
+            Error::BadStatus(status, _) => *status == hyper::StatusCode::UNAUTHORIZED,
+            Error::RefreshRejected(..) => true,
+            _ => self.has_known_code(|code| {
+                matches!(
+                    code,
+                    KnownErrorCode::CouldNotAuthenticate
+                        | KnownErrorCode::InvalidToken
+                        | KnownErrorCode::CredentialsNotVerified
+                        | KnownErrorCode::TimestampOutOfBounds
+                        | KnownErrorCode::BadAuthenticationData
+                        | KnownErrorCode::CredentialsForbidden
+                )
+            }),
+        }
+    }
+
+    ///Returns whether this error means the requested tweet, user, or other resource doesn't exist.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            Error::BadStatus(status, _) => *status == hyper::StatusCode::NOT_FOUND,
+            _ => self.has_known_code(|code| {
+                matches!(
+                    code,
+                    KnownErrorCode::NoUserMatches
+                        | KnownErrorCode::PageNotFound
+                        | KnownErrorCode::UserNotFound
+                        | KnownErrorCode::StatusNotFound
+                )
+            }),
+        }
+    }
+
+    ///Returns whether this error means the request would have repeated an earlier action, like
+    ///posting the same status twice or liking a tweet that was already liked.
+    pub fn is_duplicate(&self) -> bool {
+        self.has_known_code(|code| {
+            matches!(
+                code,
+                KnownErrorCode::AlreadyFavorited
+                    | KnownErrorCode::DuplicateStatus
+                    | KnownErrorCode::AlreadyRetweeted
+            )
+        })
+    }
+
+    ///Returns whether this error means the account involved in the request has been suspended or
+    ///locked.
+    pub fn is_suspended(&self) -> bool {
+        self.has_known_code(|code| {
+            matches!(
+                code,
+                KnownErrorCode::UserSuspended
+                    | KnownErrorCode::AccountSuspended
+                    | KnownErrorCode::AccountLocked
+            )
+        })
+    }
+
+    ///Returns whether this error means a rate limit was reached, including the daily limit on
+    ///posting statuses.
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            Error::RateLimit(_) => true,
+            Error::BadStatus(status, _) => *status == hyper::StatusCode::TOO_MANY_REQUESTS,
+            _ => self.has_known_code(|code| {
+                matches!(
+                    code,
+                    KnownErrorCode::RateLimitExceeded | KnownErrorCode::StatusUpdateLimit
+                )
+            }),
+        }
+    }
+
+    ///Returns whether any of the errors returned by Twitter match the given predicate.
+    fn has_known_code(&self, pred: impl Fn(KnownErrorCode) -> bool) -> bool {
+        match self {
+            Error::TwitterError(_, errors) => {
+                errors.errors.iter().filter_map(|e| e.known()).any(pred)
+            }
+            Error::TwitterProblem(_, problem) => problem.known().into_iter().any(pred),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn twitter_error(code: i32) -> Error {
+        let errors = TwitterErrors {
+            errors: vec![TwitterErrorCode {
+                message: String::new(),
+                code,
+            }],
+        };
+        Error::TwitterError(Headers::new(), errors)
+    }
+
+    #[test]
+    fn known_codes_round_trip() {
+        assert_eq!(
+            KnownErrorCode::from_code(187),
+            Some(KnownErrorCode::DuplicateStatus)
+        );
+        assert_eq!(KnownErrorCode::DuplicateStatus.code(), 187);
+        assert_eq!(KnownErrorCode::from_code(-1), None);
+        assert_eq!(
+            KnownErrorCode::from_problem_type(
+                "https://api.twitter.com/2/problems/resource-not-found"
+            ),
+            Some(KnownErrorCode::PageNotFound)
+        );
+        assert_eq!(KnownErrorCode::from_problem_type("about:blank"), None);
+        // covers suspended, protected, and withheld resources alike
+        assert_eq!(KnownErrorCode::from_problem_type("resource-unavailable"), None);
+    }
+
+    #[test]
+    fn classify_errors() {
+        assert!(twitter_error(130).is_retryable());
+        assert!(twitter_error(89).is_auth_failure());
+        assert!(twitter_error(144).is_not_found());
+        assert!(twitter_error(187).is_duplicate());
+        assert!(twitter_error(326).is_suspended());
+        assert!(twitter_error(185).is_rate_limited());
+        assert!(!twitter_error(187).is_retryable());
+
+        assert!(Error::Timeout(TimeoutKind::Read).is_retryable());
+        assert!(
+            Error::BadStatus(hyper::StatusCode::TOO_MANY_REQUESTS, Default::default())
+                .is_rate_limited()
+        );
+        assert!(!Error::BadStatus(hyper::StatusCode::NOT_FOUND, Default::default()).is_retryable());
+        assert!(
+            Error::RefreshRejected(hyper::StatusCode::BAD_REQUEST, Default::default())
+                .is_auth_failure()
+        );
+
+        let problem: Problem = serde_json::from_str(
+            r#"{
+                "title": "Not Found Error",
+                "detail": "Could not find tweet with id: [20].",
+                "type": "https://api.twitter.com/2/problems/resource-not-found"
+            }"#,
+        )
+        .unwrap();
+        let err = Error::TwitterProblem(Headers::new(), Box::new(problem));
+        assert!(err.is_not_found());
+        assert!(!err.is_auth_failure());
+    }
+
+    #[test]
+    fn rate_limit_without_reset() {
+        let unknown = RateLimit {
+            limit: -1,
+            remaining: -1,
+            reset: -1,
+        };
+        let details = RateLimitDetails {
+            rate_limit: unknown,
+            app_24hour: None,
+            user_24hour: None,
+        };
+        assert!(details.reset_at().is_none());
+        assert_eq!(
+            Error::RateLimit(details).to_string(),
+            "Rate limit reached, hold until unknown"
+        );
+    }
+
+    #[test]
+    fn deserialize_error_keeps_response() {
+        #[derive(Debug, Deserialize)]
+        struct Outer {
+            #[allow(dead_code)]
+            inner: Vec<Inner>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Inner {
+            #[allow(dead_code)]
+            id: u64,
+        }
+
+        let body = br#"{"inner": [{"id": 1}, {"id": "two"}]}"#;
+        let err = crate::common::json_response::<Outer>(&Headers::new(), body).unwrap_err();
+        assert!(err.to_string().ends_with(" at inner[1].id"));
+        match err {
+            Error::DeserializeError(_, Some(path), Some(response)) => {
+                assert_eq!(path, "inner[1].id");
+                assert_eq!(response.body, &body[..]);
+                assert!(!response.truncated);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let long = vec![b'x'; MAX_ERROR_BODY + 1];
+        let response = ErrorResponse::new(Headers::new(), &long);
+        assert_eq!(response.body.len(), MAX_ERROR_BODY);
+        assert!(response.truncated);
+    }
+}
@@ -81,6 +81,15 @@
 //! compile error will result. If you need to use `rustls` or `rustls_webpki`, remember to set
 //! `default-features = false` in your Cargo.toml.
 //!
+//! Separately from the TLS features, egg-mode has one more optional feature:
+//!
+//! * `tracing`: Off by default. With this feature on, egg-mode emits spans and events with the
+//!   `tracing` crate for every request it sends. Each request is traced in an `egg_mode::request`
+//!   span with its method, host, and path, and events record its status, latency, rate-limit
+//!   headers, and any retries. `CursorIter` records each page it loads, and `TwitterStream` records
+//!   its connections and the kind of each message it receives. Tokens, signatures, query strings,
+//!   and `Authorization` headers are never recorded.
+//!
 //! # Types and Functions
 //!
 //! All of the main content of egg-mode is in submodules, but there are a few things here in the
@@ -106,6 +115,16 @@
 //! existing code that used the type, and to aid the authentication process, which requires
 //! manually creating one at the very beginning.
 //!
+//! ## `Client`
+//!
+//! Every function that calls Twitter accepts anything that can be converted into a [`Client`],
+//! which pairs a `Token` with a pool of HTTP connections. Passing `&token` directly opens a new
+//! connection for each call, while creating a `Client` once and passing `&client` lets successive
+//! calls reuse the same connections. See [the `client` module][client] for more information.
+//!
+//! [`Client`]: struct.Client.html
+//! [client]: client/index.html
+//!
 //! # Modules
 //!
 //! As there are many actions available in the Twitter API, egg-mode divides them roughly into
@@ -132,6 +151,8 @@
 //!   removing users, or loading the posts made by their members.
 //! * `media`: This module lets you upload images, GIFs, and videos to Twitter so you can attach
 //!   them to tweets.
+//! * `activity`: This module lets you receive a user's tweets, likes, follows, and Direct Messages
+//!   as they happen, by registering a webhook with the Account Activity API.
 //!
 //! ## Secondary actions
 //!
@@ -149,6 +170,8 @@
 //! These modules contain some implementations that wrap some pattern seen in multiple "action"
 //! modules.
 //!
+//! * `client`: This contains the `Client` type, which lets you reuse connections to Twitter
+//!   across many calls.
 //! * `cursor`: This contains a helper trait and some helper structs that allow effective cursoring
 //!   through certain collections of results from Twitter.
 //! * `entities`: Whenever some text can be returned that may contain links, hashtags, media, or
@@ -163,7 +186,9 @@
 
 #[macro_use]
 mod common;
+pub mod activity;
 pub mod auth;
+pub mod client;
 pub mod cursor;
 pub mod direct;
 pub mod entities;
@@ -180,4 +205,5 @@ pub mod tweet;
 pub mod user;
 
 pub use crate::auth::{Token, KeyPair};
+pub use crate::client::Client;
 pub use crate::common::{Response, ResponseIter, RateLimit};
@@ -2,6 +2,16 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+// n.b. every link below is written against the default hosts in `base`. `client::Endpoints`
+// swaps these prefixes out at request time, so new links need to start with one of them.
+
+pub mod base {
+    pub const REST: &'static str = "https://api.twitter.com";
+    pub const UPLOAD: &'static str = "https://upload.twitter.com";
+    pub const STREAM: &'static str = "https://stream.twitter.com";
+    pub const V2: &'static str = "https://api.twitter.com/2";
+}
+
 pub mod auth {
     pub const REQUEST_TOKEN: &'static str = "https://api.twitter.com/oauth/request_token";
     pub const ACCESS_TOKEN: &'static str = "https://api.twitter.com/oauth/access_token";
@@ -9,6 +19,9 @@ pub mod auth {
     pub const INVALIDATE_BEARER: &'static str = "https://api.twitter.com/oauth2/invalidate_token";
     pub const AUTHORIZE: &'static str = "https://api.twitter.com/oauth/authorize";
     pub const AUTHENTICATE: &'static str = "https://api.twitter.com/oauth/authenticate";
+    pub const OAUTH2_AUTHORIZE: &'static str = "https://twitter.com/i/oauth2/authorize";
+    pub const OAUTH2_TOKEN: &'static str = "https://api.twitter.com/2/oauth2/token";
+    pub const OAUTH2_REVOKE: &'static str = "https://api.twitter.com/2/oauth2/revoke";
     pub const VERIFY_CREDENTIALS: &'static str =
         "https://api.twitter.com/1.1/account/verify_credentials.json";
 }
@@ -116,6 +129,10 @@ pub mod direct {
     pub const INDICATE_TYPING: &'static str = "https://api.twitter.com/1.1/direct_messages/indicate_typing.json";
 }
 
+pub mod activity {
+    pub const ALL: &'static str = "https://api.twitter.com/1.1/account_activity/all";
+}
+
 pub mod service {
     pub const TERMS: &'static str = "https://api.twitter.com/1.1/help/tos.json";
     pub const PRIVACY: &'static str = "https://api.twitter.com/1.1/help/privacy.json";
@@ -137,4 +154,15 @@ pub mod v2 {
         #[allow(unused)]
         pub const ALL: &'static str = "https://api.twitter.com/2/tweets/search/all";
     }
+
+    pub mod tweets {
+        pub const LOOKUP: &'static str = "https://api.twitter.com/2/tweets";
+    }
+
+    pub mod users {
+        pub const LOOKUP: &'static str = "https://api.twitter.com/2/users";
+        pub const LOOKUP_NAMES: &'static str = "https://api.twitter.com/2/users/by";
+        pub const SHOW_NAME: &'static str = "https://api.twitter.com/2/users/by/username";
+        pub const ME: &'static str = "https://api.twitter.com/2/users/me";
+    }
 }
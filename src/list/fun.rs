@@ -8,17 +8,17 @@ use super::*;
 
 use crate::common::RateLimit;
 use crate::cursor::{CursorIter, ListCursor, UserCursor};
-use crate::error::{Error::TwitterError, Result};
+use crate::error::{Error::TwitterError, KnownErrorCode, Result};
 use crate::user::{TwitterUser, UserID};
-use crate::{auth, links, tweet};
+use crate::{links, tweet};
 
 ///Look up the lists the given user has been added to.
 ///
 ///This function returns a `Stream` over the lists returned by Twitter. This method defaults to
 ///reeturning 20 lists in a single network call; the maximum is 1000.
-pub fn memberships<T: Into<UserID>>(user: T, token: &auth::Token) -> CursorIter<ListCursor> {
+pub fn memberships<T: Into<UserID>>(user: T, token: impl Into<Client>) -> CursorIter<ListCursor> {
     let params = ParamList::new().add_user_param(user.into());
-    CursorIter::new(links::lists::MEMBERSHIPS, token, Some(params), Some(20))
+    CursorIter::new(links::lists::MEMBERSHIPS, token.into(), Some(params), Some(20))
 }
 
 ///Return up to 100 lists the given user is subscribed to, including those the user made
@@ -35,82 +35,85 @@ pub fn memberships<T: Into<UserID>>(user: T, token: &auth::Token) -> CursorIter<
 pub async fn list<'id, T: Into<UserID>>(
     user: T,
     owned_first: bool,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<Vec<List>>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .add_user_param(user.into())
         .add_param("reverse", owned_first.to_string());
 
-    let req = get(links::lists::LIST, token, Some(&params));
+    let req = client.request_get(links::lists::LIST, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 ///Look up the lists the given user is subscribed to, but not ones the user made themselves.
 ///
 ///This function returns a `Stream` over the lists returned by Twitter. This method defaults to
 ///reeturning 20 lists in a single network call; the maximum is 1000.
-pub fn subscriptions<T: Into<UserID>>(user: T, token: &auth::Token) -> CursorIter<ListCursor> {
+pub fn subscriptions<T: Into<UserID>>(user: T, token: impl Into<Client>) -> CursorIter<ListCursor> {
     let params = ParamList::new().add_user_param(user.into());
-    CursorIter::new(links::lists::SUBSCRIPTIONS, token, Some(params), Some(20))
+    CursorIter::new(links::lists::SUBSCRIPTIONS, token.into(), Some(params), Some(20))
 }
 
 ///Look up the lists created by the given user.
 ///
 ///This function returns a `Stream` over the lists returned by Twitter. This method defaults to
 ///reeturning 20 lists in a single network call; the maximum is 1000.
-pub fn ownerships<T: Into<UserID>>(user: T, token: &auth::Token) -> CursorIter<ListCursor> {
+pub fn ownerships<T: Into<UserID>>(user: T, token: impl Into<Client>) -> CursorIter<ListCursor> {
     let params = ParamList::new().add_user_param(user.into());
-    CursorIter::new(links::lists::OWNERSHIPS, token, Some(params), Some(20))
+    CursorIter::new(links::lists::OWNERSHIPS, token.into(), Some(params), Some(20))
 }
 
 ///Look up information for a single list.
-pub async fn show(list: ListID, token: &auth::Token) -> Result<Response<List>> {
+pub async fn show(list: ListID, token: impl Into<Client>) -> Result<Response<List>> {
+    let client: Client = token.into();
     let params = ParamList::new().add_list_param(list);
 
-    let req = get(links::lists::SHOW, token, Some(&params));
+    let req = client.request_get(links::lists::SHOW, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 ///Look up the users that have been added to the given list.
 ///
 ///This function returns a `Stream` over the users returned by Twitter. This method defaults to
 ///reeturning 20 users in a single network call; the maximum is 5000.
-pub fn members<'a>(list: ListID, token: &auth::Token) -> CursorIter<UserCursor> {
+pub fn members<'a>(list: ListID, token: impl Into<Client>) -> CursorIter<UserCursor> {
     let params = ParamList::new().add_list_param(list);
 
-    CursorIter::new(links::lists::MEMBERS, token, Some(params), Some(20))
+    CursorIter::new(links::lists::MEMBERS, token.into(), Some(params), Some(20))
 }
 
 ///Look up the users that have subscribed to the given list.
 ///
 ///This function returns a `Stream` over the users returned by Twitter. This method defaults to
 ///reeturning 20 users in a single network call; the maximum is 5000.
-pub fn subscribers<'a>(list: ListID, token: &auth::Token) -> CursorIter<UserCursor> {
+pub fn subscribers<'a>(list: ListID, token: impl Into<Client>) -> CursorIter<UserCursor> {
     let params = ParamList::new().add_list_param(list);
 
-    CursorIter::new(links::lists::SUBSCRIBERS, token, Some(params), Some(20))
+    CursorIter::new(links::lists::SUBSCRIBERS, token.into(), Some(params), Some(20))
 }
 
 ///Check whether the given user is subscribed to the given list.
 pub async fn is_subscribed<'id, T: Into<UserID>>(
     user: T,
     list: ListID,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<bool>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .add_list_param(list)
         .add_user_param(user.into());
 
-    let req = get(links::lists::IS_SUBSCRIBER, token, Some(&params));
+    let req = client.request_get(links::lists::IS_SUBSCRIBER, Some(&params));
 
-    let out = request_with_json_response::<TwitterUser>(req).await;
+    let out = client.response_json::<TwitterUser>(req).await;
 
     match out {
         Ok(user) => Ok(Response::map(user, |_| true)),
         Err(TwitterError(headers, terrs)) => {
-            if terrs.errors.iter().any(|e| e.code == 109) {
+            if terrs.errors.iter().any(|e| e.known() == Some(KnownErrorCode::NotListMember)) {
                 // here's a fun conundrum: since "is not in this list" is returned as an error code,
                 // the rate limit info that would otherwise be part of the response isn't there. the
                 // rate_headers method was factored out specifically for this location, since it's
@@ -128,19 +131,20 @@ pub async fn is_subscribed<'id, T: Into<UserID>>(
 pub async fn is_member<'id, T: Into<UserID>>(
     user: T,
     list: ListID,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<bool>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .add_list_param(list)
         .add_user_param(user.into());
 
-    let req = get(links::lists::IS_MEMBER, token, Some(&params));
-    let out = request_with_json_response::<TwitterUser>(req).await;
+    let req = client.request_get(links::lists::IS_MEMBER, Some(&params));
+    let out = client.response_json::<TwitterUser>(req).await;
 
     match out {
         Ok(resp) => Ok(Response::map(resp, |_| true)),
         Err(TwitterError(headers, errors)) => {
-            if errors.errors.iter().any(|e| e.code == 109) {
+            if errors.errors.iter().any(|e| e.known() == Some(KnownErrorCode::NotListMember)) {
                 // here's a fun conundrum: since "is not in this list" is returned as an error code,
                 // the rate limit info that would otherwise be part of the response isn't there. the
                 // rate_headers method was factored out specifically for this location, since it's
@@ -160,12 +164,12 @@ pub async fn is_member<'id, T: Into<UserID>>(
 ///timeline. see the [`Timeline`] docs for details.
 ///
 ///[`Timeline`]: ../tweet/struct.Timeline.html
-pub fn statuses(list: ListID, with_rts: bool, token: &auth::Token) -> tweet::Timeline {
+pub fn statuses(list: ListID, with_rts: bool, token: impl Into<Client>) -> tweet::Timeline {
     let params = ParamList::new()
         .add_list_param(list)
         .add_param("include_rts", with_rts.to_string());
 
-    tweet::Timeline::new(links::lists::STATUSES, Some(params), token)
+    tweet::Timeline::new(links::lists::STATUSES, Some(params), token.into())
 }
 
 ///Adds the given user to the given list.
@@ -176,15 +180,16 @@ pub fn statuses(list: ListID, with_rts: bool, token: &auth::Token) -> tweet::Tim
 pub async fn add_member<'id, T: Into<UserID>>(
     list: ListID,
     user: T,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<List>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .add_list_param(list)
         .add_user_param(user.into());
 
-    let req = post(links::lists::ADD, token, Some(&params));
+    let req = client.request_post(links::lists::ADD, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 ///Adds a set of users to the given list.
@@ -203,12 +208,13 @@ pub async fn add_member<'id, T: Into<UserID>>(
 pub async fn add_member_list<'id, T, I>(
     members: I,
     list: ListID,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<List>>
 where
     T: Into<UserID>,
     I: IntoIterator<Item = T>,
 {
+    let client: Client = token.into();
     let (id_param, name_param) = multiple_names_param(members);
     let params = ParamList::new()
         .add_list_param(list)
@@ -229,24 +235,25 @@ where
             },
         );
 
-    let req = post(links::lists::ADD_LIST, token, Some(&params));
+    let req = client.request_post(links::lists::ADD_LIST, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 ///Removes the given user from the given list.
 pub async fn remove_member<'id, T: Into<UserID>>(
     list: ListID,
     user: T,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<List>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .add_list_param(list)
         .add_user_param(user.into());
 
-    let req = post(links::lists::REMOVE_MEMBER, token, Some(&params));
+    let req = client.request_post(links::lists::REMOVE_MEMBER, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 ///Removes a set of users from the given list.
@@ -264,12 +271,13 @@ pub async fn remove_member<'id, T: Into<UserID>>(
 pub async fn remove_member_list<T, I>(
     members: I,
     list: ListID,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<List>>
 where
     T: Into<UserID>,
     I: IntoIterator<Item = T>,
 {
+    let client: Client = token.into();
     let (id_param, name_param) = multiple_names_param(members);
     let params = ParamList::new()
         .add_list_param(list)
@@ -290,9 +298,9 @@ where
             },
         );
 
-    let req = post(links::lists::REMOVE_LIST, token, Some(&params));
+    let req = client.request_post(links::lists::REMOVE_LIST, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 ///Creates a list, with the given name, visibility, and description.
@@ -304,48 +312,52 @@ pub async fn create(
     name: String,
     public: bool,
     desc: Option<String>,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<List>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .add_param("name", name)
         .add_param("mode", if public { "public" } else { "private" })
         .add_opt_param("description", desc);
 
-    let req = post(links::lists::CREATE, token, Some(&params));
+    let req = client.request_post(links::lists::CREATE, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 ///Deletes the given list.
 ///
 ///The authenticated user must have created the list.
-pub async fn delete(list: ListID, token: &auth::Token) -> Result<Response<List>> {
+pub async fn delete(list: ListID, token: impl Into<Client>) -> Result<Response<List>> {
+    let client: Client = token.into();
     let params = ParamList::new().add_list_param(list);
 
-    let req = post(links::lists::DELETE, token, Some(&params));
+    let req = client.request_post(links::lists::DELETE, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 ///Subscribes the authenticated user to the given list.
 ///
 ///Subscribing to a list is a way to make it available in the "Lists" section of a user's profile
 ///without having to create it themselves.
-pub async fn subscribe(list: ListID, token: &auth::Token) -> Result<Response<List>> {
+pub async fn subscribe(list: ListID, token: impl Into<Client>) -> Result<Response<List>> {
+    let client: Client = token.into();
     let params = ParamList::new().add_list_param(list);
 
-    let req = post(links::lists::SUBSCRIBE, token, Some(&params));
+    let req = client.request_post(links::lists::SUBSCRIBE, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 ///Unsubscribes the authenticated user from the given list.
-pub async fn unsubscribe(list: ListID, token: &auth::Token) -> Result<Response<List>> {
+pub async fn unsubscribe(list: ListID, token: impl Into<Client>) -> Result<Response<List>> {
+    let client: Client = token.into();
     let params = ParamList::new().add_list_param(list);
 
-    let req = post(links::lists::UNSUBSCRIBE, token, Some(&params));
+    let req = client.request_post(links::lists::UNSUBSCRIBE, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 ///Begins updating a list's metadata.
@@ -71,7 +71,7 @@ use chrono;
 use serde::Deserialize;
 
 use crate::common::*;
-use crate::{auth, links, user};
+use crate::{links, user};
 
 mod fun;
 pub use self::fun::*;
@@ -217,7 +217,8 @@ impl ListUpdate {
     }
 
     ///Sends the update request to Twitter.
-    pub async fn send(self, token: &auth::Token) -> Result<Response<List>, crate::error::Error> {
+    pub async fn send(self, token: impl Into<Client>) -> Result<Response<List>, crate::error::Error> {
+        let client: Client = token.into();
         let params = ParamList::new()
             .add_list_param(self.list)
             .add_opt_param("name", self.name)
@@ -227,8 +228,8 @@ impl ListUpdate {
             )
             .add_opt_param("description", self.desc);
 
-        let req = post(links::lists::UPDATE, token, Some(&params));
-        request_with_json_response(req).await
+        let req = client.request_post(links::lists::UPDATE, Some(&params));
+        client.response_json(req).await
     }
 }
 
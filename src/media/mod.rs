@@ -29,8 +29,9 @@ use base64;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer};
 
+use crate::client::idempotent;
 use crate::common::*;
-use crate::{auth, error, links};
+use crate::{error, links};
 
 use mime;
 
@@ -227,19 +228,20 @@ impl MediaCategory {
 pub async fn upload_media(
     data: &[u8],
     media_type: &mime::Mime,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> error::Result<MediaHandle> {
+    let client: Client = token.into();
     let media_category = MediaCategory::from(media_type);
     let params = ParamList::new()
         .add_param("command", "INIT")
         .add_param("total_bytes", data.len().to_string())
         .add_param("media_type", media_type.to_string())
         .add_param("media_category", media_category.to_string());
-    let req = post(links::media::UPLOAD, &token, Some(&params));
+    let req = client.request_post(links::media::UPLOAD, Some(&params));
 
-    let media = request_with_json_response::<RawMedia>(req).await?.response;
+    let media = client.response_json::<RawMedia>(req).await?.response;
 
-    finish_upload(media, data, token).await
+    finish_upload(media, data, &client).await
 }
 
 /// Upload media to the server, for use in a Direct Message.
@@ -263,8 +265,9 @@ pub async fn upload_media_for_dm(
     data: &[u8],
     media_type: &mime::Mime,
     shared: bool,
-    token: &auth::Token
+    token: impl Into<Client>
 ) -> error::Result<MediaHandle> {
+    let client: Client = token.into();
     let media_category = MediaCategory::from(media_type);
     let params = ParamList::new()
         .add_param("command", "INIT")
@@ -272,17 +275,17 @@ pub async fn upload_media_for_dm(
         .add_param("media_type", media_type.to_string())
         .add_param("media_category", media_category.dm_category())
         .add_param("shared", shared.to_string());
-    let req = post(links::media::UPLOAD, &token, Some(&params));
+    let req = client.request_post(links::media::UPLOAD, Some(&params));
 
-    let media = request_with_json_response::<RawMedia>(req).await?.response;
+    let media = client.response_json::<RawMedia>(req).await?.response;
 
-    finish_upload(media, data, token).await
+    finish_upload(media, data, &client).await
 }
 
 async fn finish_upload(
     media: RawMedia,
     data: &[u8],
-    token: &auth::Token
+    client: &Client,
 ) -> error::Result<MediaHandle> {
     // divide into 1MB chunks
     for (ix, chunk) in data.chunks(1024 * 1024).enumerate() {
@@ -291,28 +294,30 @@ async fn finish_upload(
             .add_param("media_id", media.id.clone())
             .add_param("media_data", base64::encode(chunk))
             .add_param("segment_index", ix.to_string());
-        let req = post(links::media::UPLOAD, token, Some(&params));
+        // Each segment replaces any previous upload with the same index, so it's safe to retry
+        let req = idempotent(client.request_post(links::media::UPLOAD, Some(&params)));
         // This request has no response (upon success)
-        raw_request(req).await?;
+        client.response_raw_bytes(req).await?;
     }
 
     let params = ParamList::new()
         .add_param("command", "FINALIZE")
         .add_param("media_id", media.id.clone());
-    let req = post(links::media::UPLOAD, token, Some(&params));
-    Ok(request_with_json_response::<RawMedia>(req)
+    let req = client.request_post(links::media::UPLOAD, Some(&params));
+    Ok(client.response_json::<RawMedia>(req)
         .await?
         .response
         .into())
 }
 
 /// Check the status of uploaded media
-pub async fn get_status(media_id: MediaId, token: &auth::Token) -> error::Result<MediaHandle> {
+pub async fn get_status(media_id: MediaId, token: impl Into<Client>) -> error::Result<MediaHandle> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .add_param("command", "STATUS")
         .add_param("media_id", media_id.0);
-    let req = get(links::media::UPLOAD, token, Some(&params));
-    Ok(request_with_json_response::<RawMedia>(req)
+    let req = client.request_get(links::media::UPLOAD, Some(&params));
+    Ok(client.response_json::<RawMedia>(req)
         .await?
         .response
         .into())
@@ -323,16 +328,17 @@ pub async fn get_status(media_id: MediaId, token: &auth::Token) -> error::Result
 pub async fn set_metadata(
     media_id: &MediaId,
     alt_text: &str,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> error::Result<()> {
+    let client: Client = token.into();
     let payload = serde_json::json!({
         "media_id": media_id.0,
         "alt_text": {
             "text": alt_text
         }
     });
-    let req = post_json(links::media::METADATA, &token, payload);
-    raw_request(req).await?;
+    let req = client.request_post_json(links::media::METADATA, payload);
+    client.response_raw_bytes(req).await?;
     Ok(())
 }
 
@@ -4,7 +4,7 @@
 
 use crate::common::*;
 use crate::error::{Error, Result};
-use crate::{auth, links};
+use crate::links;
 
 use super::PlaceQuery;
 use super::*;
@@ -23,10 +23,11 @@ use super::*;
 /// assert!(result.full_name == "Dallas, TX");
 /// # }
 /// ```
-pub async fn show(id: &str, token: &auth::Token) -> Result<Response<Place>> {
+pub async fn show(id: &str, token: impl Into<Client>) -> Result<Response<Place>> {
+    let client: Client = token.into();
     let url = format!("{}/{}.json", links::place::SHOW_STEM, id);
-    let req = get(&url, token, None);
-    request_with_json_response(req).await
+    let req = client.request_get(&url, None);
+    client.response_json(req).await
 }
 
 /// Begins building a reverse-geocode search with the given coordinate.
@@ -81,10 +82,11 @@ fn parse_url(base: &'static str, full: &str) -> Result<ParamList> {
 ///
 ///In addition to errors that might occur generally, this function will return a `BadUrl` error if
 ///the given URL is not a valid `reverse_geocode` query URL.
-pub async fn reverse_geocode_url(url: &str, token: &auth::Token) -> Result<Response<SearchResult>> {
+pub async fn reverse_geocode_url(url: &str, token: impl Into<Client>) -> Result<Response<SearchResult>> {
+    let client: Client = token.into();
     let params = parse_url(links::place::REVERSE_GEOCODE, url)?;
-    let req = get(links::place::REVERSE_GEOCODE, &token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_get(links::place::REVERSE_GEOCODE, Some(&params));
+    client.response_json(req).await
 }
 
 /// Begins building a location search via latitude/longitude.
@@ -144,8 +146,9 @@ pub fn search_ip(query: impl Into<CowStr>) -> SearchBuilder {
 ///
 ///In addition to errors that might occur generally, this function will return a `BadUrl` error if
 ///the given URL is not a valid `search` query URL.
-pub async fn search_url(url: &str, token: &auth::Token) -> Result<Response<SearchResult>> {
+pub async fn search_url(url: &str, token: impl Into<Client>) -> Result<Response<SearchResult>> {
+    let client: Client = token.into();
     let params = parse_url(links::place::SEARCH, url)?;
-    let req = get(links::place::REVERSE_GEOCODE, &token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_get(links::place::REVERSE_GEOCODE, Some(&params));
+    client.response_json(req).await
 }
@@ -34,7 +34,7 @@ use serde::{Deserialize, Deserializer, Serialize};
 use serde_json;
 
 use crate::common::*;
-use crate::{auth, error, links};
+use crate::{error, links};
 
 mod fun;
 
@@ -190,7 +190,8 @@ impl GeocodeBuilder {
     }
 
     ///Finalize the search parameters and return the results collection.
-    pub async fn call(&self, token: &auth::Token) -> Result<Response<SearchResult>, error::Error> {
+    pub async fn call(&self, token: impl Into<Client>) -> Result<Response<SearchResult>, error::Error> {
+        let client: Client = token.into();
         let params = ParamList::new()
             .add_param("lat", self.coordinate.0.to_string())
             .add_param("long", self.coordinate.1.to_string())
@@ -204,8 +205,8 @@ impl GeocodeBuilder {
                 }),
             );
 
-        let req = get(links::place::REVERSE_GEOCODE, token, Some(&params));
-        request_with_json_response(req).await
+        let req = client.request_get(links::place::REVERSE_GEOCODE, Some(&params));
+        client.response_json(req).await
     }
 }
 
@@ -310,7 +311,8 @@ impl SearchBuilder {
     }
 
     ///Finalize the search parameters and return the results collection.
-    pub async fn call(&self, token: &auth::Token) -> Result<Response<SearchResult>, error::Error> {
+    pub async fn call(&self, token: impl Into<Client>) -> Result<Response<SearchResult>, error::Error> {
+        let client: Client = token.into();
         let mut params = match &self.query {
             PlaceQuery::LatLon(lat, long) => ParamList::new()
                 .add_param("lat", lat.to_string())
@@ -329,8 +331,8 @@ impl SearchBuilder {
             }
         }
 
-        let req = get(links::place::SEARCH, token, Some(&params));
-        request_with_json_response(req).await
+        let req = client.request_get(links::place::SEARCH, Some(&params));
+        client.response_json(req).await
     }
 }
 
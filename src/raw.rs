@@ -72,7 +72,7 @@
 
 use hyper::{Body, Request};
 
-use crate::auth::Token;
+use crate::client::Client;
 use crate::cursor;
 use crate::stream::TwitterStream;
 
@@ -103,10 +103,10 @@ pub use crate::auth::raw::post_json as request_post_json;
 /// `None` for the `params` to make the `Timeline` manage the parameters itself.
 pub fn request_as_tweet_timeline(
     url: &'static str,
-    token: &Token,
+    token: impl Into<Client>,
     params: Option<ParamList>
 ) -> TweetTimeline {
-    TweetTimeline::new(url, params, token)
+    TweetTimeline::new(url, params, token.into())
 }
 
 /// Assemble a GET request and convert it to a `CursorIter`.
@@ -142,11 +142,11 @@ pub fn request_as_tweet_timeline(
 /// results on-demand.
 pub fn request_as_cursor_iter<T: cursor::Cursor + serde::de::DeserializeOwned>(
     url: &'static str,
-    token: &Token,
+    token: impl Into<Client>,
     params: Option<ParamList>,
     page_size: Option<i32>
 ) -> cursor::CursorIter<T> {
-    cursor::CursorIter::new(url, token, params, page_size)
+    cursor::CursorIter::new(url, token.into(), params, page_size)
 }
 
 pub use crate::common::get_response as response_future;
@@ -162,9 +162,14 @@ pub use crate::common::request_with_empty_response as response_empty;
 /// use other endpoints or options not available to `StreamBuilder`.
 ///
 /// Since the `TwitterStream` type doesn't need to provide additional parameters to the request, it
-/// can take a signed, completed request as its constructor.
-pub fn response_as_stream(req: Request<Body>) -> TwitterStream {
-    TwitterStream::new(req)
+/// can take a signed, completed request as its constructor. The stream is opened over the given
+/// `Client`'s `Transport`, so it goes through a custom transport like a `Recorder` or `Replayer`.
+///
+/// To close the stream when it stops receiving data, set a stall timeout on the `Client` with
+/// `Client::with_timeouts`, or attach one to the request with
+/// [`Timeouts::apply`](../client/struct.Timeouts.html#method.apply).
+pub fn response_as_stream(req: Request<Body>, client: impl Into<Client>) -> TwitterStream {
+    client.into().response_as_stream(req)
 }
 
 pub use crate::common::RoundTrip;
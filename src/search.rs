@@ -50,7 +50,7 @@ use serde::{Deserialize, Deserializer};
 
 use crate::common::*;
 use crate::tweet::Tweet;
-use crate::{auth, error, links};
+use crate::{error, links};
 
 ///Begin setting up a tweet search with the given query.
 pub fn search<S: Into<CowStr>>(query: S) -> SearchBuilder {
@@ -175,7 +175,8 @@ impl SearchBuilder {
     }
 
     ///Finalize the search terms and return the first page of responses.
-    pub async fn call(self, token: &auth::Token) -> Result<Response<SearchResult>, error::Error> {
+    pub async fn call(self, token: impl Into<Client>) -> Result<Response<SearchResult>, error::Error> {
+        let client: Client = token.into();
         let params = ParamList::new()
             .extended_tweets()
             .add_param("q", self.query)
@@ -197,8 +198,8 @@ impl SearchBuilder {
                 }),
             );
 
-        let req = get(links::statuses::SEARCH, token, Some(&params));
-        let mut resp = request_with_json_response::<SearchResult>(req).await?;
+        let req = client.request_get(links::statuses::SEARCH, Some(&params));
+        let mut resp = client.response_json::<SearchResult>(req).await?;
 
         resp.response.params = Some(params);
         Ok(resp)
@@ -257,7 +258,8 @@ pub struct SearchResult {
 
 impl SearchResult {
     ///Load the next page of search results for the same query.
-    pub async fn older(&self, token: &auth::Token) -> Result<Response<SearchResult>, error::Error> {
+    pub async fn older(&self, token: impl Into<Client>) -> Result<Response<SearchResult>, error::Error> {
+        let client: Client = token.into();
         let mut params =
             ParamList::from(self.params.as_ref().cloned().unwrap_or_default()).extended_tweets();
 
@@ -269,15 +271,16 @@ impl SearchResult {
             params.remove("max_id");
         }
 
-        let req = get(links::statuses::SEARCH, token, Some(&params));
-        let mut resp = request_with_json_response::<SearchResult>(req).await?;
+        let req = client.request_get(links::statuses::SEARCH, Some(&params));
+        let mut resp = client.response_json::<SearchResult>(req).await?;
 
         resp.response.params = Some(params);
         Ok(resp)
     }
 
     ///Load the previous page of search results for the same query.
-    pub async fn newer(&self, token: &auth::Token) -> Result<Response<SearchResult>, error::Error> {
+    pub async fn newer(&self, token: impl Into<Client>) -> Result<Response<SearchResult>, error::Error> {
+        let client: Client = token.into();
         let mut params =
             ParamList::from(self.params.as_ref().cloned().unwrap_or_default()).extended_tweets();
 
@@ -288,8 +291,8 @@ impl SearchResult {
             params.remove("since_id");
         }
 
-        let req = get(links::statuses::SEARCH, token, Some(&params));
-        let mut resp = request_with_json_response::<SearchResult>(req).await?;
+        let req = client.request_get(links::statuses::SEARCH, Some(&params));
+        let mut resp = client.response_json::<SearchResult>(req).await?;
 
         resp.response.params = Some(params);
         Ok(resp)
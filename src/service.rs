@@ -29,16 +29,17 @@ use crate::error::{
     Error::{InvalidResponse, MissingValue},
     Result,
 };
-use crate::{auth, entities, links};
+use crate::{entities, links};
 
 ///Returns a future that resolves to the current Twitter Terms of Service as plain text.
 ///
 ///While the official home of Twitter's TOS is <https://twitter.com/tos>, this allows you to obtain a
 ///plain-text copy of it to display in your application.
-pub async fn terms(token: &auth::Token) -> Result<Response<String>> {
-    let req = get(links::service::TERMS, token, None);
+pub async fn terms(token: impl Into<Client>) -> Result<Response<String>> {
+    let client: Client = token.into();
+    let req = client.request_get(links::service::TERMS, None);
 
-    let ret = request_with_json_response::<serde_json::Value>(req).await?;
+    let ret = client.response_json::<serde_json::Value>(req).await?;
 
     let tos = ret
         .response
@@ -53,10 +54,11 @@ pub async fn terms(token: &auth::Token) -> Result<Response<String>> {
 ///
 ///While the official home of Twitter's Privacy Policy is <https://twitter.com/privacy>, this allows
 ///you to obtain a plain-text copy of it to display in your application.
-pub async fn privacy(token: &auth::Token) -> Result<Response<String>> {
-    let req = get(links::service::PRIVACY, token, None);
+pub async fn privacy(token: impl Into<Client>) -> Result<Response<String>> {
+    let client: Client = token.into();
+    let req = client.request_get(links::service::PRIVACY, None);
 
-    let ret = request_with_json_response::<serde_json::Value>(req).await?;
+    let ret = client.response_json::<serde_json::Value>(req).await?;
 
     let privacy = ret
         .response
@@ -77,9 +79,10 @@ pub async fn privacy(token: &auth::Token) -> Result<Response<String>> {
 ///fields returned by this function mean.
 ///
 ///[`Configuration`]: struct.Configuration.html
-pub async fn config(token: &auth::Token) -> Result<Response<Configuration>> {
-    let req = get(links::service::CONFIG, token, None);
-    request_with_json_response(req).await
+pub async fn config(token: impl Into<Client>) -> Result<Response<Configuration>> {
+    let client: Client = token.into();
+    let req = client.request_get(links::service::CONFIG, None);
+    client.response_json(req).await
 }
 
 ///Return the current rate-limit status for all available methods from the authenticated user.
@@ -89,18 +92,20 @@ pub async fn config(token: &auth::Token) -> Result<Response<Configuration>> {
 ///documentation for [`RateLimitStatus`][] and its associated enums for more information.
 ///
 ///[`RateLimitStatus`]: struct.RateLimitStatus.html
-pub async fn rate_limit_status(token: &auth::Token) -> Result<Response<RateLimitStatus>> {
-    let req = get(links::service::RATE_LIMIT_STATUS, token, None);
-    request_with_json_response(req).await
+pub async fn rate_limit_status(token: impl Into<Client>) -> Result<Response<RateLimitStatus>> {
+    let client: Client = token.into();
+    let req = client.request_get(links::service::RATE_LIMIT_STATUS, None);
+    client.response_json(req).await
 }
 
 ///Like `rate_limit_status`, but returns the raw JSON without processing it. Only intended to
 ///return the full structure so that new methods can be added to `RateLimitStatus` and its
 ///associated enums.
 #[doc(hidden)]
-pub async fn rate_limit_status_raw(token: &auth::Token) -> Result<Response<serde_json::Value>> {
-    let req = get(links::service::RATE_LIMIT_STATUS, token, None);
-    request_with_json_response(req).await
+pub async fn rate_limit_status_raw(token: impl Into<Client>) -> Result<Response<serde_json::Value>> {
+    let client: Client = token.into();
+    let req = client.request_get(links::service::RATE_LIMIT_STATUS, None);
+    client.response_json(req).await
 }
 
 ///Represents a service configuration from Twitter.
@@ -46,17 +46,20 @@
 use std::future::Future;
 use std::pin::Pin;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use std::{self, io};
 
 use futures::Stream;
-use hyper::client::ResponseFuture;
 use hyper::{Body, Request};
 use serde::de::Error;
 use serde::{Serialize, Deserialize, Deserializer};
 use serde_json;
+use tokio::time::{Instant, Sleep};
 
-use crate::auth::Token;
+use crate::client::{Transport, TransportFuture};
+use crate::error::TimeoutKind;
 use crate::common::*;
 use crate::tweet::Tweet;
 use crate::{error, links};
@@ -192,6 +195,25 @@ impl<'de> Deserialize<'de> for StreamMessage {
     }
 }
 
+impl StreamMessage {
+    /// Returns the name of this message's variant, so that it can be traced without including its
+    /// contents.
+    #[cfg(feature = "tracing")]
+    fn kind(&self) -> &'static str {
+        match self {
+            StreamMessage::Ping => "ping",
+            StreamMessage::FriendList(_) => "friend_list",
+            StreamMessage::Tweet(_) => "tweet",
+            StreamMessage::Delete { .. } => "delete",
+            StreamMessage::ScrubGeo { .. } => "scrub_geo",
+            StreamMessage::StatusWithheld { .. } => "status_withheld",
+            StreamMessage::UserWithheld { .. } => "user_withheld",
+            StreamMessage::Disconnect(..) => "disconnect",
+            StreamMessage::Unknown(_) => "unknown",
+        }
+    }
+}
+
 impl FromStr for StreamMessage {
     type Err = error::Error;
     fn from_str(input: &str) -> Result<Self, error::Error> {
@@ -205,85 +227,180 @@ impl FromStr for StreamMessage {
 }
 
 /// A `Stream` that represents a connection to the Twitter Streaming API.
+///
+/// If the `Client` that opened the stream has a stall timeout set in its [`Timeouts`], the stream
+/// returns `Error::Timeout` and closes the connection if it goes that long without receiving any
+/// data, whether that's while waiting for Twitter to accept the connection or between messages.
+///
+/// [`Timeouts`]: ../client/struct.Timeouts.html
+///
+/// With the `tracing` feature enabled, each `TwitterStream` records its connection attempts,
+/// disconnections, and the kind of each message it receives, inside an `egg_mode::request` span.
+/// Since a `TwitterStream` only opens one connection, reconnecting means creating a new
+/// `TwitterStream`, which shows up as a new span.
 #[must_use = "Streams are lazy and do nothing unless polled"]
 pub struct TwitterStream {
+    transport: Arc<dyn Transport>,
     buf: Vec<u8>,
     request: Option<Request<Body>>,
-    response: Option<ResponseFuture>,
+    response: Option<TransportFuture>,
     body: Option<Body>,
+    stall_timeout: Option<Duration>,
+    stall: Option<Pin<Box<Sleep>>>,
+    #[cfg(feature = "tracing")]
+    span: trace::Span,
 }
 
 impl TwitterStream {
-    pub(crate) fn new(request: Request<Body>) -> TwitterStream {
+    pub(crate) fn new(
+        request: Request<Body>,
+        transport: Arc<dyn Transport>,
+        stall_timeout: Option<Duration>,
+    ) -> TwitterStream {
         TwitterStream {
+            #[cfg(feature = "tracing")]
+            span: trace::request_span(&request),
+            transport,
             buf: vec![],
             request: Some(request),
             response: None,
             body: None,
+            stall_timeout,
+            stall: None,
         }
     }
+
+    /// Restarts the stall timer, if there is one.
+    fn reset_stall(&mut self) {
+        if let (Some(timeout), Some(stall)) = (self.stall_timeout, self.stall.as_mut()) {
+            stall.as_mut().reset(Instant::now() + timeout);
+        }
+    }
+
+    /// Checks whether the stall timer has run out while waiting on the connection. If it has, the
+    /// connection is closed.
+    fn poll_stall(&mut self, cx: &mut Context) -> bool {
+        let stalled = match self.stall.as_mut() {
+            Some(stall) => stall.as_mut().poll(cx).is_ready(),
+            None => false,
+        };
+
+        if stalled {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("stream stalled, closing connection");
+
+            self.response = None;
+            self.body = None;
+            self.stall = None;
+        }
+        stalled
+    }
 }
 
 impl Stream for TwitterStream {
     type Item = Result<StreamMessage, error::Error>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        #[cfg(feature = "tracing")]
+        let span = self.span.clone();
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
         if let Some(req) = self.request.take() {
-            self.response = Some(get_response(req));
+            #[cfg(feature = "tracing")]
+            tracing::info!("connecting to stream");
+
+            self.response = Some(self.transport.send(req));
+            self.stall = self.stall_timeout.map(|timeout| Box::pin(tokio::time::sleep(timeout)));
         }
 
         if let Some(mut resp) = self.response.take() {
             match Pin::new(&mut resp).poll(cx) {
                 Poll::Pending => {
                     self.response = Some(resp);
+                    if self.poll_stall(cx) {
+                        let err = error::Error::Timeout(TimeoutKind::Stall);
+                        return Poll::Ready(Some(Err(err)));
+                    }
                     return Poll::Pending;
                 }
-                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                Poll::Ready(Err(e)) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(error = %e, "stream connection failed");
+
+                    return Poll::Ready(Some(Err(e)));
+                }
                 Poll::Ready(Ok(resp)) => {
                     let status = resp.status();
+                    #[cfg(feature = "tracing")]
+                    tracing::info!(status = status.as_u16(), "stream connected");
+
                     if !status.is_success() {
                         //TODO: should i try to pull the response regardless?
-                        return Poll::Ready(Some(Err(error::Error::BadStatus(status))));
+                        let response = error::ErrorResponse::new(resp.headers().clone(), &[]);
+                        let err = error::Error::BadStatus(status, Box::new(response));
+                        return Poll::Ready(Some(Err(err)));
                     }
 
                     self.body = Some(resp.into_body());
+                    self.reset_stall();
                 }
             }
         }
 
         if let Some(mut body) = self.body.take() {
             loop {
+                // a single chunk can hold several messages, so make sure to hand out everything
+                // that's already buffered before waiting on the connection again
+                if let Some(pos) = self.buf.windows(2).position(|w| w == b"\r\n") {
+                    self.body = Some(body);
+                    let pos = pos + 2;
+                    let resp = if let Ok(msg_str) = std::str::from_utf8(&self.buf[..pos]) {
+                        StreamMessage::from_str(msg_str)
+                    } else {
+                        Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "stream did not contain valid UTF-8",
+                        )
+                        .into())
+                    };
+
+                    self.buf.drain(..pos);
+
+                    #[cfg(feature = "tracing")]
+                    match &resp {
+                        Ok(msg) => tracing::trace!(kind = msg.kind(), "received stream message"),
+                        Err(err) => tracing::warn!(error = %err, "received malformed message"),
+                    }
+
+                    return Poll::Ready(Some(Ok(resp?)));
+                }
+
                 match Pin::new(&mut body).poll_next(cx) {
                     Poll::Pending => {
                         self.body = Some(body);
+                        if self.poll_stall(cx) {
+                            let err = error::Error::Timeout(TimeoutKind::Stall);
+                            return Poll::Ready(Some(Err(err)));
+                        }
                         return Poll::Pending;
                     }
                     Poll::Ready(None) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::info!("stream ended");
+
                         return Poll::Ready(None);
                     }
                     Poll::Ready(Some(Err(e))) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(error = %e, "stream connection failed");
+
                         self.body = Some(body);
                         return Poll::Ready(Some(Err(e.into())));
                     }
                     Poll::Ready(Some(Ok(chunk))) => {
                         self.buf.extend(&*chunk);
-
-                        if let Some(pos) = self.buf.windows(2).position(|w| w == b"\r\n") {
-                            self.body = Some(body);
-                            let pos = pos + 2;
-                            let resp = if let Ok(msg_str) = std::str::from_utf8(&self.buf[..pos]) {
-                                StreamMessage::from_str(msg_str)
-                            } else {
-                                Err(io::Error::new(
-                                    io::ErrorKind::InvalidData,
-                                    "stream did not contain valid UTF-8",
-                                )
-                                .into())
-                            };
-
-                            self.buf.drain(..pos);
-                            return Poll::Ready(Some(Ok(resp?)));
-                        }
+                        self.reset_stall();
                     }
                 }
             }
@@ -429,7 +546,8 @@ impl StreamBuilder {
     }
 
     /// Finalizes the stream parameters and returns the resulting `TwitterStream`.
-    pub fn start(self, token: &Token) -> TwitterStream {
+    pub fn start(self, token: impl Into<Client>) -> TwitterStream {
+        let client: Client = token.into();
         // Re connection failure, arguably this library should check that either 'track' or
         // 'follow' exist and return an error if not. However, in such a case the request is not
         // 'invalid' from POV of twitter api, rather it is invalid at the application level.
@@ -468,9 +586,8 @@ impl StreamBuilder {
             params.add_param_ref("locations", locs);
         }
 
-        let req = post(self.url, token, Some(&params));
-
-        TwitterStream::new(req)
+        let req = client.request_post(self.url, Some(&params));
+        client.response_as_stream(req)
     }
 }
 
@@ -488,9 +605,10 @@ pub fn filter() -> StreamBuilder {
 ///
 /// [`StreamBuilder`]: struct.StreamBuilder.html
 /// [`filter`]: fn.filter.html
-pub fn sample(token: &Token) -> TwitterStream {
-    let req = get(links::stream::SAMPLE, token, None);
-    TwitterStream::new(req)
+pub fn sample(token: impl Into<Client>) -> TwitterStream {
+    let client: Client = token.into();
+    let req = client.request_get(links::stream::SAMPLE, None);
+    client.response_as_stream(req)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
@@ -528,6 +646,7 @@ impl BoundingBox {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::client::scripted::{Reply, Scripted};
     use crate::common::tests::load_file;
 
     fn load_stream(path: &str) -> StreamMessage {
@@ -554,4 +673,24 @@ mod tests {
             panic!("Not a ping")
         }
     }
+
+    #[tokio::test]
+    async fn several_messages_in_one_chunk() {
+        use futures::TryStreamExt;
+
+        // the first chunk holds a ping, a whole message, another ping, and part of a message
+        let transport = Scripted::new().then(Reply::chunks(&[
+            "\r\n{\"friends\":[1,2]}\r\n\r\n{\"friends\"",
+            ":[3]}\r\n",
+        ]));
+        let request = Request::get("https://stream.twitter.com/").body(Body::empty()).unwrap();
+        let stream = TwitterStream::new(request, Arc::new(transport), None);
+        let messages: Vec<_> = stream.try_collect().await.unwrap();
+
+        assert_eq!(messages.len(), 4);
+        assert!(matches!(messages[0], StreamMessage::Ping));
+        assert!(matches!(messages[1], StreamMessage::FriendList(ref ids) if ids == &[1, 2]));
+        assert!(matches!(messages[2], StreamMessage::Ping));
+        assert!(matches!(messages[3], StreamMessage::FriendList(ref ids) if ids == &[3]));
+    }
 }
@@ -6,29 +6,30 @@ use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::ops::{Deref, DerefMut};
 
-use crate::common::*;
 use crate::error::{Error::InvalidResponse, Result};
 use crate::user::UserID;
-use crate::{auth, cursor, links};
+use crate::{cursor, links};
 
 use super::*;
 
 ///Lookup a single tweet by numeric ID.
-pub async fn show(id: u64, token: &auth::Token) -> Result<Response<Tweet>> {
+pub async fn show(id: u64, token: impl Into<Client>) -> Result<Response<Tweet>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .extended_tweets()
         .add_param("id", id.to_string())
         .add_param("include_my_retweet", "true")
         .add_param("include_ext_alt_text", "true");
-    let req = get(links::statuses::SHOW, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_get(links::statuses::SHOW, Some(&params));
+    client.response_json(req).await
 }
 
 ///Lookup the most recent 100 (or fewer) retweets of the given tweet.
 ///
 ///Use the `count` parameter to indicate how many retweets you would like to retrieve. If `count`
 ///is 0 or greater than 100, it will be defaulted to 100 before making the call.
-pub async fn retweets_of(id: u64, count: u32, token: &auth::Token) -> Result<Response<Vec<Tweet>>> {
+pub async fn retweets_of(id: u64, count: u32, token: impl Into<Client>) -> Result<Response<Vec<Tweet>>> {
+    let client: Client = token.into();
     let params = ParamList::new().extended_tweets().add_param(
         "count",
         if count == 0 || count > 100 {
@@ -40,8 +41,8 @@ pub async fn retweets_of(id: u64, count: u32, token: &auth::Token) -> Result<Res
     );
 
     let url = format!("{}/{}.json", links::statuses::RETWEETS_OF_STEM, id);
-    let req = get(&url, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_get(&url, Some(&params));
+    client.response_json(req).await
 }
 
 ///Lookup the user IDs that have retweeted the given tweet.
@@ -50,9 +51,9 @@ pub async fn retweets_of(id: u64, count: u32, token: &auth::Token) -> Result<Res
 ///set the page size. Calling `with_page_size` on the iterator returned by this function will not
 ///change the page size used by the network call. Setting `page_size` manually may result in an
 ///error from Twitter.
-pub fn retweeters_of(id: u64, token: &auth::Token) -> cursor::CursorIter<cursor::IDCursor> {
+pub fn retweeters_of(id: u64, token: impl Into<Client>) -> cursor::CursorIter<cursor::IDCursor> {
     let params = ParamList::new().add_param("id", id.to_string());
-    cursor::CursorIter::new(links::statuses::RETWEETERS_OF, token, Some(params), None)
+    cursor::CursorIter::new(links::statuses::RETWEETERS_OF, token.into(), Some(params), None)
 }
 
 ///Lookup tweet information for the given list of tweet IDs.
@@ -61,8 +62,9 @@ pub fn retweeters_of(id: u64, token: &auth::Token) -> cursor::CursorIter<cursor:
 ///`lookup` gives a Vec of just the tweets it could load, leaving out any that it couldn't find.
 pub async fn lookup<I: IntoIterator<Item = u64>>(
     ids: I,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<Vec<Tweet>>> {
+    let client: Client = token.into();
     let id_param = ids.into_iter().fold(String::new(), |mut acc, x| {
         if !acc.is_empty() {
             acc.push(',');
@@ -75,8 +77,8 @@ pub async fn lookup<I: IntoIterator<Item = u64>>(
         .add_param("id", id_param)
         .add_param("include_ext_alt_text", "true");
 
-    let req = post(links::statuses::LOOKUP, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(links::statuses::LOOKUP, Some(&params));
+    client.response_json(req).await
 }
 
 ///Lookup tweet information for the given list of tweet IDs, and return a map indicating which IDs
@@ -88,8 +90,9 @@ pub async fn lookup<I: IntoIterator<Item = u64>>(
 ///loaded store `Some` and the requested status.
 pub async fn lookup_map<I: IntoIterator<Item = u64>>(
     ids: I,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<HashMap<u64, Option<Tweet>>>> {
+    let client: Client = token.into();
     let id_param = ids.into_iter().fold(String::new(), |mut acc, x| {
         if !acc.is_empty() {
             acc.push(',');
@@ -103,8 +106,8 @@ pub async fn lookup_map<I: IntoIterator<Item = u64>>(
         .add_param("map", "true")
         .add_param("include_ext_alt_text", "true");
 
-    let req = post(links::statuses::LOOKUP, token, Some(&params));
-    let parsed = request_with_json_response::<serde_json::Value>(req).await?;
+    let req = client.request_post(links::statuses::LOOKUP, Some(&params));
+    let parsed = client.response_json::<serde_json::Value>(req).await?;
     let mut map = HashMap::new();
 
     for (key, val) in parsed
@@ -139,8 +142,8 @@ pub async fn lookup_map<I: IntoIterator<Item = u64>>(
 ///This method has a default page size of 20 tweets, with a maximum of 200.
 ///
 ///Twitter will only return the most recent 800 tweets by navigating this method.
-pub fn home_timeline(token: &auth::Token) -> Timeline {
-    Timeline::new(links::statuses::HOME_TIMELINE, None, token)
+pub fn home_timeline(token: impl Into<Client>) -> Timeline {
+    Timeline::new(links::statuses::HOME_TIMELINE, None, token.into())
 }
 
 ///Make a `Timeline` struct for navigating the collection of tweets that mention the authenticated
@@ -149,8 +152,8 @@ pub fn home_timeline(token: &auth::Token) -> Timeline {
 ///This method has a default page size of 20 tweets, with a maximum of 200.
 ///
 ///Twitter will only return the most recent 800 tweets by navigating this method.
-pub fn mentions_timeline(token: &auth::Token) -> Timeline {
-    Timeline::new(links::statuses::MENTIONS_TIMELINE, None, token)
+pub fn mentions_timeline(token: impl Into<Client>) -> Timeline {
+    Timeline::new(links::statuses::MENTIONS_TIMELINE, None, token.into())
 }
 
 ///Make a `Timeline` struct for navigating the collection of tweets posted by the given user,
@@ -169,7 +172,7 @@ pub fn user_timeline<T: Into<UserID>>(
     acct: T,
     with_replies: bool,
     with_rts: bool,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Timeline {
     let params = ParamList::new()
         .extended_tweets()
@@ -177,36 +180,37 @@ pub fn user_timeline<T: Into<UserID>>(
         .add_param("exclude_replies", (!with_replies).to_string())
         .add_param("include_rts", with_rts.to_string());
 
-    Timeline::new(links::statuses::USER_TIMELINE, Some(params), token)
+    Timeline::new(links::statuses::USER_TIMELINE, Some(params), token.into())
 }
 
 ///Make a `Timeline` struct for navigating the collection of tweets posted by the authenticated
 ///user that have been retweeted by others.
 ///
 ///This method has a default page size of 20 tweets, with a maximum of 100.
-pub fn retweets_of_me(token: &auth::Token) -> Timeline {
-    Timeline::new(links::statuses::RETWEETS_OF_ME, None, token)
+pub fn retweets_of_me(token: impl Into<Client>) -> Timeline {
+    Timeline::new(links::statuses::RETWEETS_OF_ME, None, token.into())
 }
 
 ///Make a `Timeline` struct for navigating the collection of tweets liked by the given user.
 ///
 ///This method has a default page size of 20 tweets, with a maximum of 200.
-pub fn liked_by<T: Into<UserID>>(acct: T, token: &auth::Token) -> Timeline {
+pub fn liked_by<T: Into<UserID>>(acct: T, token: impl Into<Client>) -> Timeline {
     let params = ParamList::new()
         .extended_tweets()
         .add_user_param(acct.into());
-    Timeline::new(links::statuses::LIKES_OF, Some(params), token)
+    Timeline::new(links::statuses::LIKES_OF, Some(params), token.into())
 }
 
 ///Retweet the given status as the authenticated user.
 ///
 ///On success, the future returned by this function yields the retweet, with the original status
 ///contained in `retweeted_status`.
-pub async fn retweet(id: u64, token: &auth::Token) -> Result<Response<Tweet>> {
+pub async fn retweet(id: u64, token: impl Into<Client>) -> Result<Response<Tweet>> {
+    let client: Client = token.into();
     let params = ParamList::new().extended_tweets();
     let url = format!("{}/{}.json", links::statuses::RETWEET_STEM, id);
-    let req = post(&url, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(&url, Some(&params));
+    client.response_json(req).await
 }
 
 ///Unretweet the given status as the authenticated user.
@@ -215,43 +219,47 @@ pub async fn retweet(id: u64, token: &auth::Token) -> Result<Response<Tweet>> {
 ///it.
 ///
 ///On success, the future returned by this function yields the original tweet.
-pub async fn unretweet(id: u64, token: &auth::Token) -> Result<Response<Tweet>> {
+pub async fn unretweet(id: u64, token: impl Into<Client>) -> Result<Response<Tweet>> {
+    let client: Client = token.into();
     let params = ParamList::new().extended_tweets();
     let url = format!("{}/{}.json", links::statuses::UNRETWEET_STEM, id);
-    let req = post(&url, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(&url, Some(&params));
+    client.response_json(req).await
 }
 
 ///Like the given status as the authenticated user.
 ///
 ///On success, the future returned by this function yields the liked tweet.
-pub async fn like(id: u64, token: &auth::Token) -> Result<Response<Tweet>> {
+pub async fn like(id: u64, token: impl Into<Client>) -> Result<Response<Tweet>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .extended_tweets()
         .add_param("id", id.to_string());
-    let req = post(links::statuses::LIKE, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(links::statuses::LIKE, Some(&params));
+    client.response_json(req).await
 }
 
 ///Clears a like of the given status as the authenticated user.
 ///
 ///On success, the future returned by this function yields the given tweet.
-pub async fn unlike(id: u64, token: &auth::Token) -> Result<Response<Tweet>> {
+pub async fn unlike(id: u64, token: impl Into<Client>) -> Result<Response<Tweet>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .extended_tweets()
         .add_param("id", id.to_string());
-    let req = post(links::statuses::UNLIKE, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(links::statuses::UNLIKE, Some(&params));
+    client.response_json(req).await
 }
 
 ///Delete the given tweet. The authenticated user must be the user who posted the given tweet.
 ///
 ///On success, the future returned by this function yields the given tweet.
-pub async fn delete(id: u64, token: &auth::Token) -> Result<Response<Tweet>> {
+pub async fn delete(id: u64, token: impl Into<Client>) -> Result<Response<Tweet>> {
+    let client: Client = token.into();
     let params = ParamList::new().extended_tweets();
     let url = format!("{}/{}.json", links::statuses::DELETE_STEM, id);
-    let req = post(&url, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(&url, Some(&params));
+    client.response_json(req).await
 }
 
 /// Wrapper for [`Tweet`].
@@ -277,26 +285,33 @@ impl TryFrom<RawTweetV2> for TweetWrapper {
     }
 }
 
+impl v2::FromRawTweetV2 for TweetWrapper {
+    fn from_raw_tweet_v2(raw: RawTweetV2, includes: &v2::Includes) -> Result<Self> {
+        Ok(Self(includes.hydrate(raw)?))
+    }
+}
+
 ///All the children of a particular tweet (replies), recursively.
 pub async fn all_children(
     root_tweet_id: u64,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> cursor::CursorIter<cursor::SearchCursor<TweetWrapper>> {
     let params = ParamList::new()
         .add_param("query", format!("conversation_id:{}", root_tweet_id))
         .add_param("tweet.fields", RawTweetV2::fields_needed_for_v1_raw_tweet());
+    let params = v2::v1_expansion_params(params);
 
-    cursor::CursorIter::new(links::v2::search::RECENT, token, Some(params), Some(100))
+    cursor::CursorIter::new(links::v2::search::RECENT, token.into(), Some(params), Some(100))
 }
 
 ///All the children of a particular tweet (replies), recursively.
 pub async fn all_children_raw(
     root_tweet_id: u64,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> cursor::CursorIter<cursor::SearchCursor<RawTweetV2>> {
     let params = ParamList::new()
         .add_param("query", format!("conversation_id:{}", root_tweet_id))
         .add_param("tweet.fields", RawTweetV2::all_fields());
 
-    cursor::CursorIter::new(links::v2::search::RECENT, token, Some(params), Some(100))
+    cursor::CursorIter::new(links::v2::search::RECENT, token.into(), Some(params), Some(100))
 }
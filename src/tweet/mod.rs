@@ -44,6 +44,11 @@
 //! - `retweeters_of`
 //! - `retweets_of`
 //!
+//! The [`v2`][] module has versions of `show` and `lookup` that use the V2 API, letting you pick
+//! which fields and related objects are loaded.
+//!
+//! [`v2`]: v2/index.html
+//!
 //! ### `Timeline` cursors
 //!
 //! These functions return `Timeline`s and can be cursored around in the same way. See the
@@ -64,13 +69,15 @@ use hyper::{Body, Request};
 use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::auth::AccessLevel;
 use crate::common::*;
 use crate::error::{Error::InvalidResponse, Result};
 use crate::stream::FilterLevel;
-use crate::{auth, entities, error, links, media, place, user};
+use crate::{entities, error, links, media, place, user};
 
 mod fun;
 mod raw;
+pub mod v2;
 
 pub use self::fun::*;
 pub use self::raw::RawTweetV2;
@@ -309,108 +316,158 @@ impl TryFrom<raw::RawTweet> for Tweet {
 impl TryFrom<RawTweetV2> for Tweet {
     type Error = error::Error;
 
+    /// Converts a tweet from the V2 API into a `Tweet`, without the objects from `includes`.
+    ///
+    /// Use `v2::Includes::hydrate` to fill in the author, media, place, and referenced tweets.
     fn try_from(raw: RawTweetV2) -> Result<Tweet> {
-        raw::RawTweet {
-            coordinates: raw.geo.and_then(|g| g.coordinates),
-            created_at: raw
-                .created_at
-                .ok_or(error::Error::MissingValue("created_at"))?,
-            current_user_retweet: None,
-            display_text_range: None,
-            entities: {
-                if let None = raw.entities {
-                    TweetEntities {
-                        hashtags: vec![],
-                        symbols: vec![],
-                        urls: vec![],
-                        user_mentions: vec![],
-                        media: None,
-                    }
-                } else {
-                    let raw::v2_supporting_structs::Entities {
-                        annotations: _annotations,
-                        cashtags,
-                        hashtags,
-                        mentions: _mentions,
-                        urls,
-                    } = raw.entities.unwrap();
-
-                    TweetEntities {
-                        hashtags: hashtags
-                            .into_iter()
-                            .map(|h| entities::HashtagEntity {
-                                range: (h.start as usize, h.end as usize),
-                                text: h.tag,
-                            })
-                            .collect(),
-                        symbols: cashtags
-                            .into_iter()
-                            .map(|c| entities::HashtagEntity {
-                                range: (c.start as usize, c.end as usize),
-                                text: c.tag,
-                            })
-                            .collect(),
-                        urls: urls
-                            .into_iter()
-                            .map(|u| entities::UrlEntity {
-                                display_url: u.display_url,
-                                expanded_url: Some(u.expanded_url.into_string()),
-                                range: (u.start as usize, u.end as usize),
-                                url: u.url.into_string(),
-                            })
-                            .collect(),
-                        user_mentions: vec![], // TODO! // mentions.into_iter().map(|m| entities::MentionEntity { id: m.id, }),
-                        media: None,           // TODO!
-                    }
-                }
-            },
-            extended_entities: None, // todo!(),
-            extended_tweet: None,    // todo!(),
-            favorite_count: raw
-                .public_metrics
-                .as_ref()
-                .ok_or(error::Error::MissingValue("public_metrics"))?
-                .like_count
-                .try_into()
-                .unwrap(),
-            favorited: None,
-            filter_level: None, // todo!(),
-            id: raw.id,
-            in_reply_to_user_id: raw.in_reply_to_user_id,
-            in_reply_to_screen_name: None,
-            in_reply_to_status_id: raw.referenced_tweets.and_then(|v| {
-                for r in v {
-                    if let raw::v2_supporting_structs::ReferencedTweet::RepliedTo { id } = r {
-                        return Some(id);
-                    }
-                }
+        from_v2(raw, &v2::Includes::default(), true)
+    }
+}
 
-                None
-            }),
-            lang: raw.lang,
-            place: None,
-            possibly_sensitive: raw.possibly_sensitive,
-            quoted_status_id: None, // todo
-            quoted_status: None,    // todo
-            retweet_count: raw
-                .public_metrics
-                .ok_or(error::Error::MissingValue("public_metrics"))?
-                .retweet_count
-                .try_into()
-                .unwrap(),
-            retweeted: None,
-            retweeted_status: None,
-            source: None,
-            text: Some(raw.text.clone()),
-            full_text: Some(raw.text),
-            truncated: false,
-            user: None,
-            withheld_copyright: raw.withheld.as_ref().map(|w| w.copyright).unwrap_or(false),
-            withheld_in_countries: raw.withheld.map(|w| w.country_codes),
-            withheld_scope: None,
+/// Converts a tweet from the V2 API into a `Tweet`, filling in what it can from `includes`.
+///
+/// Objects that are missing from `includes`, or that don't have the fields needed to convert
+/// them, are left out. Referenced tweets are only filled in if `follow_refs` is set, so that they
+/// don't pull in referenced tweets of their own.
+fn from_v2(raw: RawTweetV2, includes: &v2::Includes, follow_refs: bool) -> Result<Tweet> {
+    use self::raw::v2_supporting_structs::{Attachments, ReferencedTweet};
+
+    let mut in_reply_to_status_id = None;
+    let mut quoted_status_id = None;
+    let mut retweeted_status_id = None;
+    for reference in raw.referenced_tweets.iter().flatten() {
+        match *reference {
+            ReferencedTweet::RepliedTo { id } => in_reply_to_status_id = Some(id),
+            ReferencedTweet::Quoted { id } => quoted_status_id = Some(id),
+            ReferencedTweet::Retweeted { id } => retweeted_status_id = Some(id),
+        }
+    }
+    let referenced = |id: Option<u64>| {
+        if !follow_refs {
+            return None;
         }
-        .try_into()
+        let raw = includes.tweet(id?)?.clone();
+        from_v2(raw, includes, false).ok().map(Box::new)
+    };
+
+    let raw::v2_supporting_structs::Entities {
+        annotations: _annotations,
+        cashtags,
+        hashtags,
+        mentions,
+        urls,
+    } = raw.entities.unwrap_or_default();
+    // Links to attached media are listed with the media in V1, not with the other links.
+    let (media_urls, urls): (Vec<_>, Vec<_>) =
+        urls.into_iter().partition(|u| u.media_key.is_some());
+    let media = match raw.attachments {
+        Some(Attachments::MediaKeys(ref keys)) => keys
+            .iter()
+            .filter_map(|key| {
+                let url = media_urls.iter().find(|u| u.media_key.as_ref() == Some(key));
+                includes.media(key)?.to_media_entity(url)
+            })
+            .collect(),
+        _ => vec![],
+    };
+
+    let user = raw
+        .author_id
+        .and_then(|id| includes.user(id))
+        .and_then(|u| user::TwitterUser::try_from(u.clone()).ok());
+    let in_reply_to_screen_name = raw
+        .in_reply_to_user_id
+        .and_then(|id| includes.user(id))
+        .map(|u| u.username.clone());
+    let place = raw
+        .geo
+        .as_ref()
+        .and_then(|g| g.place_id.as_ref())
+        .and_then(|id| includes.place(id))
+        .and_then(|p| place::Place::try_from(p.clone()).ok());
+    let public_metrics = raw
+        .public_metrics
+        .ok_or(error::Error::MissingValue("public_metrics"))?;
+
+    raw::RawTweet {
+        coordinates: raw.geo.and_then(|g| g.coordinates),
+        created_at: raw
+            .created_at
+            .ok_or(error::Error::MissingValue("created_at"))?,
+        current_user_retweet: None,
+        display_text_range: None,
+        entities: TweetEntities {
+            hashtags: hashtags
+                .into_iter()
+                .map(|h| entities::HashtagEntity {
+                    range: (h.start as usize, h.end as usize),
+                    text: h.tag,
+                })
+                .collect(),
+            symbols: cashtags
+                .into_iter()
+                .map(|c| entities::HashtagEntity {
+                    range: (c.start as usize, c.end as usize),
+                    text: c.tag,
+                })
+                .collect(),
+            urls: urls
+                .into_iter()
+                .map(|u| entities::UrlEntity {
+                    display_url: u.display_url,
+                    expanded_url: Some(u.expanded_url.into_string()),
+                    range: (u.start as usize, u.end as usize),
+                    url: u.url.into_string(),
+                })
+                .collect(),
+            user_mentions: mentions
+                .into_iter()
+                .filter_map(|m| {
+                    let user = includes
+                        .users
+                        .iter()
+                        .find(|u| u.username.eq_ignore_ascii_case(&m.username))?;
+                    Some(entities::MentionEntity {
+                        id: user.id,
+                        range: (m.start, m.end),
+                        name: user.name.clone(),
+                        screen_name: m.username,
+                    })
+                })
+                .collect(),
+            media: media.first().cloned().map(|m| vec![m]),
+        },
+        extended_entities: if media.is_empty() {
+            None
+        } else {
+            Some(ExtendedTweetEntities { media })
+        },
+        extended_tweet: None,
+        favorite_count: public_metrics.like_count.try_into().unwrap(),
+        favorited: None,
+        filter_level: None,
+        id: raw.id,
+        in_reply_to_user_id: raw.in_reply_to_user_id,
+        in_reply_to_screen_name,
+        in_reply_to_status_id,
+        lang: raw.lang,
+        place,
+        possibly_sensitive: raw.possibly_sensitive,
+        quoted_status_id,
+        quoted_status: referenced(quoted_status_id),
+        retweet_count: public_metrics.retweet_count.try_into().unwrap(),
+        retweeted: None,
+        retweeted_status: referenced(retweeted_status_id),
+        source: None,
+        text: Some(raw.text.clone()),
+        full_text: Some(raw.text),
+        truncated: false,
+        user: user.map(Box::new),
+        withheld_copyright: raw.withheld.as_ref().map(|w| w.copyright).unwrap_or(false),
+        withheld_in_countries: raw.withheld.map(|w| w.country_codes),
+        withheld_scope: None,
     }
+    .try_into()
 }
 
 ///Represents the app from which a specific tweet was posted.
@@ -594,8 +651,8 @@ pub struct ExtendedTweetEntities {
 pub struct Timeline {
     ///The URL to request tweets from.
     link: &'static str,
-    ///The token to authorize requests with.
-    token: auth::Token,
+    ///The client to authorize and send requests with.
+    client: Client,
     ///Optional set of params to include prior to adding timeline navigation parameters.
     params_base: Option<ParamList>,
     ///The maximum number of tweets to return in a single call. Twitter doesn't guarantee returning
@@ -626,7 +683,8 @@ impl Timeline {
     ///ID to bound with.
     pub fn older(self, since_id: Option<u64>) -> TimelineFuture {
         let req = self.request(since_id, self.min_id.map(|id| id - 1));
-        let loader = Box::pin(request_with_json_response(req));
+        let client = self.client.clone();
+        let loader = Box::pin(async move { client.response_json(req).await });
 
         TimelineFuture {
             timeline: Some(self),
@@ -638,7 +696,8 @@ impl Timeline {
     ///ID to bound with.
     pub fn newer(self, max_id: Option<u64>) -> TimelineFuture {
         let req = self.request(self.max_id, max_id);
-        let loader = Box::pin(request_with_json_response(req));
+        let client = self.client.clone();
+        let loader = Box::pin(async move { client.response_json(req).await });
 
         TimelineFuture {
             timeline: Some(self),
@@ -658,7 +717,7 @@ impl Timeline {
         since_id: Option<u64>,
         max_id: Option<u64>,
     ) -> Result<Response<Vec<Tweet>>> {
-        request_with_json_response(self.request(since_id, max_id)).await
+        self.client.response_json(self.request(since_id, max_id)).await
     }
 
     ///Helper function to construct a `Request` from the current state.
@@ -670,7 +729,7 @@ impl Timeline {
             .add_opt_param("since_id", since_id.map(|v| v.to_string()))
             .add_opt_param("max_id", max_id.map(|v| v.to_string()));
 
-        get(self.link, &self.token, Some(&params))
+        self.client.request_get(self.link, Some(&params))
     }
 
     ///Helper builder function to set the page size.
@@ -687,15 +746,15 @@ impl Timeline {
         self.min_id = resp.last().map(|status| status.id);
     }
 
-    ///Create an instance of `Timeline` with the given link and tokens.
+    ///Create an instance of `Timeline` with the given link and client.
     pub(crate) fn new(
         link: &'static str,
         params_base: Option<ParamList>,
-        token: &auth::Token,
+        client: Client,
     ) -> Self {
         Timeline {
             link: link,
-            token: token.clone(),
+            client,
             params_base: params_base,
             count: 20,
             max_id: None,
@@ -944,7 +1003,9 @@ impl DraftTweet {
     }
 
     ///Send the assembled tweet as the authenticated user.
-    pub async fn send(&self, token: &auth::Token) -> Result<Response<Tweet>> {
+    pub async fn send(&self, token: impl Into<Client>) -> Result<Response<Tweet>> {
+        let client: Client = token.into();
+        client.require_access(AccessLevel::ReadWrite)?;
         let mut params = ParamList::new()
             .add_param("status", self.text.clone())
             .add_opt_param("in_reply_to_status_id", self.in_reply_to.map_string())
@@ -987,8 +1048,8 @@ impl DraftTweet {
             params.add_param_ref("media_ids", media);
         }
 
-        let req = post(links::statuses::UPDATE, token, Some(&params));
-        request_with_json_response(req).await
+        let req = client.request_post(links::statuses::UPDATE, Some(&params));
+        client.response_json(req).await
     }
 }
 
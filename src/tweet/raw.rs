@@ -94,6 +94,8 @@ impl RawTweetV2 {
     /// [`RawTweetV2`] into a [`RawTweet`] and then a [`Tweet`](super::Tweet).
     pub const fn fields_needed_for_v1_raw_tweet() -> &'static str {
         "\
+        attachments,\
+        author_id,\
         created_at,\
         entities,\
         geo,\
@@ -163,7 +165,7 @@ pub(crate) mod v2_supporting_structs {
         pub(crate) name: String,
     }
 
-    #[derive(Debug, Clone, Deserialize)]
+    #[derive(Debug, Clone, Default, Deserialize)]
     pub struct Entities {
         #[serde(default)]
         pub(crate) annotations: Vec<Annotation>,
@@ -218,6 +220,7 @@ pub(crate) mod v2_supporting_structs {
         pub(crate) title: Option<String>,
         pub(crate) description: Option<String>,
         pub(crate) unwound_url: Option<Url>,
+        pub(crate) media_key: Option<String>,
     }
 
     #[derive(Debug, Clone, Deserialize)]
@@ -262,6 +265,10 @@ pub(crate) mod v2_supporting_structs {
             #[serde(deserialize_with = "deserialize_number_from_string")]
             id: u64,
         },
+        Retweeted {
+            #[serde(deserialize_with = "deserialize_number_from_string")]
+            id: u64,
+        },
     }
 
     #[derive(Debug, Clone, Deserialize)]
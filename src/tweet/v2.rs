@@ -0,0 +1,1053 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Loading tweets through the Twitter V2 API.
+//!
+//! The V2 API only returns the fields that are asked for, and returns related objects like the
+//! author or attached media separately, in an "includes" section, when they're asked for with an
+//! "expansion". The [`Lookup`] builder returned by [`lookup`] and [`show`] lets you pick these
+//! with the [`TweetField`], [`UserField`], [`MediaField`], [`PlaceField`], [`PollField`], and
+//! [`Expansion`] enums, and checks that they make sense together before sending the request.
+//!
+//! [`Lookup`]: struct.Lookup.html
+//! [`lookup`]: fn.lookup.html
+//! [`show`]: fn.show.html
+//! [`TweetField`]: enum.TweetField.html
+//! [`UserField`]: enum.UserField.html
+//! [`MediaField`]: enum.MediaField.html
+//! [`PlaceField`]: enum.PlaceField.html
+//! [`PollField`]: enum.PollField.html
+//! [`Expansion`]: enum.Expansion.html
+//!
+//! To use the V2 API from code written against the (V1 based) [`Tweet`] type, the objects in
+//! [`Includes`] can be attached to the tweets they belong to with [`Includes::hydrate`]. This fills
+//! in the author, media, place, and referenced tweets, where V1 would have returned them. The
+//! [`Lookup::with_v1_expansions`] method asks for everything needed to do this.
+//!
+//! [`Tweet`]: ../struct.Tweet.html
+//! [`Includes`]: struct.Includes.html
+//! [`Includes::hydrate`]: struct.Includes.html#method.hydrate
+//! [`Lookup::with_v1_expansions`]: struct.Lookup.html#method.with_v1_expansions
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use egg_mode::Token;
+//! # #[tokio::main]
+//! # async fn main() {
+//! # let token: Token = unimplemented!();
+//! use egg_mode::tweet::v2::{self, Expansion, UserField};
+//!
+//! let resp = v2::lookup(vec![1261253754969640960, 1278747501642657792])
+//!     .expansions(vec![Expansion::AuthorId])
+//!     .user_fields(vec![UserField::Verified])
+//!     .send(&token)
+//!     .await
+//!     .unwrap();
+//!
+//! for tweet in &resp.data {
+//!     println!("{}: {}", tweet.id, tweet.text);
+//! }
+//! for user in &resp.includes.users {
+//!     println!("@{}", user.username);
+//! }
+//!
+//! let tweet = v2::show(1261253754969640960)
+//!     .with_v1_expansions()
+//!     .send(&token)
+//!     .await
+//!     .unwrap()
+//!     .response
+//!     .into_tweet()
+//!     .unwrap();
+//! println!("{} from @{}", tweet.text, tweet.user.unwrap().screen_name);
+//! # }
+//! ```
+
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::auth::Token;
+use crate::common::*;
+use crate::entities::{MediaEntity, MediaSize, MediaSizes, MediaType, ResizeMode};
+use crate::error::{Error, Problem, Result};
+use crate::links;
+use crate::place::{Place, PlaceType};
+use crate::user::RawUserV2;
+
+use super::raw::v2_supporting_structs::UrlEntity;
+use super::{RawTweetV2, Tweet};
+
+/// The most tweets that can be loaded with one call to `lookup`.
+pub const MAX_LOOKUP_IDS: usize = 100;
+
+macro_rules! field_enum {
+    ($(#[$attr:meta])* $name:ident { $($(#[$vattr:meta])* $variant:ident = $value:expr,)* }) => {
+        $(#[$attr])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum $name {
+            $($(#[$vattr])* $variant,)*
+        }
+
+        impl $name {
+            /// Every value of this type.
+            pub const ALL: &'static [$name] = &[$($name::$variant,)*];
+
+            /// Returns the name Twitter uses for this value.
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $value,)*
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+field_enum! {
+    /// Fields that can be requested for tweets, with `Lookup::tweet_fields`.
+    ///
+    /// `id` and `text` are always returned.
+    TweetField {
+        /// Which media or polls are attached to the tweet.
+        Attachments = "attachments",
+        /// The ID of the user who posted the tweet.
+        AuthorId = "author_id",
+        /// Twitter's guesses about what the tweet is about.
+        ContextAnnotations = "context_annotations",
+        /// The ID of the tweet that started the conversation this tweet is part of.
+        ConversationId = "conversation_id",
+        /// When the tweet was posted.
+        CreatedAt = "created_at",
+        /// Hashtags, links, mentions, and so on, parsed from the text.
+        Entities = "entities",
+        /// The location attached to the tweet.
+        Geo = "geo",
+        /// The ID of the user this tweet replies to.
+        InReplyToUserId = "in_reply_to_user_id",
+        /// The language Twitter detected the tweet as.
+        Lang = "lang",
+        /// Private engagement counts. Only available with a user token, for the user's own tweets.
+        NonPublicMetrics = "non_public_metrics",
+        /// Engagement counts without promoted views. Only available with a user token, for the
+        /// user's own tweets.
+        OrganicMetrics = "organic_metrics",
+        /// Whether the tweet's links might lead to sensitive content.
+        PossiblySensitive = "possibly_sensitive",
+        /// Engagement counts from promoted views. Only available with a user token, for the user's
+        /// own tweets.
+        PromotedMetrics = "promoted_metrics",
+        /// Public engagement counts, like retweets and likes.
+        PublicMetrics = "public_metrics",
+        /// The tweets this one quotes, replies to, or retweets.
+        ReferencedTweets = "referenced_tweets",
+        /// Who can reply to the tweet.
+        ReplySettings = "reply_settings",
+        /// The app the tweet was posted from.
+        Source = "source",
+        /// Where the tweet is withheld, if it is.
+        Withheld = "withheld",
+    }
+}
+
+field_enum! {
+    /// Fields that can be requested for users in `includes`, with `Lookup::user_fields`.
+    ///
+    /// `id`, `name`, and `username` are always returned. Users are only included if they're asked
+    /// for with an `Expansion`. These are also used for the users loaded by `user::v2`.
+    UserField {
+        /// When the account was created.
+        CreatedAt = "created_at",
+        /// The text of the user's profile.
+        Description = "description",
+        /// Links and mentions parsed from the user's profile.
+        Entities = "entities",
+        /// The location given in the user's profile.
+        Location = "location",
+        /// The ID of the tweet pinned to the user's profile.
+        PinnedTweetId = "pinned_tweet_id",
+        /// The URL of the user's avatar.
+        ProfileImageUrl = "profile_image_url",
+        /// Whether the user's tweets are protected.
+        Protected = "protected",
+        /// Follower, following, tweet, and list counts.
+        PublicMetrics = "public_metrics",
+        /// The website given in the user's profile.
+        Url = "url",
+        /// Whether the user is verified.
+        Verified = "verified",
+        /// Where the user is withheld, if they are.
+        Withheld = "withheld",
+    }
+}
+
+field_enum! {
+    /// Fields that can be requested for media in `includes`, with `Lookup::media_fields`.
+    ///
+    /// `media_key` and `type` are always returned. Media is only included if it's asked for with
+    /// `Expansion::AttachmentsMediaKeys`.
+    MediaField {
+        /// The length of a video, in milliseconds.
+        DurationMs = "duration_ms",
+        /// The height of the media, in pixels.
+        Height = "height",
+        /// A URL to a still image for a video.
+        PreviewImageUrl = "preview_image_url",
+        /// A URL to the image, for photos.
+        Url = "url",
+        /// The width of the media, in pixels.
+        Width = "width",
+        /// Public engagement counts, like views.
+        PublicMetrics = "public_metrics",
+        /// Private engagement counts. Only available with a user token, for the user's own media.
+        NonPublicMetrics = "non_public_metrics",
+        /// Engagement counts without promoted views. Only available with a user token, for the
+        /// user's own media.
+        OrganicMetrics = "organic_metrics",
+        /// Engagement counts from promoted views. Only available with a user token, for the user's
+        /// own media.
+        PromotedMetrics = "promoted_metrics",
+        /// The description of the media given by its uploader.
+        AltText = "alt_text",
+    }
+}
+
+field_enum! {
+    /// Fields that can be requested for places in `includes`, with `Lookup::place_fields`.
+    ///
+    /// `id` and `full_name` are always returned. Places are only included if they're asked for
+    /// with `Expansion::GeoPlaceId`.
+    PlaceField {
+        /// The IDs of places that contain this one.
+        ContainedWithin = "contained_within",
+        /// The name of the country the place is in.
+        Country = "country",
+        /// The ISO code of the country the place is in.
+        CountryCode = "country_code",
+        /// The bounding box of the place.
+        Geo = "geo",
+        /// The short name of the place.
+        Name = "name",
+        /// What kind of place this is, like `city`.
+        PlaceType = "place_type",
+    }
+}
+
+field_enum! {
+    /// Fields that can be requested for polls in `includes`, with `Lookup::poll_fields`.
+    ///
+    /// `id` and `options` are always returned. Polls are only included if they're asked for with
+    /// `Expansion::AttachmentsPollIds`.
+    PollField {
+        /// How long the poll is open for, in minutes.
+        DurationMinutes = "duration_minutes",
+        /// When the poll closes.
+        EndDatetime = "end_datetime",
+        /// Whether the poll is still open.
+        VotingStatus = "voting_status",
+    }
+}
+
+field_enum! {
+    /// Related objects that can be loaded into `includes` alongside tweets, with
+    /// `Lookup::expansions`.
+    Expansion {
+        /// The polls attached to the tweets.
+        AttachmentsPollIds = "attachments.poll_ids",
+        /// The media attached to the tweets.
+        AttachmentsMediaKeys = "attachments.media_keys",
+        /// The users who posted the tweets.
+        AuthorId = "author_id",
+        /// The users mentioned in the tweets.
+        EntitiesMentionsUsername = "entities.mentions.username",
+        /// The places attached to the tweets.
+        GeoPlaceId = "geo.place_id",
+        /// The users the tweets reply to.
+        InReplyToUserId = "in_reply_to_user_id",
+        /// The tweets that the tweets quote, reply to, or retweet.
+        ReferencedTweetsId = "referenced_tweets.id",
+        /// The users who posted the tweets that the tweets quote, reply to, or retweet.
+        ReferencedTweetsIdAuthorId = "referenced_tweets.id.author_id",
+    }
+}
+
+/// The expansions `Includes::hydrate` uses to fill in V1 fields.
+const V1_EXPANSIONS: &[Expansion] = &[
+    Expansion::AttachmentsMediaKeys,
+    Expansion::AuthorId,
+    Expansion::EntitiesMentionsUsername,
+    Expansion::GeoPlaceId,
+    Expansion::InReplyToUserId,
+    Expansion::ReferencedTweetsId,
+];
+
+/// The user fields from `RawUserV2::fields_needed_for_v1_twitter_user`.
+fn v1_user_fields() -> impl Iterator<Item = UserField> {
+    let needed = RawUserV2::fields_needed_for_v1_twitter_user();
+    UserField::ALL
+        .iter()
+        .copied()
+        .filter(move |field| needed.split(',').any(|name| name == field.as_str()))
+}
+
+/// The media fields `Includes::hydrate` uses to fill in `MediaEntity`s.
+const V1_MEDIA_FIELDS: &[MediaField] = &[
+    MediaField::AltText,
+    MediaField::Height,
+    MediaField::PreviewImageUrl,
+    MediaField::Url,
+    MediaField::Width,
+];
+
+/// Adds the expansions and fields `Includes::hydrate` needs to the given parameters.
+pub(crate) fn v1_expansion_params(params: ParamList) -> ParamList {
+    params
+        .add_param("expansions", join(V1_EXPANSIONS))
+        .add_param(
+            "user.fields",
+            RawUserV2::fields_needed_for_v1_twitter_user(),
+        )
+        .add_param("media.fields", join(V1_MEDIA_FIELDS))
+        .add_param("place.fields", join(PlaceField::ALL))
+}
+
+impl Expansion {
+    /// Returns whether this expansion loads users into `includes`.
+    pub fn includes_users(self) -> bool {
+        matches!(
+            self,
+            Expansion::AuthorId
+                | Expansion::EntitiesMentionsUsername
+                | Expansion::InReplyToUserId
+                | Expansion::ReferencedTweetsIdAuthorId
+        )
+    }
+}
+
+/// Loads tweets by their IDs, up to `MAX_LOOKUP_IDS` at a time.
+///
+/// Tweets that couldn't be loaded, for example because they were deleted, are left out of `data`,
+/// and Twitter's explanation of why is put in `errors` instead.
+pub fn lookup<I: IntoIterator<Item = u64>>(ids: I) -> Lookup<Vec<RawTweetV2>> {
+    let ids = ids.into_iter().collect::<Vec<_>>();
+    let params = ParamList::new().add_param(
+        "ids",
+        ids.iter().map(u64::to_string).collect::<Vec<_>>().join(","),
+    );
+    Lookup::new(links::v2::tweets::LOOKUP.to_string(), ids.len(), params)
+}
+
+/// Loads a single tweet by its ID.
+///
+/// If the tweet couldn't be loaded, Twitter's explanation of why is returned as
+/// `Error::TwitterProblem`.
+pub fn show(id: u64) -> Lookup<RawTweetV2> {
+    let uri = format!("{}/{}", links::v2::tweets::LOOKUP, id);
+    Lookup::new(uri, 1, ParamList::new())
+}
+
+/// A request to load tweets from the V2 API, before being sent.
+///
+/// This is returned by `lookup` and `show`. If no tweet fields are given with `tweet_fields`, the
+/// fields from `RawTweetV2::fields_needed_for_v1_raw_tweet` are requested, so the tweets can be
+/// turned into `Tweet`s.
+#[must_use = "Lookup is lazy and won't do anything unless `send` is called"]
+#[derive(Debug, Clone)]
+pub struct Lookup<T> {
+    uri: String,
+    id_count: usize,
+    params: ParamList,
+    tweet_fields: BTreeSet<TweetField>,
+    user_fields: BTreeSet<UserField>,
+    media_fields: BTreeSet<MediaField>,
+    place_fields: BTreeSet<PlaceField>,
+    poll_fields: BTreeSet<PollField>,
+    expansions: BTreeSet<Expansion>,
+    data: PhantomData<fn() -> T>,
+}
+
+impl<T> Lookup<T> {
+    fn new(uri: String, id_count: usize, params: ParamList) -> Lookup<T> {
+        Lookup {
+            uri,
+            id_count,
+            params,
+            tweet_fields: BTreeSet::new(),
+            user_fields: BTreeSet::new(),
+            media_fields: BTreeSet::new(),
+            place_fields: BTreeSet::new(),
+            poll_fields: BTreeSet::new(),
+            expansions: BTreeSet::new(),
+            data: PhantomData,
+        }
+    }
+
+    /// Adds the given fields to the ones requested for tweets.
+    pub fn tweet_fields<I: IntoIterator<Item = TweetField>>(mut self, fields: I) -> Self {
+        self.tweet_fields.extend(fields);
+        self
+    }
+
+    /// Adds the given fields to the ones requested for users in `includes`.
+    pub fn user_fields<I: IntoIterator<Item = UserField>>(mut self, fields: I) -> Self {
+        self.user_fields.extend(fields);
+        self
+    }
+
+    /// Adds the given fields to the ones requested for media in `includes`.
+    pub fn media_fields<I: IntoIterator<Item = MediaField>>(mut self, fields: I) -> Self {
+        self.media_fields.extend(fields);
+        self
+    }
+
+    /// Adds the given fields to the ones requested for places in `includes`.
+    pub fn place_fields<I: IntoIterator<Item = PlaceField>>(mut self, fields: I) -> Self {
+        self.place_fields.extend(fields);
+        self
+    }
+
+    /// Adds the given fields to the ones requested for polls in `includes`.
+    pub fn poll_fields<I: IntoIterator<Item = PollField>>(mut self, fields: I) -> Self {
+        self.poll_fields.extend(fields);
+        self
+    }
+
+    /// Adds the given expansions to the ones requested.
+    pub fn expansions<I: IntoIterator<Item = Expansion>>(mut self, expansions: I) -> Self {
+        self.expansions.extend(expansions);
+        self
+    }
+
+    /// Adds the expansions and fields needed to turn the loaded tweets into `Tweet`s with their
+    /// author, media, place, and referenced tweets filled in.
+    ///
+    /// This doesn't add any tweet fields, since the ones `Tweet` needs are requested by default.
+    /// If other tweet fields are requested, the ones from
+    /// `RawTweetV2::fields_needed_for_v1_raw_tweet` need to be included with them.
+    pub fn with_v1_expansions(self) -> Self {
+        self.expansions(V1_EXPANSIONS.iter().copied())
+            .user_fields(v1_user_fields())
+            .media_fields(V1_MEDIA_FIELDS.iter().copied())
+            .place_fields(PlaceField::ALL.iter().copied())
+    }
+
+    /// Checks that the requested fields and expansions make sense together.
+    ///
+    /// This returns `Error::InvalidRequest` if the wrong number of IDs was given, or if fields were
+    /// requested for an object that none of the expansions will include. `send` calls this before
+    /// sending the request.
+    pub fn validate(&self) -> Result<()> {
+        if self.id_count == 0 || self.id_count > MAX_LOOKUP_IDS {
+            return Err(Error::InvalidRequest(
+                "tweet lookup needs between 1 and 100 IDs",
+                Some(self.id_count.to_string()),
+            ));
+        }
+
+        let expanded = |check: fn(Expansion) -> bool| self.expansions.iter().any(|&e| check(e));
+        if !self.user_fields.is_empty() && !expanded(Expansion::includes_users) {
+            return Err(Error::InvalidRequest(
+                "user fields need an expansion that includes users",
+                Some(join(&self.user_fields)),
+            ));
+        }
+        if !self.media_fields.is_empty() && !expanded(|e| e == Expansion::AttachmentsMediaKeys) {
+            return Err(Error::InvalidRequest(
+                "media fields need the attachments.media_keys expansion",
+                Some(join(&self.media_fields)),
+            ));
+        }
+        if !self.place_fields.is_empty() && !expanded(|e| e == Expansion::GeoPlaceId) {
+            return Err(Error::InvalidRequest(
+                "place fields need the geo.place_id expansion",
+                Some(join(&self.place_fields)),
+            ));
+        }
+        if !self.poll_fields.is_empty() && !expanded(|e| e == Expansion::AttachmentsPollIds) {
+            return Err(Error::InvalidRequest(
+                "poll fields need the attachments.poll_ids expansion",
+                Some(join(&self.poll_fields)),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the requested fields can be loaded with the given token.
+    fn validate_token(&self, token: &Token) -> Result<()> {
+        let private_tweet_fields = self.tweet_fields.iter().filter(|f| {
+            matches!(
+                f,
+                TweetField::NonPublicMetrics
+                    | TweetField::OrganicMetrics
+                    | TweetField::PromotedMetrics
+            )
+        });
+        let private_media_fields = self.media_fields.iter().filter(|f| {
+            matches!(
+                f,
+                MediaField::NonPublicMetrics
+                    | MediaField::OrganicMetrics
+                    | MediaField::PromotedMetrics
+            )
+        });
+        let private = private_tweet_fields
+            .map(|f| f.as_str())
+            .chain(private_media_fields.map(|f| f.as_str()))
+            .collect::<Vec<_>>();
+
+        if let (Token::Bearer(_), Some(field)) = (token, private.first()) {
+            return Err(Error::InvalidRequest(
+                "private metrics need a user token",
+                Some(field.to_string()),
+            ));
+        }
+        Ok(())
+    }
+
+    fn params(&self) -> ParamList {
+        let tweet_fields = if self.tweet_fields.is_empty() {
+            RawTweetV2::fields_needed_for_v1_raw_tweet().to_string()
+        } else {
+            join(&self.tweet_fields)
+        };
+        self.params
+            .clone()
+            .add_param("tweet.fields", tweet_fields)
+            .add_opt_param("user.fields", opt_join(&self.user_fields))
+            .add_opt_param("media.fields", opt_join(&self.media_fields))
+            .add_opt_param("place.fields", opt_join(&self.place_fields))
+            .add_opt_param("poll.fields", opt_join(&self.poll_fields))
+            .add_opt_param("expansions", opt_join(&self.expansions))
+    }
+}
+
+impl<T: DeserializeOwned> Lookup<T> {
+    async fn load(self, client: &Client) -> Result<(Headers, Response<RawLookup<T>>)> {
+        self.validate()?;
+        self.validate_token(client.token())?;
+        load_lookup(client, &self.uri, &self.params()).await
+    }
+}
+
+impl Lookup<Vec<RawTweetV2>> {
+    /// Sends the request, loading the tweets and any objects requested with `expansions`.
+    pub async fn send(
+        self,
+        token: impl Into<Client>,
+    ) -> Result<Response<LookupResponse<Vec<RawTweetV2>>>> {
+        let client: Client = token.into();
+        let (_, resp) = self.load(&client).await?;
+        Ok(Response::map(resp, RawLookup::into_many))
+    }
+}
+
+impl Lookup<RawTweetV2> {
+    /// Sends the request, loading the tweet and any objects requested with `expansions`.
+    pub async fn send(
+        self,
+        token: impl Into<Client>,
+    ) -> Result<Response<LookupResponse<RawTweetV2>>> {
+        let client: Client = token.into();
+        let (headers, resp) = self.load(&client).await?;
+        Response::try_map(resp, |raw| raw.into_one(headers))
+    }
+}
+
+/// Sends a lookup request to the V2 API, and parses the response.
+pub(crate) async fn load_lookup<T: DeserializeOwned>(
+    client: &Client,
+    uri: &str,
+    params: &ParamList,
+) -> Result<(Headers, Response<RawLookup<T>>)> {
+    let req = client.request_get(uri, Some(params));
+    let (headers, body) = client.response_raw_bytes(req).await?;
+    let resp = json_response(&headers, &body)?;
+    Ok((headers, resp))
+}
+
+/// Tweets or users loaded from the V2 API, along with the objects requested with expansions.
+///
+/// This is returned by tweet lookups in this module, and by user lookups in `user::v2`.
+#[derive(Debug, Clone)]
+pub struct LookupResponse<T> {
+    /// The tweets or users that were loaded.
+    pub data: T,
+    /// The objects requested with expansions.
+    pub includes: Includes,
+    /// Problems loading some of the requested objects or expanded objects, if there were any.
+    pub errors: Vec<Problem>,
+}
+
+impl LookupResponse<Vec<RawTweetV2>> {
+    /// Converts the loaded tweets into `Tweet`s, filled in from `includes`.
+    pub fn into_tweets(self) -> Result<Vec<Tweet>> {
+        let includes = self.includes;
+        self.data
+            .into_iter()
+            .map(|raw| includes.hydrate(raw))
+            .collect()
+    }
+}
+
+impl LookupResponse<RawTweetV2> {
+    /// Converts the loaded tweet into a `Tweet`, filled in from `includes`.
+    pub fn into_tweet(self) -> Result<Tweet> {
+        self.includes.hydrate(self.data)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+pub(crate) struct RawLookup<T> {
+    #[serde(default = "Option::default")]
+    data: Option<T>,
+    #[serde(default)]
+    includes: Includes,
+    #[serde(default)]
+    errors: Vec<Problem>,
+}
+
+impl<T> RawLookup<Vec<T>> {
+    /// Returns the objects that were loaded, leaving the ones that couldn't be in `errors`.
+    pub(crate) fn into_many(self) -> LookupResponse<Vec<T>> {
+        LookupResponse {
+            data: self.data.unwrap_or_default(),
+            includes: self.includes,
+            errors: self.errors,
+        }
+    }
+}
+
+impl<T> RawLookup<T> {
+    /// Returns the object that was loaded, or the first error if it couldn't be.
+    pub(crate) fn into_one(self, headers: Headers) -> Result<LookupResponse<T>> {
+        match self.data {
+            Some(data) => Ok(LookupResponse {
+                data,
+                includes: self.includes,
+                errors: self.errors,
+            }),
+            None => match self.errors.into_iter().next() {
+                Some(problem) => Err(Error::TwitterProblem(headers, Box::new(problem))),
+                None => Err(Error::MissingValue("data")),
+            },
+        }
+    }
+}
+
+/// The objects requested with expansions, loaded alongside tweets from the V2 API.
+///
+/// Each list is only filled in if an `Expansion` asked for it.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Includes {
+    /// Tweets that were quoted, replied to, or retweeted.
+    #[serde(default)]
+    pub tweets: Vec<RawTweetV2>,
+    /// Users who posted or were mentioned in the tweets.
+    #[serde(default)]
+    pub users: Vec<RawUserV2>,
+    /// Media attached to the tweets.
+    #[serde(default)]
+    pub media: Vec<RawMediaV2>,
+    /// Places attached to the tweets.
+    #[serde(default)]
+    pub places: Vec<RawPlaceV2>,
+    /// Polls attached to the tweets.
+    #[serde(default)]
+    pub polls: Vec<RawPollV2>,
+}
+
+impl Includes {
+    /// Converts a tweet from the same response into a `Tweet`, filling in its author, media,
+    /// place, and referenced tweets from these objects.
+    ///
+    /// Objects are only filled in if they were included, and have the fields needed to convert
+    /// them into their V1 types. `Lookup::with_v1_expansions` asks for all of these. Referenced
+    /// tweets are filled in as `quoted_status` or `retweeted_status`, but their own referenced
+    /// tweets aren't.
+    pub fn hydrate(&self, raw: RawTweetV2) -> Result<Tweet> {
+        super::from_v2(raw, self, true)
+    }
+
+    /// Returns the included tweet with the given ID, if there is one.
+    pub fn tweet(&self, id: u64) -> Option<&RawTweetV2> {
+        self.tweets.iter().find(|t| t.id == id)
+    }
+
+    /// Returns the included user with the given ID, if there is one.
+    pub fn user(&self, id: u64) -> Option<&RawUserV2> {
+        self.users.iter().find(|u| u.id == id)
+    }
+
+    /// Returns the included media with the given key, if there is one.
+    pub fn media(&self, media_key: &str) -> Option<&RawMediaV2> {
+        self.media.iter().find(|m| m.media_key == media_key)
+    }
+
+    /// Returns the included place with the given ID, if there is one.
+    pub fn place(&self, id: &str) -> Option<&RawPlaceV2> {
+        self.places.iter().find(|p| p.id == id)
+    }
+
+    /// Returns the included poll with the given ID, if there is one.
+    pub fn poll(&self, id: u64) -> Option<&RawPollV2> {
+        self.polls.iter().find(|p| p.id == id)
+    }
+}
+
+/// Types that tweets from the V2 API can be converted into, using the `Includes` loaded with them.
+///
+/// This lets search results be returned as either `RawTweetV2` or `Tweet`.
+pub trait FromRawTweetV2: Sized {
+    /// Converts the given tweet, using the `Includes` from the same response.
+    fn from_raw_tweet_v2(raw: RawTweetV2, includes: &Includes) -> Result<Self>;
+}
+
+impl FromRawTweetV2 for RawTweetV2 {
+    fn from_raw_tweet_v2(raw: RawTweetV2, _: &Includes) -> Result<Self> {
+        Ok(raw)
+    }
+}
+
+impl FromRawTweetV2 for Tweet {
+    fn from_raw_tweet_v2(raw: RawTweetV2, includes: &Includes) -> Result<Self> {
+        includes.hydrate(raw)
+    }
+}
+
+/// A photo, video, or GIF attached to a tweet, as returned from the V2 API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawMediaV2 {
+    // Always present.
+    /// The key tweets use to refer to this media in their `attachments`.
+    pub media_key: String,
+    // Always present.
+    /// What kind of media this is: `photo`, `video`, or `animated_gif`.
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// The length of a video, in milliseconds.
+    pub duration_ms: Option<u64>,
+    /// The height of the media, in pixels.
+    pub height: Option<u32>,
+    /// The width of the media, in pixels.
+    pub width: Option<u32>,
+    /// A URL to a still image for a video.
+    pub preview_image_url: Option<String>,
+    /// A URL to the image, for photos.
+    pub url: Option<String>,
+    /// The description of the media given by its uploader.
+    pub alt_text: Option<String>,
+}
+
+impl RawMediaV2 {
+    /// Converts this media into a `MediaEntity`, with the link to it from the tweet's entities.
+    ///
+    /// The V2 API only gives the size of the original media, so every entry in `sizes` uses it.
+    /// Returns `None` if the media type or key isn't one that V1 knows about.
+    pub(crate) fn to_media_entity(&self, link: Option<&UrlEntity>) -> Option<MediaEntity> {
+        let media_type = match self.kind.as_str() {
+            "photo" => MediaType::Photo,
+            "video" => MediaType::Video,
+            "animated_gif" => MediaType::Gif,
+            _ => return None,
+        };
+        // media keys look like `3_1064638969197977600`, with the media ID after the underscore
+        let id = self.media_key.split('_').nth(1)?.parse().ok()?;
+        let media_url = self
+            .url
+            .clone()
+            .or_else(|| self.preview_image_url.clone())
+            .unwrap_or_default();
+        let size = MediaSize {
+            w: self.width.unwrap_or(0) as i32,
+            h: self.height.unwrap_or(0) as i32,
+            resize: ResizeMode::Fit,
+        };
+
+        Some(MediaEntity {
+            display_url: link.map_or_else(|| media_url.clone(), |l| l.display_url.clone()),
+            expanded_url: link.map_or_else(|| media_url.clone(), |l| l.expanded_url.to_string()),
+            id,
+            range: link.map_or((0, 0), |l| (l.start, l.end)),
+            media_url_https: media_url.clone(),
+            sizes: MediaSizes {
+                thumb: size,
+                small: size,
+                medium: size,
+                large: size,
+            },
+            source_status_id: None,
+            media_type,
+            url: link.map_or_else(|| media_url.clone(), |l| l.url.to_string()),
+            video_info: None,
+            ext_alt_text: self.alt_text.clone(),
+            media_url,
+        })
+    }
+}
+
+/// A place attached to a tweet, as returned from the V2 API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawPlaceV2 {
+    // Always present.
+    /// ID of this place.
+    pub id: String,
+    // Always present.
+    /// The full name of the place, like "San Francisco, CA".
+    pub full_name: String,
+
+    /// The IDs of places that contain this one.
+    pub contained_within: Option<Vec<String>>,
+    /// The name of the country the place is in.
+    pub country: Option<String>,
+    /// The ISO code of the country the place is in.
+    pub country_code: Option<String>,
+    /// The bounding box of the place.
+    pub geo: Option<PlaceGeoV2>,
+    /// The short name of the place, like "San Francisco".
+    pub name: Option<String>,
+    /// What kind of place this is, like `city`.
+    pub place_type: Option<String>,
+}
+
+impl TryFrom<RawPlaceV2> for Place {
+    type Error = Error;
+
+    /// Converts a place from the V2 API into a `Place`.
+    ///
+    /// The `place_type` field needs to have been requested. `contained_within` only holds IDs in
+    /// the V2 API, so it's left empty.
+    fn try_from(raw: RawPlaceV2) -> Result<Place> {
+        let place_type = raw.place_type.ok_or(Error::MissingValue("place_type"))?;
+        let place_type: PlaceType = serde_json::from_value(place_type.clone().into())
+            .map_err(|_| Error::InvalidResponse("unknown place type", Some(place_type)))?;
+        let bounding_box = match raw.geo.as_ref().map(|g| &g.bbox[..]) {
+            Some(&[west, south, east, north]) => {
+                vec![(west, south), (east, south), (east, north), (west, north)]
+            }
+            _ => vec![],
+        };
+
+        Ok(Place {
+            id: raw.id,
+            attributes: Default::default(),
+            bounding_box,
+            country: raw.country.unwrap_or_default(),
+            country_code: raw.country_code.unwrap_or_default(),
+            full_name: raw.full_name,
+            name: raw.name.unwrap_or_default(),
+            place_type,
+            contained_within: None,
+        })
+    }
+}
+
+/// The bounding box of a `RawPlaceV2`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaceGeoV2 {
+    /// The corners of the box, as `[west longitude, south latitude, east longitude, north
+    /// latitude]`.
+    pub bbox: Vec<f64>,
+}
+
+/// A poll attached to a tweet, as returned from the V2 API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawPollV2 {
+    // Always present.
+    /// ID of this poll.
+    #[serde(deserialize_with = "serde_num_string::deserialize_number_from_string")]
+    pub id: u64,
+    // Always present.
+    /// The choices in the poll.
+    pub options: Vec<PollOptionV2>,
+
+    /// How long the poll is open for, in minutes.
+    pub duration_minutes: Option<u32>,
+    /// When the poll closes.
+    pub end_datetime: Option<chrono::DateTime<chrono::Utc>>,
+    /// Whether the poll is `open` or `closed`.
+    pub voting_status: Option<String>,
+}
+
+/// One of the choices in a `RawPollV2`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PollOptionV2 {
+    /// Where this choice appears in the poll, starting at 1.
+    pub position: u32,
+    /// The text of this choice.
+    pub label: String,
+    /// How many votes this choice has.
+    pub votes: u64,
+}
+
+pub(crate) fn join<I>(items: I) -> String
+where
+    I: IntoIterator,
+    I::Item: fmt::Display,
+{
+    items
+        .into_iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn opt_join<T: fmt::Display>(set: &BTreeSet<T>) -> Option<String> {
+    if set.is_empty() {
+        None
+    } else {
+        Some(join(set))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_validates_fields() {
+        assert!(lookup(Vec::new()).validate().is_err());
+        assert!(lookup(0..=MAX_LOOKUP_IDS as u64).validate().is_err());
+        assert!(show(20).validate().is_ok());
+
+        let err = show(20).user_fields(vec![UserField::Verified]).validate();
+        assert!(matches!(err, Err(Error::InvalidRequest(_, Some(_)))));
+        let req = show(20)
+            .user_fields(vec![UserField::Verified])
+            .expansions(vec![Expansion::ReferencedTweetsIdAuthorId]);
+        assert!(req.validate().is_ok());
+        assert!(show(20)
+            .media_fields(vec![MediaField::Url])
+            .expansions(vec![Expansion::AuthorId])
+            .validate()
+            .is_err());
+
+        assert_eq!(
+            join(v1_user_fields()),
+            RawUserV2::fields_needed_for_v1_twitter_user()
+        );
+
+        let req = show(20).tweet_fields(vec![TweetField::NonPublicMetrics]);
+        assert!(req.validate_token(&Token::Bearer("token".into())).is_err());
+
+        let params = lookup(vec![20, 21])
+            .tweet_fields(vec![TweetField::CreatedAt, TweetField::AuthorId])
+            .tweet_fields(vec![TweetField::AuthorId])
+            .expansions(vec![Expansion::GeoPlaceId, Expansion::AuthorId])
+            .params();
+        assert_eq!(params.get("ids").map(|v| &**v), Some("20,21"));
+        assert_eq!(
+            params.get("tweet.fields").map(|v| &**v),
+            Some("author_id,created_at")
+        );
+        assert_eq!(
+            params.get("expansions").map(|v| &**v),
+            Some("author_id,geo.place_id")
+        );
+        assert!(params.get("user.fields").is_none());
+    }
+
+    #[test]
+    fn parse_lookup_response() {
+        let raw: RawLookup<Vec<RawTweetV2>> = serde_json::from_str(
+            r#"{
+                "data": [{
+                    "id": "1067094924124872705",
+                    "text": "Just getting started with Twitter APIs?",
+                    "author_id": "2244994945",
+                    "attachments": { "media_keys": ["13_1064638969197977600"] },
+                    "referenced_tweets": [{ "type": "retweeted", "id": "1067094924124872704" }]
+                }],
+                "includes": {
+                    "users": [{
+                        "id": "2244994945",
+                        "name": "Twitter Dev",
+                        "username": "TwitterDev"
+                    }],
+                    "media": [{ "media_key": "13_1064638969197977600", "type": "video" }],
+                    "polls": [{
+                        "id": "1199786642468413448",
+                        "options": [{ "position": 1, "label": "yes", "votes": 3 }]
+                    }]
+                },
+                "errors": [{
+                    "value": "20",
+                    "detail": "Could not find tweet with ids: [20].",
+                    "title": "Not Found Error",
+                    "resource_type": "tweet",
+                    "parameter": "ids",
+                    "resource_id": "20",
+                    "type": "https://api.twitter.com/2/problems/resource-not-found"
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let data = raw.data.unwrap();
+        assert_eq!(data[0].author_id, Some(2244994945));
+        assert_eq!(raw.includes.users[0].username, "TwitterDev");
+        assert_eq!(raw.includes.media[0].kind, "video");
+        assert_eq!(raw.includes.polls[0].options[0].votes, 3);
+        assert!(raw.includes.places.is_empty());
+        assert!(raw.errors[0].known().is_some());
+    }
+
+    #[test]
+    fn hydrate_from_includes() {
+        let json: serde_json::Value =
+            serde_json::from_str(include_str!("../../sample_payloads/sample-v2-search.json"))
+                .unwrap();
+        let page: crate::cursor::SearchCursor<Tweet> =
+            serde_json::from_value(json.clone()).unwrap();
+        let tweet = &page.data[0];
+
+        let user = tweet.user.as_ref().unwrap();
+        assert_eq!(user.screen_name, "Twitter");
+        assert_eq!(user.followers_count, 60512380);
+        assert!(user.verified);
+        assert_eq!(tweet.in_reply_to_status_id, Some(1389968470408482818));
+        assert_eq!(tweet.in_reply_to_screen_name.as_deref(), Some("TwitterDev"));
+        assert_eq!(tweet.entities.user_mentions[0].id, 2244994945);
+
+        assert_eq!(tweet.entities.urls.len(), 1);
+        let media = &tweet.extended_entities.as_ref().unwrap().media;
+        assert_eq!(media.len(), 1);
+        assert_eq!(media[0].id, 1389989931025928196);
+        assert_eq!(media[0].display_url, "pic.twitter.com/b1m7t4L5Ih");
+        assert_eq!(media[0].range, (50, 73));
+        assert_eq!(media[0].sizes.large.w, 1200);
+        assert_eq!(media[0].ext_alt_text.as_deref(), Some("A bird in a field"));
+        assert_eq!(tweet.entities.media.as_ref().unwrap().len(), 1);
+
+        let place = tweet.place.as_ref().unwrap();
+        assert_eq!(place.full_name, "San Francisco, CA");
+        assert_eq!(place.bounding_box[2], (-122.357031, 37.833238));
+
+        let quoted = tweet.quoted_status.as_ref().unwrap();
+        assert_eq!(tweet.quoted_status_id, Some(quoted.id));
+        assert_eq!(quoted.favorite_count, 211);
+        // the quoted tweet's author didn't come with the fields `TwitterUser` needs
+        assert!(quoted.user.is_none());
+        assert!(tweet.retweeted_status.is_none());
+
+        // without includes, the same tweet still converts
+        let raw: RawTweetV2 = serde_json::from_value(json["data"][0].clone()).unwrap();
+        let bare = Tweet::try_from(raw).unwrap();
+        assert!(bare.user.is_none() && bare.place.is_none() && bare.quoted_status.is_none());
+        assert_eq!(bare.entities.urls.len(), 1);
+        assert!(bare.extended_entities.is_none());
+    }
+}
@@ -2,9 +2,10 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use crate::auth::AccessLevel;
 use crate::common::*;
 use crate::error::Result;
-use crate::{auth, cursor, links};
+use crate::{cursor, links};
 
 use super::*;
 
@@ -73,11 +74,12 @@ use super::*;
 /// let users = egg_mode::user::lookup(list, &token).await.unwrap();
 /// # }
 /// ```
-pub async fn lookup<T, I>(accts: I, token: &auth::Token) -> Result<Response<Vec<TwitterUser>>>
+pub async fn lookup<T, I>(accts: I, token: impl Into<Client>) -> Result<Response<Vec<TwitterUser>>>
 where
     T: Into<UserID>,
     I: IntoIterator<Item = T>,
 {
+    let client: Client = token.into();
     let (id_param, name_param) = multiple_names_param(accts);
 
     let params = ParamList::new()
@@ -85,37 +87,40 @@ where
         .add_param("user_id", id_param)
         .add_param("screen_name", name_param);
 
-    let req = post(links::users::LOOKUP, token, Some(&params));
+    let req = client.request_post(links::users::LOOKUP, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 /// Lookup user information for a single user.
-pub async fn show<T: Into<UserID>>(acct: T, token: &auth::Token) -> Result<Response<TwitterUser>> {
+pub async fn show<T: Into<UserID>>(acct: T, token: impl Into<Client>) -> Result<Response<TwitterUser>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .extended_tweets()
         .add_user_param(acct.into());
 
-    let req = get(links::users::SHOW, token, Some(&params));
+    let req = client.request_get(links::users::SHOW, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 /// Lookup the user IDs that the authenticating user has disabled retweets from.
 ///
 /// Use `update_follow` to enable/disable viewing retweets from a specific user.
-pub async fn friends_no_retweets(token: &auth::Token) -> Result<Response<Vec<u64>>> {
-    let req = get(links::users::FRIENDS_NO_RETWEETS, token, None);
+pub async fn friends_no_retweets(token: impl Into<Client>) -> Result<Response<Vec<u64>>> {
+    let client: Client = token.into();
+    let req = client.request_get(links::users::FRIENDS_NO_RETWEETS, None);
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 /// Lookup relationship settings between two arbitrary users.
-pub async fn relation<F, T>(from: F, to: T, token: &auth::Token) -> Result<Response<Relationship>>
+pub async fn relation<F, T>(from: F, to: T, token: impl Into<Client>) -> Result<Response<Relationship>>
 where
     F: Into<UserID>,
     T: Into<UserID>,
 {
+    let client: Client = token.into();
     let mut params = match from.into() {
         UserID::ID(id) => ParamList::new().add_param("source_id", id.to_string()),
         UserID::ScreenName(name) => ParamList::new().add_param("source_screen_name", name),
@@ -125,14 +130,14 @@ where
         UserID::ScreenName(name) => params.add_param_ref("target_screen_name", name),
     };
 
-    let req = get(links::users::FRIENDSHIP_SHOW, token, Some(&params));
+    let req = client.request_get(links::users::FRIENDSHIP_SHOW, Some(&params));
 
     // the relationship returned by Twitter is actually contained within a `"relationship"` field,
     // so this wrapper struct makes sure the data is loaded correctly
     #[derive(Deserialize)]
     struct RelationWrapper { relationship: Relationship }
 
-    let resp: Response<RelationWrapper> = request_with_json_response(req).await?;
+    let resp: Response<RelationWrapper> = client.response_json(req).await?;
 
     Ok(Response::map(resp, |r| r.relationship))
 }
@@ -140,21 +145,22 @@ where
 /// Lookup the relations between the authenticated user and the given accounts.
 pub async fn relation_lookup<T, I>(
     accts: I,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<Vec<RelationLookup>>>
 where
     T: Into<UserID>,
     I: IntoIterator<Item = T>,
 {
+    let client: Client = token.into();
     let (id_param, name_param) = multiple_names_param(accts);
 
     let params = ParamList::new()
         .add_param("user_id", id_param)
         .add_param("screen_name", name_param);
 
-    let req = get(links::users::FRIENDSHIP_LOOKUP, token, Some(&params));
+    let req = client.request_get(links::users::FRIENDSHIP_LOOKUP, Some(&params));
 
-    request_with_json_response(req).await
+    client.response_json(req).await
 }
 
 //---Cursored collections---
@@ -167,8 +173,8 @@ where
 /// page for details.
 ///
 /// [`UserSearch`]: struct.UserSearch.html
-pub fn search<S: Into<CowStr>>(query: S, token: &auth::Token) -> UserSearch {
-    UserSearch::new(query, token)
+pub fn search<S: Into<CowStr>>(query: S, token: impl Into<Client>) -> UserSearch {
+    UserSearch::new(query, token.into())
 }
 
 /// Lookup the users a given account follows, also called their "friends" within the API.
@@ -177,10 +183,10 @@ pub fn search<S: Into<CowStr>>(query: S, token: &auth::Token) -> UserSearch {
 /// method defaults to returning 20 users in a single network call; the maximum is 200.
 pub fn friends_of<T: Into<UserID>>(
     acct: T,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> cursor::CursorIter<cursor::UserCursor> {
     let params = ParamList::new().add_user_param(acct.into());
-    cursor::CursorIter::new(links::users::FRIENDS_LIST, token, Some(params), Some(20))
+    cursor::CursorIter::new(links::users::FRIENDS_LIST, token.into(), Some(params), Some(20))
 }
 
 /// Lookup the users a given account follows, also called their "friends" within the API, but only
@@ -194,10 +200,10 @@ pub fn friends_of<T: Into<UserID>>(
 /// several results and don't need all the user information.
 pub fn friends_ids<T: Into<UserID>>(
     acct: T,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> cursor::CursorIter<cursor::IDCursor> {
     let params = ParamList::new().add_user_param(acct.into());
-    cursor::CursorIter::new(links::users::FRIENDS_IDS, token, Some(params), Some(500))
+    cursor::CursorIter::new(links::users::FRIENDS_IDS, token.into(), Some(params), Some(500))
 }
 
 /// Lookup the users that follow a given account.
@@ -206,12 +212,12 @@ pub fn friends_ids<T: Into<UserID>>(
 /// method defaults to returning 20 users in a single network call; the maximum is 200.
 pub fn followers_of<T: Into<UserID>>(
     acct: T,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> cursor::CursorIter<cursor::UserCursor> {
     let params = ParamList::new()
         .extended_tweets()
         .add_user_param(acct.into());
-    cursor::CursorIter::new(links::users::FOLLOWERS_LIST, token, Some(params), Some(20))
+    cursor::CursorIter::new(links::users::FOLLOWERS_LIST, token.into(), Some(params), Some(20))
 }
 
 /// Lookup the users that follow a given account, but only return their user IDs.
@@ -224,10 +230,10 @@ pub fn followers_of<T: Into<UserID>>(
 /// several results and don't need all the user information.
 pub fn followers_ids<T: Into<UserID>>(
     acct: T,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> cursor::CursorIter<cursor::IDCursor> {
     let params = ParamList::new().add_user_param(acct.into());
-    cursor::CursorIter::new(links::users::FOLLOWERS_IDS, token, Some(params), Some(500))
+    cursor::CursorIter::new(links::users::FOLLOWERS_IDS, token.into(), Some(params), Some(500))
 }
 
 /// Lookup the users that have been blocked by the authenticated user.
@@ -236,8 +242,8 @@ pub fn followers_ids<T: Into<UserID>>(
 /// the page size. Calling `with_page_size` on a stream returned by this function will not
 /// change the page size used by the network call. Setting `page_size` manually may result in an
 /// error from Twitter.
-pub fn blocks(token: &auth::Token) -> cursor::CursorIter<cursor::UserCursor> {
-    cursor::CursorIter::new(links::users::BLOCKS_LIST, token, None, None)
+pub fn blocks(token: impl Into<Client>) -> cursor::CursorIter<cursor::UserCursor> {
+    cursor::CursorIter::new(links::users::BLOCKS_LIST, token.into(), None, None)
 }
 
 /// Lookup the users that have been blocked by the authenticated user, but only return their user
@@ -251,8 +257,8 @@ pub fn blocks(token: &auth::Token) -> cursor::CursorIter<cursor::UserCursor> {
 /// the page size. Calling `with_page_size` on a stream returned by this function will not
 /// change the page size used by the network call. Setting `page_size` manually may result in an
 /// error from Twitter.
-pub fn blocks_ids(token: &auth::Token) -> cursor::CursorIter<cursor::IDCursor> {
-    cursor::CursorIter::new(links::users::BLOCKS_IDS, token, None, None)
+pub fn blocks_ids(token: impl Into<Client>) -> cursor::CursorIter<cursor::IDCursor> {
+    cursor::CursorIter::new(links::users::BLOCKS_IDS, token.into(), None, None)
 }
 
 /// Lookup the users that have been muted by the authenticated user.
@@ -261,8 +267,8 @@ pub fn blocks_ids(token: &auth::Token) -> cursor::CursorIter<cursor::IDCursor> {
 /// the page size. Calling `with_page_size` on a stream returned by this function will not
 /// change the page size used by the network call. Setting `page_size` manually may result in an
 /// error from Twitter.
-pub fn mutes(token: &auth::Token) -> cursor::CursorIter<cursor::UserCursor> {
-    cursor::CursorIter::new(links::users::MUTES_LIST, token, None, None)
+pub fn mutes(token: impl Into<Client>) -> cursor::CursorIter<cursor::UserCursor> {
+    cursor::CursorIter::new(links::users::MUTES_LIST, token.into(), None, None)
 }
 
 /// Lookup the users that have been muted by the authenticated user, but only return their user IDs.
@@ -275,20 +281,20 @@ pub fn mutes(token: &auth::Token) -> cursor::CursorIter<cursor::UserCursor> {
 /// the page size. Calling `with_page_size` on a stream returned by this function will not
 /// change the page size used by the network call. Setting `page_size` manually may result in an
 /// error from Twitter.
-pub fn mutes_ids(token: &auth::Token) -> cursor::CursorIter<cursor::IDCursor> {
-    cursor::CursorIter::new(links::users::MUTES_IDS, token, None, None)
+pub fn mutes_ids(token: impl Into<Client>) -> cursor::CursorIter<cursor::IDCursor> {
+    cursor::CursorIter::new(links::users::MUTES_IDS, token.into(), None, None)
 }
 
 /// Lookup the user IDs who have pending requests to follow the authenticated protected user.
 ///
 /// If the authenticated user is not a protected account, this will return an empty collection.
-pub fn incoming_requests(token: &auth::Token) -> cursor::CursorIter<cursor::IDCursor> {
-    cursor::CursorIter::new(links::users::FRIENDSHIPS_INCOMING, token, None, None)
+pub fn incoming_requests(token: impl Into<Client>) -> cursor::CursorIter<cursor::IDCursor> {
+    cursor::CursorIter::new(links::users::FRIENDSHIPS_INCOMING, token.into(), None, None)
 }
 
 /// Lookup the user IDs with which the authenticating user has a pending follow request.
-pub fn outgoing_requests(token: &auth::Token) -> cursor::CursorIter<cursor::IDCursor> {
-    cursor::CursorIter::new(links::users::FRIENDSHIPS_OUTGOING, token, None, None)
+pub fn outgoing_requests(token: impl Into<Client>) -> cursor::CursorIter<cursor::IDCursor> {
+    cursor::CursorIter::new(links::users::FRIENDSHIPS_OUTGOING, token.into(), None, None)
 }
 
 //---User actions---
@@ -305,14 +311,16 @@ pub fn outgoing_requests(token: &auth::Token) -> cursor::CursorIter<cursor::IDCu
 pub async fn follow<T: Into<UserID>>(
     acct: T,
     notifications: bool,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<TwitterUser>> {
+    let client: Client = token.into();
+    client.require_access(AccessLevel::ReadWrite)?;
     let params = ParamList::new()
         .extended_tweets()
         .add_user_param(acct.into())
         .add_param("follow", notifications.to_string());
-    let req = post(links::users::FOLLOW, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(links::users::FOLLOW, Some(&params));
+    client.response_json(req).await
 }
 
 /// Unfollow the given account with the authenticated user.
@@ -323,13 +331,14 @@ pub async fn follow<T: Into<UserID>>(
 /// change any settings.
 pub async fn unfollow<T: Into<UserID>>(
     acct: T,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<TwitterUser>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .extended_tweets()
         .add_user_param(acct.into());
-    let req = post(links::users::UNFOLLOW, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(links::users::UNFOLLOW, Some(&params));
+    client.response_json(req).await
 }
 
 /// Update notification settings and reweet visibility for the given user.
@@ -342,28 +351,30 @@ pub async fn update_follow<T>(
     acct: T,
     notifications: Option<bool>,
     retweets: Option<bool>,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<Relationship>>
 where
     T: Into<UserID>,
 {
+    let client: Client = token.into();
     let params = ParamList::new()
         .add_user_param(acct.into())
         .add_opt_param("device", notifications.map(|v| v.to_string()))
         .add_opt_param("retweets", retweets.map(|v| v.to_string()));
-    let req = post(links::users::FRIENDSHIP_UPDATE, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(links::users::FRIENDSHIP_UPDATE, Some(&params));
+    client.response_json(req).await
 }
 
 /// Block the given account with the authenticated user.
 ///
 /// Upon success, the future returned by this function yields the given user.
-pub async fn block<T: Into<UserID>>(acct: T, token: &auth::Token) -> Result<Response<TwitterUser>> {
+pub async fn block<T: Into<UserID>>(acct: T, token: impl Into<Client>) -> Result<Response<TwitterUser>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .extended_tweets()
         .add_user_param(acct.into());
-    let req = post(links::users::BLOCK, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(links::users::BLOCK, Some(&params));
+    client.response_json(req).await
 }
 
 /// Block the given account and report it for spam, with the authenticated user.
@@ -371,13 +382,14 @@ pub async fn block<T: Into<UserID>>(acct: T, token: &auth::Token) -> Result<Resp
 /// Upon success, the future returned by this function yields the given user.
 pub async fn report_spam<T: Into<UserID>>(
     acct: T,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<TwitterUser>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .extended_tweets()
         .add_user_param(acct.into());
-    let req = post(links::users::REPORT_SPAM, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(links::users::REPORT_SPAM, Some(&params));
+    client.response_json(req).await
 }
 
 /// Unblock the given user with the authenticated user.
@@ -385,24 +397,26 @@ pub async fn report_spam<T: Into<UserID>>(
 /// Upon success, the future returned by this function yields the given user.
 pub async fn unblock<T: Into<UserID>>(
     acct: T,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<TwitterUser>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .extended_tweets()
         .add_user_param(acct.into());
-    let req = post(links::users::UNBLOCK, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(links::users::UNBLOCK, Some(&params));
+    client.response_json(req).await
 }
 
 /// Mute the given user with the authenticated user.
 ///
 /// Upon success, the future returned by this function yields the given user.
-pub async fn mute<T: Into<UserID>>(acct: T, token: &auth::Token) -> Result<Response<TwitterUser>> {
+pub async fn mute<T: Into<UserID>>(acct: T, token: impl Into<Client>) -> Result<Response<TwitterUser>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .extended_tweets()
         .add_user_param(acct.into());
-    let req = post(links::users::MUTE, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(links::users::MUTE, Some(&params));
+    client.response_json(req).await
 }
 
 /// Unmute the given user with the authenticated user.
@@ -410,11 +424,12 @@ pub async fn mute<T: Into<UserID>>(acct: T, token: &auth::Token) -> Result<Respo
 /// Upon success, the future returned by this function yields the given user.
 pub async fn unmute<T: Into<UserID>>(
     acct: T,
-    token: &auth::Token,
+    token: impl Into<Client>,
 ) -> Result<Response<TwitterUser>> {
+    let client: Client = token.into();
     let params = ParamList::new()
         .extended_tweets()
         .add_user_param(acct.into());
-    let req = post(links::users::UNMUTE, token, Some(&params));
-    request_with_json_response(req).await
+    let req = client.request_post(links::users::UNMUTE, Some(&params));
+    client.response_json(req).await
 }
@@ -42,6 +42,11 @@
 //! - `friends_no_retweets`
 //! - `relation`/`relation_lookup`
 //!
+//! The [`v2`][] module has versions of `show` and `lookup` that use the V2 API, along with `me`
+//! to load the user a token belongs to. The users they load can be turned into `TwitterUser`s.
+//!
+//! [`v2`]: v2/index.html
+//!
 //! ### Cursored lookup
 //!
 //! These functions imply that they can return more entries than Twitter is willing to return at
@@ -55,6 +60,7 @@
 //! - `mutes`/`mutes_ids`
 //! - `incoming_requests`/`outgoing_requests`
 
+use std::convert::TryFrom;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -65,12 +71,14 @@ use futures::Stream;
 use serde::{Deserialize, Serialize};
 
 use crate::common::*;
-use crate::{auth, entities, error, links, tweet};
+use crate::{entities, error, links, tweet};
 
 mod fun;
 mod raw;
+pub mod v2;
 
 pub use self::fun::*;
+pub use self::raw::{RawUserV2, UserPublicMetrics};
 
 /// Convenience enum to generalize between referring to an account by numeric ID or by screen name.
 ///
@@ -357,6 +365,73 @@ impl From<raw::RawTwitterUser> for TwitterUser {
     }
 }
 
+impl TryFrom<RawUserV2> for TwitterUser {
+    type Error = error::Error;
+
+    /// Converts a user from the V2 API into a `TwitterUser`.
+    ///
+    /// The fields from `RawUserV2::fields_needed_for_v1_twitter_user` need to have been requested.
+    /// Fields the V2 API doesn't have, like the profile theme colors, are left empty.
+    fn try_from(raw: RawUserV2) -> error::Result<TwitterUser> {
+        let metrics = raw
+            .public_metrics
+            .ok_or(error::Error::MissingValue("public_metrics"))?;
+        let profile_image_url = raw
+            .profile_image_url
+            .ok_or(error::Error::MissingValue("profile_image_url"))?;
+
+        Ok(raw::RawTwitterUser {
+            contributors_enabled: false,
+            created_at: raw
+                .created_at
+                .ok_or(error::Error::MissingValue("created_at"))?,
+            default_profile: false,
+            default_profile_image: profile_image_url.contains("default_profile_images"),
+            description: raw.description,
+            entities: UserEntities::default(),
+            favourites_count: 0,
+            follow_request_sent: None,
+            followers_count: metrics.followers_count,
+            friends_count: metrics.following_count,
+            geo_enabled: false,
+            id: raw.id,
+            is_translator: false,
+            lang: None,
+            listed_count: metrics.listed_count,
+            location: raw.location,
+            name: raw.name,
+            profile_background_color: String::new(),
+            profile_background_image_url: None,
+            profile_background_image_url_https: None,
+            profile_background_tile: None,
+            profile_banner_url: None,
+            profile_image_url: profile_image_url.clone(),
+            profile_image_url_https: profile_image_url,
+            profile_link_color: String::new(),
+            profile_sidebar_border_color: String::new(),
+            profile_sidebar_fill_color: String::new(),
+            profile_text_color: String::new(),
+            profile_use_background_image: false,
+            protected: raw
+                .protected
+                .ok_or(error::Error::MissingValue("protected"))?,
+            screen_name: raw.username,
+            show_all_inline_media: None,
+            status: None,
+            statuses_count: metrics.tweet_count,
+            time_zone: None,
+            url: raw.url,
+            utc_offset: None,
+            verified: raw
+                .verified
+                .ok_or(error::Error::MissingValue("verified"))?,
+            withheld_in_countries: None,
+            withheld_scope: None,
+        }
+        .into())
+    }
+}
+
 /// Container for URL entity information that may be paired with a user's profile.
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct UserEntities {
@@ -470,7 +545,7 @@ pub struct UserEntityDetail {
 /// ```
 #[must_use = "search iterators are lazy and do nothing unless consumed"]
 pub struct UserSearch {
-    token: auth::Token,
+    client: Client,
     query: CowStr,
     /// The current page of results being returned, starting at 1.
     pub page_num: i32,
@@ -518,14 +593,15 @@ impl UserSearch {
             .add_param("page", self.page_num.to_string())
             .add_param("count", self.page_size.to_string());
 
-        let req = get(links::users::SEARCH, &self.token, Some(&params));
-        request_with_json_response(req)
+        let req = self.client.request_get(links::users::SEARCH, Some(&params));
+        let client = self.client.clone();
+        async move { client.response_json(req).await }
     }
 
-    /// Returns a new UserSearch with the given query and tokens, with the default page size of 10.
-    fn new<S: Into<CowStr>>(query: S, token: &auth::Token) -> UserSearch {
+    /// Returns a new UserSearch with the given query and client, with the default page size of 10.
+    fn new<S: Into<CowStr>>(query: S, client: Client) -> UserSearch {
         UserSearch {
-            token: token.clone(),
+            client,
             query: query.into(),
             page_num: 1,
             page_size: 10,
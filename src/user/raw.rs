@@ -145,3 +145,77 @@ pub struct RawTwitterUser {
     /// When present, indicates whether the content being withheld is a "status" or "user".
     pub withheld_scope: Option<String>,
 }
+
+/// A user as returned from the Twitter V2 API.
+///
+/// The V2 API only returns the `id`, `name`, and `username` fields unless others are asked for.
+/// A full list of fields available on users when using the V2 API is available [here][docs].
+///
+/// [docs]: https://developer.twitter.com/en/docs/twitter-api/data-dictionary/object-model/user
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawUserV2 {
+    // Always present.
+    /// Id of this user.
+    #[serde(deserialize_with = "serde_num_string::deserialize_number_from_string")]
+    pub id: u64,
+    // Always present.
+    /// The user-entered display name.
+    pub name: String,
+    // Always present.
+    /// The screen name or handle identifying this user.
+    pub username: String,
+
+    /// When this account was created.
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The user-entered string describing their account.
+    pub description: Option<String>,
+    /// The user-entered location field from their profile.
+    pub location: Option<String>,
+    /// The ID of the tweet this user has pinned to their profile, if any.
+    #[serde(
+        default,
+        deserialize_with = "serde_num_string::deserialize_number_from_opt_string"
+    )]
+    pub pinned_tweet_id: Option<u64>,
+    /// A URL pointing to the user's avatar image.
+    pub profile_image_url: Option<String>,
+    /// Indicates whether the user is a protected account.
+    pub protected: Option<bool>,
+    /// Follower, following, tweet, and list counts for this user.
+    pub public_metrics: Option<UserPublicMetrics>,
+    /// The website link given by this user in their profile.
+    pub url: Option<String>,
+    /// Indicates whether this user is a verified account.
+    pub verified: Option<bool>,
+}
+
+impl RawUserV2 {
+    /// The V2 API requires that you specify which fields you want the server to send back.
+    ///
+    /// This function returns the list of fields that need to be present in order to turn a
+    /// [`RawUserV2`] into a [`TwitterUser`](super::TwitterUser).
+    pub const fn fields_needed_for_v1_twitter_user() -> &'static str {
+        "\
+        created_at,\
+        description,\
+        location,\
+        profile_image_url,\
+        protected,\
+        public_metrics,\
+        url,\
+        verified"
+    }
+}
+
+/// Follower, following, tweet, and list counts for a [`RawUserV2`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserPublicMetrics {
+    /// The number of followers this account has.
+    pub followers_count: i32,
+    /// The number of users this account follows.
+    pub following_count: i32,
+    /// The number of tweets (including retweets) posted by this user.
+    pub tweet_count: i32,
+    /// The number of public lists the user is a member of.
+    pub listed_count: i32,
+}
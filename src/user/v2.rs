@@ -0,0 +1,351 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Loading users through the Twitter V2 API.
+//!
+//! Like tweets, users from the V2 API only come with the fields that are asked for. The
+//! [`Lookup`] builder returned by [`lookup`], [`lookup_names`], [`show`], and [`me`] requests the
+//! fields from [`RawUserV2::fields_needed_for_v1_twitter_user`] unless others are given, so the
+//! users it loads can be turned into [`TwitterUser`]s with `TryFrom`, or with
+//! [`LookupResponse::into_users`] and [`LookupResponse::into_user`].
+//!
+//! [`Lookup`]: struct.Lookup.html
+//! [`lookup`]: fn.lookup.html
+//! [`lookup_names`]: fn.lookup_names.html
+//! [`show`]: fn.show.html
+//! [`me`]: fn.me.html
+//! [`RawUserV2::fields_needed_for_v1_twitter_user`]:
+//!     ../struct.RawUserV2.html#method.fields_needed_for_v1_twitter_user
+//! [`TwitterUser`]: ../struct.TwitterUser.html
+//! [`LookupResponse::into_users`]: ../../tweet/v2/struct.LookupResponse.html#method.into_users
+//! [`LookupResponse::into_user`]: ../../tweet/v2/struct.LookupResponse.html#method.into_user
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use egg_mode::Token;
+//! # #[tokio::main]
+//! # async fn main() {
+//! # let token: Token = unimplemented!();
+//! use egg_mode::user::v2;
+//!
+//! let users = v2::lookup_names(vec!["rustlang", "TwitterDev"])
+//!     .send(&token)
+//!     .await
+//!     .unwrap()
+//!     .response
+//!     .into_users()
+//!     .unwrap();
+//!
+//! for user in &users {
+//!     println!("@{} has {} followers", user.screen_name, user.followers_count);
+//! }
+//! # }
+//! ```
+
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+
+use crate::auth::Token;
+use crate::common::*;
+use crate::error::{Error, Result};
+use crate::links;
+use crate::tweet::v2::{join, load_lookup, LookupResponse, RawLookup, TweetField, UserField};
+use crate::tweet::RawTweetV2;
+
+use super::{RawUserV2, TwitterUser, UserID};
+
+/// The most users that can be loaded with one call to `lookup` or `lookup_names`.
+pub const MAX_LOOKUP_USERS: usize = 100;
+
+/// Loads users by their IDs, up to `MAX_LOOKUP_USERS` at a time.
+///
+/// Users that couldn't be loaded, for example because they were suspended, are left out of
+/// `data`, and Twitter's explanation of why is put in `errors` instead.
+pub fn lookup<I: IntoIterator<Item = u64>>(ids: I) -> Lookup<Vec<RawUserV2>> {
+    let ids = ids.into_iter().map(|id| id.to_string()).collect::<Vec<_>>();
+    let params = ParamList::new().add_param("ids", ids.join(","));
+    Lookup::new(links::v2::users::LOOKUP.to_string(), ids.len(), params)
+}
+
+/// Loads users by their screen names, up to `MAX_LOOKUP_USERS` at a time.
+///
+/// Users that couldn't be loaded are left out of `data`, and Twitter's explanation of why is put
+/// in `errors` instead.
+pub fn lookup_names<S, I>(names: I) -> Lookup<Vec<RawUserV2>>
+where
+    S: Into<CowStr>,
+    I: IntoIterator<Item = S>,
+{
+    let names = names.into_iter().map(Into::into).collect::<Vec<CowStr>>();
+    let params = ParamList::new().add_param("usernames", names.join(","));
+    Lookup::new(
+        links::v2::users::LOOKUP_NAMES.to_string(),
+        names.len(),
+        params,
+    )
+}
+
+/// Loads a single user by their ID or screen name.
+///
+/// If the user couldn't be loaded, Twitter's explanation of why is returned as
+/// `Error::TwitterProblem`.
+pub fn show<T: Into<UserID>>(acct: T) -> Lookup<RawUserV2> {
+    let uri = match acct.into() {
+        UserID::ID(id) => format!("{}/{}", links::v2::users::LOOKUP, id),
+        UserID::ScreenName(name) => {
+            format!("{}/{}", links::v2::users::SHOW_NAME, percent_encode(&name))
+        }
+    };
+    Lookup::new(uri, 1, ParamList::new())
+}
+
+/// Loads the user the token belongs to.
+///
+/// This needs a token for a user, so it returns `Error::InvalidRequest` if it's sent with a
+/// Bearer token.
+pub fn me() -> Lookup<RawUserV2> {
+    let mut lookup = Lookup::new(links::v2::users::ME.to_string(), 1, ParamList::new());
+    lookup.needs_user = true;
+    lookup
+}
+
+/// A request to load users from the V2 API, before being sent.
+///
+/// This is returned by `lookup`, `lookup_names`, `show`, and `me`. If no user fields are given
+/// with `user_fields`, the fields from `RawUserV2::fields_needed_for_v1_twitter_user` are
+/// requested, so the users can be turned into `TwitterUser`s.
+#[must_use = "Lookup is lazy and won't do anything unless `send` is called"]
+#[derive(Debug, Clone)]
+pub struct Lookup<T> {
+    uri: String,
+    user_count: usize,
+    params: ParamList,
+    needs_user: bool,
+    user_fields: BTreeSet<UserField>,
+    tweet_fields: BTreeSet<TweetField>,
+    pinned_tweet: bool,
+    data: PhantomData<fn() -> T>,
+}
+
+impl<T> Lookup<T> {
+    fn new(uri: String, user_count: usize, params: ParamList) -> Lookup<T> {
+        Lookup {
+            uri,
+            user_count,
+            params,
+            needs_user: false,
+            user_fields: BTreeSet::new(),
+            tweet_fields: BTreeSet::new(),
+            pinned_tweet: false,
+            data: PhantomData,
+        }
+    }
+
+    /// Adds the given fields to the ones requested for users.
+    pub fn user_fields<I: IntoIterator<Item = UserField>>(mut self, fields: I) -> Self {
+        self.user_fields.extend(fields);
+        self
+    }
+
+    /// Adds the given fields to the ones requested for pinned tweets in `includes`.
+    pub fn tweet_fields<I: IntoIterator<Item = TweetField>>(mut self, fields: I) -> Self {
+        self.tweet_fields.extend(fields);
+        self
+    }
+
+    /// Sets whether to load each user's pinned tweet into `includes`.
+    ///
+    /// If this is set and no tweet fields are given with `tweet_fields`, the fields from
+    /// `RawTweetV2::fields_needed_for_v1_raw_tweet` are requested for the pinned tweets.
+    pub fn pinned_tweet(mut self, pinned_tweet: bool) -> Self {
+        self.pinned_tweet = pinned_tweet;
+        self
+    }
+
+    /// Checks that the request makes sense before sending it.
+    ///
+    /// This returns `Error::InvalidRequest` if the wrong number of users was given, or if tweet
+    /// fields were requested without loading pinned tweets. `send` calls this before sending the
+    /// request.
+    pub fn validate(&self) -> Result<()> {
+        if self.user_count == 0 || self.user_count > MAX_LOOKUP_USERS {
+            return Err(Error::InvalidRequest(
+                "user lookup needs between 1 and 100 users",
+                Some(self.user_count.to_string()),
+            ));
+        }
+        if !self.tweet_fields.is_empty() && !self.pinned_tweet {
+            return Err(Error::InvalidRequest(
+                "tweet fields need the pinned_tweet_id expansion",
+                None,
+            ));
+        }
+        Ok(())
+    }
+
+    fn params(&self) -> ParamList {
+        let user_fields = if self.user_fields.is_empty() {
+            RawUserV2::fields_needed_for_v1_twitter_user().to_string()
+        } else {
+            join(&self.user_fields)
+        };
+        let mut params = self.params.clone().add_param("user.fields", user_fields);
+        if self.pinned_tweet {
+            let tweet_fields = if self.tweet_fields.is_empty() {
+                RawTweetV2::fields_needed_for_v1_raw_tweet().to_string()
+            } else {
+                join(&self.tweet_fields)
+            };
+            params = params
+                .add_param("expansions", "pinned_tweet_id")
+                .add_param("tweet.fields", tweet_fields);
+        }
+        params
+    }
+}
+
+impl<T: DeserializeOwned> Lookup<T> {
+    async fn load(self, client: &Client) -> Result<(Headers, Response<RawLookup<T>>)> {
+        self.validate()?;
+        if self.needs_user && matches!(client.token(), Token::Bearer(_)) {
+            return Err(Error::InvalidRequest(
+                "loading the current user needs a user token",
+                Some(self.uri),
+            ));
+        }
+        load_lookup(client, &self.uri, &self.params()).await
+    }
+}
+
+impl Lookup<Vec<RawUserV2>> {
+    /// Sends the request, loading the users and any pinned tweets.
+    pub async fn send(
+        self,
+        token: impl Into<Client>,
+    ) -> Result<Response<LookupResponse<Vec<RawUserV2>>>> {
+        let client: Client = token.into();
+        let (_, resp) = self.load(&client).await?;
+        Ok(Response::map(resp, RawLookup::into_many))
+    }
+}
+
+impl Lookup<RawUserV2> {
+    /// Sends the request, loading the user and their pinned tweet if it was asked for.
+    pub async fn send(
+        self,
+        token: impl Into<Client>,
+    ) -> Result<Response<LookupResponse<RawUserV2>>> {
+        let client: Client = token.into();
+        let (headers, resp) = self.load(&client).await?;
+        Response::try_map(resp, |raw| raw.into_one(headers))
+    }
+}
+
+impl LookupResponse<Vec<RawUserV2>> {
+    /// Converts the loaded users into `TwitterUser`s.
+    pub fn into_users(self) -> Result<Vec<TwitterUser>> {
+        self.data.into_iter().map(TwitterUser::try_from).collect()
+    }
+}
+
+impl LookupResponse<RawUserV2> {
+    /// Converts the loaded user into a `TwitterUser`.
+    pub fn into_user(self) -> Result<TwitterUser> {
+        TwitterUser::try_from(self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_validates_and_builds_params() {
+        assert!(lookup(Vec::new()).validate().is_err());
+        assert!(lookup(0..=MAX_LOOKUP_USERS as u64).validate().is_err());
+        assert!(me().validate().is_ok());
+        assert!(show("rustlang")
+            .tweet_fields(vec![TweetField::CreatedAt])
+            .validate()
+            .is_err());
+
+        let req = lookup_names(vec!["rustlang", "TwitterDev"]);
+        assert_eq!(req.uri, links::v2::users::LOOKUP_NAMES);
+        let params = req.params();
+        assert_eq!(
+            params.get("usernames").map(|v| &**v),
+            Some("rustlang,TwitterDev")
+        );
+        assert_eq!(
+            params.get("user.fields").map(|v| &**v),
+            Some(RawUserV2::fields_needed_for_v1_twitter_user())
+        );
+        assert!(params.get("expansions").is_none());
+
+        let req = show(165262228)
+            .user_fields(vec![UserField::PinnedTweetId, UserField::Verified])
+            .pinned_tweet(true);
+        assert_eq!(req.uri, "https://api.twitter.com/2/users/165262228");
+        let params = req.params();
+        assert_eq!(
+            params.get("user.fields").map(|v| &**v),
+            Some("pinned_tweet_id,verified")
+        );
+        assert_eq!(
+            params.get("expansions").map(|v| &**v),
+            Some("pinned_tweet_id")
+        );
+        assert!(params.get("tweet.fields").is_some());
+    }
+
+    #[test]
+    fn convert_user() {
+        let raw: RawLookup<RawUserV2> = serde_json::from_str(
+            r#"{
+                "data": {
+                    "id": "2244994945",
+                    "name": "Twitter Dev",
+                    "username": "TwitterDev",
+                    "created_at": "2013-12-14T04:35:55.000Z",
+                    "description": "The voice of the #TwitterDev team",
+                    "location": "127.0.0.1",
+                    "pinned_tweet_id": "1255542774432063488",
+                    "profile_image_url": "https://abs.twimg.com/default_profile_images/a.png",
+                    "protected": false,
+                    "public_metrics": {
+                        "followers_count": 513958,
+                        "following_count": 2039,
+                        "tweet_count": 3635,
+                        "listed_count": 1672
+                    },
+                    "url": "https://t.co/3ZX3TNiZCY",
+                    "verified": true
+                }
+            }"#,
+        )
+        .unwrap();
+        let resp = raw.into_one(Headers::new()).unwrap();
+        assert_eq!(resp.data.pinned_tweet_id, Some(1255542774432063488));
+
+        let user = resp.into_user().unwrap();
+        assert_eq!(user.id, 2244994945);
+        assert_eq!(user.screen_name, "TwitterDev");
+        assert_eq!(user.followers_count, 513958);
+        assert_eq!(user.friends_count, 2039);
+        assert_eq!(user.statuses_count, 3635);
+        assert!(user.verified && !user.protected && user.default_profile_image);
+        assert_eq!(user.url.as_deref(), Some("https://t.co/3ZX3TNiZCY"));
+
+        let bare: RawUserV2 =
+            serde_json::from_str(r#"{"id": "12", "name": "jack", "username": "jack"}"#).unwrap();
+        assert!(matches!(
+            TwitterUser::try_from(bare),
+            Err(Error::MissingValue(_))
+        ));
+    }
+}